@@ -914,6 +914,15 @@ class IncludeBlock(Executable):
     content: list[Block] | None = None
     """The structured content decoded from the source."""
 
+    arguments: list[CallArgument] | None = None
+    """Arguments to use when including the source."""
+
+    is_remote: bool | None = None
+    """Whether the source was resolved to a remote URL."""
+
+    content_from: str | None = None
+    """The id of an earlier include with identical content."""
+
 
 @dataclass(kw_only=True, repr=False)
 class Instruction(Executable):
@@ -1340,6 +1349,9 @@ class CallArgument(Parameter):
     programming_language: str | None = None
     """The programming language of the code."""
 
+    is_expression: bool | None = None
+    """Whether the argument is an expression to be evaluated."""
+
 
 @dataclass(kw_only=True, repr=False)
 class CallBlock(IncludeBlock):
@@ -1652,6 +1664,9 @@ class CompilationMessage(Entity):
     code_location: CodeLocation | None = None
     """The location that the error occurred."""
 
+    depth: int | None = None
+    """The include nesting depth at which the message was generated."""
+
 
 @dataclass(kw_only=True, repr=False)
 class ConstantValidator(Entity):
@@ -334,6 +334,13 @@ pub struct EncodeOptions {
     #[arg(long, help_heading = "Encoding Options")]
     recursive: bool,
 
+    /// Encode a placeholder for includes that failed to resolve
+    ///
+    /// Only supported when encoding to Markdown formats that do not support
+    /// include blocks (e.g. plain Markdown). By default, such includes are omitted.
+    #[arg(long, help_heading = "Encoding Options")]
+    include_placeholders: bool,
+
     /// Use a compact form of encoding if available
     ///
     /// Use this flag to produce a compact form of encoding if the format supports it.
@@ -399,6 +406,8 @@ impl EncodeOptions {
 
         let recurse = self.recursive.then_some(true);
 
+        let include_placeholders = self.include_placeholders.then_some(true);
+
         let from_path = input.map(PathBuf::from);
 
         let embed_media = self.embed_media.then_some(true);
@@ -442,6 +451,7 @@ impl EncodeOptions {
             embed_supplements,
             extract_supplements,
             recurse,
+            include_placeholders,
             from_path,
             strip_scopes: strip_options.strip_scopes,
             strip_types: strip_options.strip_types,
@@ -4,8 +4,9 @@ use stencila_codec::{
     DecodeInfo, DecodeOptions,
     eyre::{Result, bail},
     stencila_schema::{
-        Article, Block, ImageObject, Inline, Link, Node, StyledBlock, StyledInline,
-        shortcuts::{em, h1, h2, h3, h4, h5, h6, p, sec, stg, stk, sub, sup, t, u},
+        Article, Block, Heading, ImageObject, Inline, Link, Node, Paragraph, Section, StyledBlock,
+        StyledInline,
+        shortcuts::{em, p, stg, stk, sub, sup, t, u},
     },
 };
 
@@ -83,12 +84,24 @@ fn decode_blocks(parser: &Parser, nodes: &RawChildren) -> Vec<Block> {
     blocks
 }
 
+/// Decode the `id` attribute of an element
+///
+/// Retained so that content can be referenced by id (e.g. using the fragment of a URL).
+fn decode_id(tag: &HTMLTag) -> Option<String> {
+    tag.attributes()
+        .id()
+        .map(|id| id.as_utf8_str().trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
 /// Decode a <div> element into either a [`StyledBlock`] or [`Section`] node
 fn decode_div(parser: &Parser, tag: &HTMLTag) -> Block {
     let attrs = tag.attributes();
+    let id = decode_id(tag);
     let content = decode_blocks(parser, tag.children().top());
     if let Some(classes) = attrs.class() {
         Block::StyledBlock(StyledBlock {
+            id,
             code: classes.as_utf8_str().into(),
             style_language: Some("tailwind".to_string()),
             content,
@@ -96,32 +109,43 @@ fn decode_div(parser: &Parser, tag: &HTMLTag) -> Block {
         })
     } else if let Some(style) = attrs.get("style").flatten() {
         Block::StyledBlock(StyledBlock {
+            id,
             code: style.as_utf8_str().into(),
             style_language: Some("css".to_string()),
             content,
             ..Default::default()
         })
     } else {
-        sec(content)
+        Block::Section(Section {
+            id,
+            ..Section::new(content)
+        })
     }
 }
 
 /// Decode a <h1>, <h2>,... element into a [`Heading`]
 fn decode_h(parser: &Parser, tag: &HTMLTag, name: &str) -> Block {
+    let level = match name {
+        "h1" => 1,
+        "h2" => 2,
+        "h3" => 3,
+        "h4" => 4,
+        "h5" => 5,
+        _ => 6,
+    };
     let content = decode_inlines(parser, tag.children().top());
-    match name {
-        "h1" => h1(content),
-        "h2" => h2(content),
-        "h3" => h3(content),
-        "h4" => h4(content),
-        "h5" => h5(content),
-        _ => h6(content),
-    }
+    Block::Heading(Heading {
+        id: decode_id(tag),
+        ..Heading::new(level, content)
+    })
 }
 
 /// Decode a <p> element into a [`Paragraph`]
 fn decode_p(parser: &Parser, tag: &HTMLTag) -> Block {
-    p(decode_inlines(parser, tag.children().top()))
+    Block::Paragraph(Paragraph {
+        id: decode_id(tag),
+        ..Paragraph::new(decode_inlines(parser, tag.children().top()))
+    })
 }
 
 /// Decode inline elements
@@ -116,12 +116,13 @@ fn node_from_notebook(notebook: Notebook) -> Result<(Node, Losses)> {
             )?),
 
             Cell::Code {
+                id,
                 source,
                 outputs,
                 metadata,
                 execution_count,
-                ..
             } => content.push(code_chunk_from_code_cell(
+                id,
                 source,
                 outputs,
                 metadata,
@@ -288,6 +289,7 @@ fn blocks_from_markdown_cell(
 
 /// Convert a Jupyter code cell to a Stencila [`CodeChunk`]
 fn code_chunk_from_code_cell(
+    id: CellId,
     source: Vec<String>,
     outputs: Vec<Output>,
     metadata: CellMetadata,
@@ -349,6 +351,7 @@ fn code_chunk_from_code_cell(
     }
 
     Block::CodeChunk(CodeChunk {
+        id: cell_id_to_id(id),
         code: source.join("\n").into(),
         programming_language,
         label_type,
@@ -418,8 +421,14 @@ fn code_chunk_to_code_cell(code_chunk: &CodeChunk) -> Result<Cell> {
         .map(|output| node_to_output(output, execution_count))
         .collect();
 
+    // Use the id of the code chunk, if any, so that cell ids are preserved
+    let id = match code_chunk.id.as_ref().and_then(|id| CellId::new(id).ok()) {
+        Some(id) => id,
+        None => node_id_to_cell_id(code_chunk.node_id())?,
+    };
+
     Ok(Cell::Code {
-        id: node_id_to_cell_id(code_chunk.node_id())?,
+        id,
         metadata,
         execution_count: code_chunk.options.execution_count.map(|count| count as i32),
         source: vec![code_chunk.code.to_string()],
@@ -729,6 +738,15 @@ fn node_id_to_cell_id(node_id: NodeId) -> Result<CellId> {
     CellId::new(&node_id.to_string()).map_err(|error| eyre!(error))
 }
 
+/// Convert a Jupyter [`CellId`] to the `id` of a Stencila node
+///
+/// Cell ids that are Stencila node ids (i.e. those generated when encoding
+/// a node without an `id`) are ignored since they are not stable.
+fn cell_id_to_id(cell_id: CellId) -> Option<String> {
+    let id = cell_id.to_string();
+    NodeId::from_str(&id).is_err().then_some(id)
+}
+
 /// Create a default Jupyter [`CellMetadata`]
 fn cell_metadata_default() -> CellMetadata {
     CellMetadata {
@@ -1,3 +1,17 @@
+/// Mapping for ordinary text-mode escapes.
+const MAP: [(char, &str); 10] = [
+    ('\\', r"\textbackslash{}"),
+    ('{', r"\{"),
+    ('}', r"\}"),
+    ('#', r"\#"),
+    ('$', r"\$"),
+    ('%', r"\%"),
+    ('&', r"\&"),
+    ('~', r"\textasciitilde{}"),
+    ('_', r"\_"),
+    ('^', r"\textasciicircum{}"),
+];
+
 /// Escape LaTeX-special characters **except** inside real math.
 ///
 /// Heuristics
@@ -11,20 +25,6 @@
 /// * When we do enter math we remember whether we opened with `$` or
 ///   `$$`, so we don’t exit until we see the *same* delimiter.
 pub fn escape_latex(input: &str) -> String {
-    // Mapping for ordinary text-mode escapes.
-    const MAP: [(char, &str); 10] = [
-        ('\\', r"\textbackslash{}"),
-        ('{', r"\{"),
-        ('}', r"\}"),
-        ('#', r"\#"),
-        ('$', r"\$"),
-        ('%', r"\%"),
-        ('&', r"\&"),
-        ('~', r"\textasciitilde{}"),
-        ('_', r"\_"),
-        ('^', r"\textasciicircum{}"),
-    ];
-
     let mut out = String::with_capacity(input.len());
     let char_positions: Vec<usize> = input.char_indices().map(|(i, _)| i).collect();
     let chars: Vec<char> = input.chars().collect();
@@ -107,9 +107,28 @@ pub fn escape_latex(input: &str) -> String {
     out
 }
 
+/// Escape all LaTeX-special characters, with no special handling of math
+///
+/// For literal text such as file paths and URLs, in which `$` is never math.
+/// Also escapes `|` which is otherwise rendered incorrectly in some fonts and
+/// which is the usual delimiter for `\verb`.
+pub fn escape_latex_literal(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        if let Some((_, repl)) = MAP.iter().find(|(ch, _)| *ch == c) {
+            out.push_str(repl);
+        } else if c == '|' {
+            out.push_str(r"\textbar{}");
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
-    use super::escape_latex;
+    use super::{escape_latex, escape_latex_literal};
 
     #[test]
     fn leaves_math_alone() {
@@ -131,4 +150,11 @@ mod tests {
         let want = r"Emoji: 😊 \& price 5€";
         assert_eq!(escape_latex(raw), want);
     }
+
+    #[test]
+    fn escapes_literals() {
+        let raw = r"my dir/file #1 50% $5|$6.tex";
+        let want = r"my dir/file \#1 50\% \$5\textbar{}\$6.tex";
+        assert_eq!(escape_latex_literal(raw), want);
+    }
 }
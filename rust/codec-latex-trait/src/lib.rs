@@ -21,6 +21,7 @@ pub use stencila_codec_latex_derive::LatexCodec;
 
 mod escape;
 use escape::escape_latex;
+pub use escape::escape_latex_literal;
 
 /// Encode a node that implements `LatexCodec` to Latex
 ///
@@ -58,6 +58,9 @@ pub struct MarkdownEncodeContext {
     /// Encode the outputs, rather than the source, of executable nodes
     pub render: bool,
 
+    /// Encode a placeholder for includes that failed to resolve, rather than omitting them
+    pub include_placeholders: bool,
+
     /// The encoded Markdown content
     pub content: String,
 
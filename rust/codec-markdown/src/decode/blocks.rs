@@ -1,4 +1,4 @@
-use std::{collections::HashMap, str::FromStr};
+use std::str::FromStr;
 
 use indexmap::IndexMap;
 use inflector::Inflector;
@@ -10,9 +10,9 @@ use serde_json::json;
 use winnow::{
     LocatingSlice as Located, ModalResult, Parser,
     ascii::{Caseless, multispace0, multispace1, space0},
-    combinator::{alt, delimited, eof, opt, preceded, separated, terminated},
+    combinator::{alt, delimited, eof, not, opt, peek, preceded, repeat, separated, terminated},
     stream::AsChar,
-    token::{take_till, take_until, take_while},
+    token::{any, take_till, take_until, take_while},
 };
 
 use stencila_codec::{
@@ -614,19 +614,80 @@ fn call_block(input: &mut Located<&str>) -> ModalResult<Block> {
     .parse_next(input)
 }
 
+/// Parse an argument of an [`IncludeBlock`] e.g. `--if={{draft}}`, `--level=2`
+fn include_arg(input: &mut Located<&str>) -> ModalResult<CallArgument> {
+    preceded("--", (terminated(include_arg_name, "="), include_arg_value))
+        .map(|(name, arg)| CallArgument {
+            name: name.into(),
+            ..arg
+        })
+        .parse_next(input)
+}
+
+/// Parse the name of an argument of an [`IncludeBlock`]
+///
+/// Unlike other names, may contain hyphens after the first character (e.g. `latex-float`)
+/// for arguments that configure the include itself rather than being set as variables.
+fn include_arg_name<'s>(input: &mut Located<&'s str>) -> ModalResult<&'s str> {
+    (
+        name,
+        take_while(0.., |c: char| {
+            c.is_ascii_alphanumeric() || c == '_' || c == '-'
+        }),
+    )
+        .take()
+        .parse_next(input)
+}
+
+/// Parse the value of an argument of an [`IncludeBlock`]
+///
+/// Code within double braces (or backticks) is an expression to be evaluated.
+/// Anything else (including quoted strings containing braces) is a literal value.
+/// Values that only start with a primitive (e.g. `60s`) are treated as strings.
+fn include_arg_value(input: &mut Located<&str>) -> ModalResult<CallArgument> {
+    alt((
+        alt((
+            delimited("{{", take_until(0.., "}}"), "}}").map(|code: &str| code.trim()),
+            delimited('`', take_until(0.., "`"), '`'),
+        ))
+        .map(|code: &str| CallArgument {
+            code: code.into(),
+            is_expression: Some(true),
+            ..Default::default()
+        }),
+        alt((
+            terminated(primitive_node, peek(alt((multispace1, "{", eof)))),
+            take_while(1.., |c: char| !c.is_whitespace() && c != '{')
+                .map(|value: &str| Node::String(value.into())),
+        ))
+        .map(|value| CallArgument {
+            value: Some(Box::new(value)),
+            ..Default::default()
+        }),
+    ))
+    .parse_next(input)
+}
+
 /// Parse an [`IncludeBlock`] node
 fn include_block(input: &mut Located<&str>) -> ModalResult<Block> {
     preceded(
         ("include", multispace0),
-        (take_while(0.., |c| c != '{'), opt(attrs)),
+        (
+            repeat(0.., (not(alt((" --", "{"))), any))
+                .map(|()| ())
+                .take(),
+            repeat(0.., preceded(multispace1, include_arg)),
+            preceded(multispace0, opt(attrs)),
+        ),
     )
-    .map(|(source, attrs)| {
+    .map(|(source, arguments, attrs): (&str, Vec<CallArgument>, _)| {
         let mut options: IndexMap<&str, _> = attrs.unwrap_or_default().into_iter().collect();
 
         Block::IncludeBlock(IncludeBlock {
             source: source.trim().to_string(),
             media_type: options.swap_remove("format").flatten().map(node_to_string),
             select: options.swap_remove("select").flatten().map(node_to_string),
+            arguments: (!arguments.is_empty()).then_some(arguments),
             execution_mode: execution_mode_from_options(options),
             ..Default::default()
         })
@@ -1505,7 +1566,7 @@ fn myst_to_block(code: &mdast::Code, context: &mut Context) -> Option<Block> {
     let args = code.meta.as_deref();
 
     // Extract directive options and separate them from the value of the directive
-    let mut options: HashMap<&str, &str> = HashMap::new();
+    let mut options: IndexMap<&str, &str> = IndexMap::new();
     let mut value = String::new();
     for line in code.value.lines() {
         if line.starts_with(':') && line.chars().filter(|&c| c == ':').count() > 1 {
@@ -1623,13 +1684,34 @@ fn myst_to_block(code: &mdast::Code, context: &mut Context) -> Option<Block> {
                 ..Default::default()
             })
         }
-        "include" => Block::IncludeBlock(IncludeBlock {
-            source: args.unwrap_or_default().to_string(),
-            execution_mode: options.get("mode").and_then(|mode| mode.parse().ok()),
-            media_type: options.get("format").map(|format| format.to_string()),
-            select: options.get("select").map(|select| select.to_string()),
-            ..Default::default()
-        }),
+        "include" => {
+            // Any options other than those for properties are arguments
+            let arguments: Vec<CallArgument> = options
+                .iter()
+                .filter(|(name, ..)| !matches!(**name, "mode" | "format" | "select"))
+                .map(|(name, value)| {
+                    let arg = terminated(include_arg_value, eof)
+                        .parse_next(&mut Located::new(*value))
+                        .unwrap_or_else(|_| CallArgument {
+                            value: Some(Box::new(Node::String(value.to_string()))),
+                            ..Default::default()
+                        });
+                    CallArgument {
+                        name: name.to_string(),
+                        ..arg
+                    }
+                })
+                .collect();
+
+            Block::IncludeBlock(IncludeBlock {
+                source: args.unwrap_or_default().to_string(),
+                execution_mode: options.get("mode").and_then(|mode| mode.parse().ok()),
+                media_type: options.get("format").map(|format| format.to_string()),
+                select: options.get("select").map(|select| select.to_string()),
+                arguments: (!arguments.is_empty()).then_some(arguments),
+                ..Default::default()
+            })
+        }
         "create" | "edit" | "fix" | "describe" => {
             let prompt = options
                 .get("prompt")
@@ -2050,6 +2132,115 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_include_block() {
+        assert_eq!(
+            include_block(&mut Located::new("include file.md {format=md}")).unwrap(),
+            Block::IncludeBlock(IncludeBlock {
+                source: "file.md".to_string(),
+                media_type: Some("md".to_string()),
+                ..Default::default()
+            })
+        );
+
+        // With args
+        assert_eq!(
+            include_block(&mut Located::new("include file.md --if={{draft}}")).unwrap(),
+            Block::IncludeBlock(IncludeBlock {
+                source: "file.md".to_string(),
+                arguments: Some(vec![CallArgument {
+                    name: "if".to_string(),
+                    code: "draft".into(),
+                    is_expression: Some(true),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            include_block(&mut Located::new(
+                "include file.md --if={{ x > 1 }} --a=1 --b=`var` {select=h1}"
+            ))
+            .unwrap(),
+            Block::IncludeBlock(IncludeBlock {
+                source: "file.md".to_string(),
+                select: Some("h1".to_string()),
+                arguments: Some(vec![
+                    CallArgument {
+                        name: "if".to_string(),
+                        code: "x > 1".into(),
+                        is_expression: Some(true),
+                        ..Default::default()
+                    },
+                    CallArgument {
+                        name: "a".to_string(),
+                        value: Some(Box::new(Node::Integer(1))),
+                        ..Default::default()
+                    },
+                    CallArgument {
+                        name: "b".to_string(),
+                        code: "var".into(),
+                        is_expression: Some(true),
+                        ..Default::default()
+                    }
+                ]),
+                ..Default::default()
+            })
+        );
+
+        // Values that only start with a number are strings
+        assert_eq!(
+            include_block(&mut Located::new(
+                "include file.md --timeout=60s --retries=5"
+            ))
+            .unwrap(),
+            Block::IncludeBlock(IncludeBlock {
+                source: "file.md".to_string(),
+                arguments: Some(vec![
+                    CallArgument {
+                        name: "timeout".to_string(),
+                        value: Some(Box::new(Node::String("60s".to_string()))),
+                        ..Default::default()
+                    },
+                    CallArgument {
+                        name: "retries".to_string(),
+                        value: Some(Box::new(Node::Integer(5))),
+                        ..Default::default()
+                    }
+                ]),
+                ..Default::default()
+            })
+        );
+
+        // Argument names may contain hyphens
+        assert_eq!(
+            include_block(&mut Located::new("include file.md --latex-float=figure")).unwrap(),
+            Block::IncludeBlock(IncludeBlock {
+                source: "file.md".to_string(),
+                arguments: Some(vec![CallArgument {
+                    name: "latex-float".to_string(),
+                    value: Some(Box::new(Node::String("figure".to_string()))),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            })
+        );
+
+        // Quoted literals are not expressions, even if they contain braces
+        assert_eq!(
+            include_block(&mut Located::new(r#"include file.md --a="{{b}}""#)).unwrap(),
+            Block::IncludeBlock(IncludeBlock {
+                source: "file.md".to_string(),
+                arguments: Some(vec![CallArgument {
+                    name: "a".to_string(),
+                    value: Some(Box::new(Node::String("{{b}}".to_string()))),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            })
+        );
+    }
+
     #[test]
     fn test_call_block() {
         // Incomplete (e.g. partially written in editor)
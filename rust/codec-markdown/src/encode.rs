@@ -6,6 +6,7 @@ pub fn encode(node: &Node, options: Option<EncodeOptions>) -> Result<(String, En
     let options = options.unwrap_or_default();
 
     let mut context = MarkdownEncodeContext::new(options.format, options.render);
+    context.include_placeholders = options.include_placeholders.unwrap_or_default();
 
     node.to_markdown(&mut context);
     if context.content.ends_with("\n\n") {
@@ -0,0 +1,108 @@
+use pretty_assertions::assert_eq;
+use stencila_codec::{
+    Codec, DecodeOptions, EncodeOptions,
+    eyre::{Result, bail},
+    stencila_format::Format,
+    stencila_schema::{Article, Block, ExecutionMode, IncludeBlock, Inline, Node, Paragraph, Text},
+};
+use stencila_codec_markdown::MarkdownCodec;
+
+/// Test that the `mode`, `format` and `select` options of MyST include
+/// directives are preserved when round-tripping through MyST
+#[tokio::test]
+async fn myst_options_roundtrip() -> Result<()> {
+    let codec = MarkdownCodec {};
+
+    let include = IncludeBlock {
+        execution_mode: Some(ExecutionMode::Always),
+        media_type: Some("text/markdown".into()),
+        select: Some("after:## Notes".into()),
+        ..IncludeBlock::new("notes/part one.md".into())
+    }
+    .with_argument("level", Node::Integer(2));
+    let article = Node::Article(Article::new(vec![Block::IncludeBlock(include.clone())]));
+
+    let (myst, ..) = codec
+        .to_string(
+            &article,
+            Some(EncodeOptions {
+                format: Some(Format::Myst),
+                ..Default::default()
+            }),
+        )
+        .await?;
+
+    let (node, ..) = codec
+        .from_str(
+            &myst,
+            Some(DecodeOptions {
+                format: Some(Format::Myst),
+                ..Default::default()
+            }),
+        )
+        .await?;
+
+    let Node::Article(Article { content, .. }) = node else {
+        bail!("Expected an article")
+    };
+    let [Block::IncludeBlock(decoded)] = content.as_slice() else {
+        bail!("Expected a single include block, got:\n{myst}")
+    };
+
+    assert_eq!(decoded.source, include.source);
+    assert_eq!(decoded.execution_mode, include.execution_mode);
+    assert_eq!(decoded.media_type, include.media_type);
+    assert_eq!(decoded.select, include.select);
+    assert_eq!(
+        decoded
+            .arguments
+            .iter()
+            .flatten()
+            .map(|arg| (arg.name.clone(), arg.value.clone()))
+            .collect::<Vec<_>>(),
+        vec![("level".to_string(), Some(Box::new(Node::Integer(2))))]
+    );
+
+    Ok(())
+}
+
+/// Encode a node to a format, returning the content and its losses
+async fn encode_with_losses(node: &Node, format: Format) -> Result<(String, serde_json::Value)> {
+    let (content, info) = MarkdownCodec {}
+        .to_string(
+            node,
+            Some(EncodeOptions {
+                format: Some(format),
+                ..Default::default()
+            }),
+        )
+        .await?;
+
+    Ok((content, serde_json::to_value(&info.losses)?))
+}
+
+/// Test that encoding an include to a format that can not represent it (and
+/// so flattens it to its content) records the include as lost
+#[tokio::test]
+async fn flattened_losses() -> Result<()> {
+    let include = IncludeBlock {
+        select: Some("Figure".into()),
+        content: Some(vec![Block::Paragraph(Paragraph::new(vec![Inline::Text(
+            Text::from("Included"),
+        )]))]),
+        ..IncludeBlock::new("part.md".into())
+    };
+    let article = Node::Article(Article::new(vec![Block::IncludeBlock(include)]));
+
+    let (md, losses) = encode_with_losses(&article, Format::Markdown).await?;
+    assert_eq!(md.trim(), "Included");
+    assert_eq!(losses["IncludeBlock"], 1);
+    assert_eq!(losses["IncludeBlock.source"], 1);
+    assert_eq!(losses["IncludeBlock.select"], 1);
+
+    let (.., losses) = encode_with_losses(&article, Format::Smd).await?;
+    assert!(losses.get("IncludeBlock").is_none());
+    assert!(losses.get("IncludeBlock.select").is_none());
+
+    Ok(())
+}
@@ -654,6 +654,13 @@ pub struct EncodeOptions {
     /// Recursively encode the content of `IncludeBlock`s to their source file
     pub recurse: Option<bool>,
 
+    /// Encode a visible placeholder for `IncludeBlock`s that failed to resolve
+    ///
+    /// Only supported by Markdown formats that do not support include blocks (e.g. plain
+    /// Markdown), for which the content of an include is encoded in place of the include
+    /// itself. By default, includes without content are silently omitted.
+    pub include_placeholders: Option<bool>,
+
     /// The base URL of the file being encoded to
     ///
     /// Used by some codecs when it is necessary to create absolute URLs.
@@ -763,6 +763,7 @@ impl Document {
                 should_lint: true,
                 should_format,
                 should_fix,
+                ..Default::default()
             },
         })
         .await
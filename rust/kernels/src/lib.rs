@@ -213,6 +213,11 @@ impl Kernels {
         Self::new(bounds, &path, None)
     }
 
+    /// Get the execution bounds of the kernels
+    pub fn bounds(&self) -> ExecutionBounds {
+        self.bounds
+    }
+
     /// A task to handle requests from kernels for variables in other contexts
     async fn variable_requests_task(
         instances: KernelInstances,
@@ -495,6 +500,23 @@ impl Kernels {
         Ok(None)
     }
 
+    /// List the names of the variables in all the kernels
+    pub async fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in self.instances.read().await.iter() {
+            let mut instance = entry.instance.lock().await;
+            names.extend(
+                instance
+                    .list()
+                    .await?
+                    .into_iter()
+                    .map(|variable| variable.name),
+            );
+        }
+
+        Ok(names)
+    }
+
     /// Set a variable in the first kernel instance
     pub async fn set(&mut self, name: &str, value: &Node, language: Option<&str>) -> Result<()> {
         let instance = match language {
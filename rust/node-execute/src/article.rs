@@ -4,7 +4,7 @@ use stencila_codec_biblio::encode::render_citations;
 use stencila_codec_markdown::decode_frontmatter;
 use stencila_schema::{Article, NodeSlot, NodeType, diff};
 
-use crate::{HeadingInfo, interrupt_impl, prelude::*};
+use crate::{HeadingInfo, include_block::add_bibliographies, interrupt_impl, prelude::*};
 
 impl Executable for Article {
     #[tracing::instrument(skip_all)]
@@ -43,6 +43,9 @@ impl Executable for Article {
             }
         }
 
+        // Add references from any included bibliography sources (e.g. `refs.bib`)
+        add_bibliographies(&self.content, executor).await;
+
         // Clear the executor's citations list before waling over content so
         // that citations and citation groups can add themselves to it
         executor.citations.clear();
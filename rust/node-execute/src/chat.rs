@@ -389,6 +389,7 @@ impl Executable for Chat {
                             should_lint: true,
                             should_format: true,
                             should_fix: true,
+                            ..Default::default()
                         });
 
                         let mut content = content.clone();
@@ -315,17 +315,7 @@ async fn execute_if_block_clause(clause: &mut IfBlockClause, executor: &mut Exec
         };
 
         // Determine truthy-ness of the code's output value
-        let truthy = match &value {
-            Node::Null(..) => false,
-            Node::Boolean(bool) => *bool,
-            Node::Integer(int) => *int > 0,
-            Node::UnsignedInteger(uint) => *uint > 0,
-            Node::Number(number) => *number > 0.,
-            Node::String(string) => !string.is_empty(),
-            Node::Array(array) => !array.is_empty(),
-            Node::Object(object) => !object.is_empty(),
-            _ => true,
-        };
+        let truthy = is_truthy(&value);
 
         // Execute nodes in `content` if truthy
         if truthy {
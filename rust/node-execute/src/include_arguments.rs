@@ -0,0 +1,566 @@
+//! Evaluation and parsing of the arguments of includes
+//!
+//! Arguments with reserved names (see [`RESERVED_ARGUMENTS`]) configure how the source of an
+//! include is got, decoded and executed, rather than being set as variables for its content.
+//! This module has the names of those arguments, and the functions for evaluating them and
+//! parsing their values, so that the `Executable` implementation of `IncludeBlock` only needs
+//! to orchestrate them. Other arguments are set as variables for the content of the include
+//! (see [`set_arguments`]). When [`crate::Executor::set_include_restore_arguments`] is enabled,
+//! those variables are restored to their previous values, or removed, once the content has
+//! been executed (see [`ArgumentsScope`]) so that they do not leak into the including document.
+
+use std::time::Duration;
+
+use encoding_rs::Encoding;
+use eyre::{Result, bail};
+
+use stencila_codec_text_trait::to_text;
+use stencila_schema::{CallArgument, CompilationMessage, IncludeBlock};
+
+use crate::{
+    include_checksum::SHA256_ARGUMENT,
+    include_decode::{
+        DECODE_ARGUMENTS, DECODE_COARSE_ARGUMENT, DECODE_CODEC_ARGUMENT, DECODE_STRICT_ARGUMENT,
+        DecodeOverrides,
+    },
+    include_encoding::encoding_for_label,
+    include_error::IncludeError,
+    include_export::EXPORT_ARGUMENT,
+    include_isolate::ISOLATE_ARGUMENT,
+    include_pipes::Pipeline,
+    include_version::VERSION_ARGUMENT,
+    prelude::*,
+};
+
+/// The name of the argument used to override the timeout for fetching a remote source
+pub(crate) const TIMEOUT_ARGUMENT: &str = "timeout";
+
+/// The name of the argument used to override the number of retries for fetching a remote source
+pub(crate) const RETRIES_ARGUMENT: &str = "retries";
+
+/// The name of the argument used to include a source as an attachment, rather than decoding it
+pub(crate) const RAW_ARGUMENT: &str = "raw";
+
+/// The name of the argument used to remove the outputs of code chunks (e.g. of notebook cells)
+pub(crate) const STRIP_OUTPUTS_ARGUMENT: &str = "strip-outputs";
+
+/// The name of the argument used to limit the number of rows decoded from a JSON Lines source
+pub(crate) const LIMIT_ARGUMENT: &str = "limit";
+
+/// The name of the argument used to specify the character encoding of a text source
+pub(crate) const ENCODING_ARGUMENT: &str = "encoding";
+
+/// The name of the argument used to continue the numbering of lists from the host document
+pub(crate) const CONTINUE_LISTS_ARGUMENT: &str = "continue-lists";
+
+/// The names of arguments that configure the include itself, rather than
+/// being set as variables for its content
+///
+/// Should be kept in sync with the `$comment` of `arguments` in `schema/IncludeBlock.yaml`.
+pub(crate) const RESERVED_ARGUMENTS: [&str; 19] = [
+    IncludeBlock::IF_ARGUMENT,
+    TIMEOUT_ARGUMENT,
+    RETRIES_ARGUMENT,
+    RAW_ARGUMENT,
+    STRIP_OUTPUTS_ARGUMENT,
+    LIMIT_ARGUMENT,
+    ENCODING_ARGUMENT,
+    CONTINUE_LISTS_ARGUMENT,
+    IncludeBlock::LATEX_FLOAT_ARGUMENT,
+    IncludeBlock::LATEX_CAPTION_ARGUMENT,
+    IncludeBlock::MYST_TABS_ARGUMENT,
+    IncludeBlock::RENDER_ARGUMENT,
+    DECODE_CODEC_ARGUMENT,
+    DECODE_STRICT_ARGUMENT,
+    DECODE_COARSE_ARGUMENT,
+    ISOLATE_ARGUMENT,
+    VERSION_ARGUMENT,
+    SHA256_ARGUMENT,
+    EXPORT_ARGUMENT,
+];
+
+/// Evaluate an argument of an include
+///
+/// If the argument is not an expression then its `value` is used as is. Otherwise, its
+/// code is evaluated (see [`evaluate_code`]). The code may be followed by a pipeline of
+/// filters which are applied to its value (e.g. `{{title | lower | replace(' ', '-')}}`).
+pub(crate) async fn evaluate_argument(arg: &CallArgument, executor: &mut Executor) -> Result<Node> {
+    if !arg.is_expression.unwrap_or_default() {
+        return Ok(arg.value.as_deref().cloned().unwrap_or(Node::Null(Null)));
+    }
+
+    let code = arg.code.trim();
+    if code.is_empty() {
+        bail!("Argument `{}` is an expression but has no code", arg.name)
+    }
+
+    let lang = &arg.programming_language;
+    let Some(pipeline) = Pipeline::parse(code)? else {
+        return evaluate_code(code, lang, executor).await;
+    };
+
+    let value = match evaluate_code(&pipeline.expression, lang, executor).await {
+        Ok(value) => value,
+        Err(error) => match pipeline.fallback() {
+            Some(fallback) => fallback.clone(),
+            None => return Err(error),
+        },
+    };
+
+    pipeline.apply(value)
+}
+
+/// Evaluate an expression used by an include
+///
+/// If the code is a variable name then an attempt is made to get that variable
+/// directly from the kernels before falling back to evaluating the code.
+pub(crate) async fn evaluate_code(
+    code: &str,
+    programming_language: &Option<String>,
+    executor: &mut Executor,
+) -> Result<Node> {
+    if programming_language.is_none()
+        && is_valid_variable_name(code)
+        && let Some(node) = executor.kernels.read().await.get(code).await?
+    {
+        return Ok(node);
+    }
+
+    let lang = executor.programming_language(programming_language);
+    let (value, messages, ..) = executor
+        .kernels
+        .write()
+        .await
+        .evaluate(code, lang.as_deref())
+        .await?;
+
+    if let Some(message) = messages
+        .iter()
+        .find(|message| matches!(message.level, MessageLevel::Error | MessageLevel::Exception))
+    {
+        bail!("{}", message.message)
+    }
+
+    Ok(value)
+}
+
+/// Whether an include has an argument with a name
+pub(crate) fn has_argument(arguments: &Option<Vec<CallArgument>>, name: &str) -> bool {
+    arguments.iter().flatten().any(|arg| arg.name == name)
+}
+
+/// Get the arguments of an include that can affect its content, normalized for comparison
+///
+/// Arguments that only affect whether, or how, the source is fetched (i.e. `if`, `timeout`,
+/// and `retries`) are excluded. Each of the remaining arguments is paired with its trimmed
+/// code, or if it has none, the text of its value, and they are sorted by name. Used to
+/// distinguish the variants of cached content.
+pub(crate) fn content_arguments(arguments: &Option<Vec<CallArgument>>) -> Vec<(String, String)> {
+    let mut normalized: Vec<(String, String)> = arguments
+        .iter()
+        .flatten()
+        .filter(|arg| {
+            ![
+                IncludeBlock::IF_ARGUMENT,
+                TIMEOUT_ARGUMENT,
+                RETRIES_ARGUMENT,
+            ]
+            .contains(&arg.name.as_str())
+        })
+        .map(|arg| {
+            let code = arg.code.trim();
+            let value = if code.is_empty() {
+                arg.value.as_deref().map(to_text).unwrap_or_default()
+            } else {
+                code.to_string()
+            };
+            (arg.name.clone(), value)
+        })
+        .collect();
+    normalized.sort();
+    normalized
+}
+
+/// Determine whether the source of an include should be included
+///
+/// Returns `true` if there is no `if` argument, or if it evaluates to a truthy value.
+/// If the argument fails to evaluate then a warning message is returned and whether
+/// the source is included depends upon the `exclude_on_condition_error` compile option.
+pub(crate) async fn should_include(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+) -> (bool, Option<CompilationMessage>) {
+    let Some(arg) = arguments
+        .iter()
+        .flatten()
+        .find(|arg| arg.name == IncludeBlock::IF_ARGUMENT)
+    else {
+        return (true, None);
+    };
+
+    match evaluate_argument(arg, executor).await {
+        Ok(value) => (is_truthy(&value), None),
+        Err(error) => {
+            let exclude = executor
+                .compile_options
+                .as_ref()
+                .map(|opts| opts.exclude_on_condition_error)
+                .unwrap_or_default();
+
+            let message = CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Unable to evaluate `if` condition `{}` so {} source: {error}",
+                    arg.code.trim(),
+                    if exclude { "excluding" } else { "including" }
+                ),
+            );
+
+            (!exclude, Some(message))
+        }
+    }
+}
+
+/// Set the arguments of an include as variables in the kernels
+///
+/// Arguments are evaluated and set in order, and the kernels lock is released
+/// after each, so that the expressions of later arguments can use the values
+/// of earlier ones (e.g. `--base=10 --scaled={{base*2}}`). Reserved arguments
+/// (e.g. `if`, `timeout`) are not set. Expression arguments with unbalanced braces
+/// (e.g. `{{site`) are not set either, and a warning is returned for each, rather than the
+/// confusing error, or missing variable, that would otherwise result. Literal arguments are
+/// always set, even if their value contains braces. Returns an error message for each
+/// argument that could not be set.
+pub(crate) async fn set_arguments(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+) -> Vec<CompilationMessage> {
+    let mut messages = Vec::new();
+    for arg in arguments
+        .iter()
+        .flatten()
+        .filter(|arg| !RESERVED_ARGUMENTS.contains(&arg.name.as_str()))
+    {
+        if let Some(problem) = arg.unbalanced_braces() {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Argument `{}` has unbalanced braces ({problem}) so was not set",
+                    arg.name
+                ),
+            ));
+            continue;
+        }
+
+        let lang = executor.programming_language(&arg.programming_language);
+        let result = match evaluate_argument(arg, executor).await {
+            Ok(value) => {
+                executor
+                    .kernels
+                    .write()
+                    .await
+                    .set(&arg.name, &value, lang.as_deref())
+                    .await
+            }
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Error,
+                format!("Unable to set argument `{}`: {error}", arg.name),
+            ));
+        }
+    }
+    messages
+}
+
+/// A scope within which the arguments of an include are set as variables
+pub(crate) struct ArgumentsScope {
+    /// The name of each variable and its value before the scope was entered, if any
+    previous: Vec<(String, Option<Node>)>,
+}
+
+impl ArgumentsScope {
+    /// Snapshot the values of the variables with the names of arguments
+    ///
+    /// Should be called before the arguments are set.
+    pub async fn enter(names: Vec<String>, executor: &Executor) -> Self {
+        let kernels = executor.kernels.read().await;
+
+        let mut previous = Vec::with_capacity(names.len());
+        for name in names {
+            let value = kernels.get(&name).await.ok().flatten();
+            previous.push((name, value));
+        }
+
+        Self { previous }
+    }
+
+    /// Restore the variables to their previous values, or remove them if none
+    pub async fn exit(self, executor: &mut Executor) {
+        let lang = executor.programming_language(&None);
+        let mut kernels = executor.kernels.write().await;
+
+        for (name, previous) in self.previous.into_iter().rev() {
+            let result = match &previous {
+                Some(previous) => kernels.set(&name, previous, lang.as_deref()).await,
+                None => kernels.remove(&name).await,
+            };
+
+            if let Err(error) = result {
+                tracing::debug!("While restoring argument variable `{name}`: {error}");
+            }
+        }
+    }
+}
+
+/// Whether an include has an argument with a name which evaluates to a truthy value
+///
+/// Used for boolean arguments e.g. `--isolate=true`. Returns `false` if there is no such
+/// argument. If the argument fails to evaluate then a warning is added to `messages` and
+/// `false` is returned.
+pub(crate) async fn truthy_argument(
+    arguments: &Option<Vec<CallArgument>>,
+    name: &str,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> bool {
+    let Some(arg) = arguments.iter().flatten().find(|arg| arg.name == name) else {
+        return false;
+    };
+
+    match evaluate_argument(arg, executor).await {
+        Ok(value) => is_truthy(&value),
+        Err(error) => {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("Unable to evaluate `{name}` argument so ignoring it: {error}"),
+            ));
+            false
+        }
+    }
+}
+
+/// Get the overrides of decode options from the arguments of an include
+///
+/// If an argument fails to evaluate, or has an invalid value, then a warning is
+/// added to `messages` and the option is not overridden.
+pub(crate) async fn decode_overrides(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> DecodeOverrides {
+    let mut overrides = DecodeOverrides::default();
+    for arg in arguments
+        .iter()
+        .flatten()
+        .filter(|arg| DECODE_ARGUMENTS.contains(&arg.name.as_str()))
+    {
+        let result = match evaluate_argument(arg, executor).await {
+            Ok(value) => overrides.set(&arg.name, &value),
+            Err(error) => Err(error.to_string()),
+        };
+        if let Err(error) = result {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Unable to use `{}` argument so using default: {error}",
+                    arg.name
+                ),
+            ));
+        }
+    }
+    overrides
+}
+
+/// Get the encoding specified by any `encoding` argument of an include
+///
+/// Returns an error if the argument can not be evaluated, is not a string, or
+/// is not the label of a supported encoding.
+pub(crate) async fn encoding_argument(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+) -> Result<Option<&'static Encoding>> {
+    let Some(arg) = arguments
+        .iter()
+        .flatten()
+        .find(|arg| arg.name == ENCODING_ARGUMENT)
+    else {
+        return Ok(None);
+    };
+
+    match evaluate_argument(arg, executor).await {
+        Ok(Node::String(label)) => Ok(Some(encoding_for_label(&label)?)),
+        Ok(value) => bail!(IncludeError::ParseError(format!(
+            "Invalid `encoding` argument `{}`, expected the name of an encoding",
+            to_text(&value).trim()
+        ))),
+        Err(error) => bail!(IncludeError::ParseError(format!(
+            "Unable to evaluate `encoding` argument: {error}"
+        ))),
+    }
+}
+
+/// Get the value of any `limit` argument of an include
+///
+/// If the argument can not be evaluated, or is not a non-negative integer, then a
+/// warning is added to `messages` and `None` (i.e. no limit) is returned.
+pub(crate) async fn limit_argument(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Option<usize> {
+    let arg = arguments
+        .iter()
+        .flatten()
+        .find(|arg| arg.name == LIMIT_ARGUMENT)?;
+
+    let value = match evaluate_argument(arg, executor).await {
+        Ok(value) => value,
+        Err(error) => {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("Unable to evaluate `limit` argument so not limiting: {error}"),
+            ));
+            return None;
+        }
+    };
+
+    let limit = parse_limit(&value);
+    if limit.is_none() {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Warning,
+            format!(
+                "Invalid `limit` argument `{}` so not limiting",
+                to_text(&value).trim()
+            ),
+        ));
+    }
+
+    limit
+}
+
+/// Parse the value of a `limit` argument
+fn parse_limit(value: &Node) -> Option<usize> {
+    match value {
+        Node::Integer(limit) => usize::try_from(*limit).ok(),
+        Node::UnsignedInteger(limit) => usize::try_from(*limit).ok(),
+        Node::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parse the value of a `timeout` argument
+///
+/// Numbers are in seconds. Strings are a number optionally followed by
+/// a unit: `ms`, `s`, `m`, or `h` e.g. `500ms`, `60s`, `2m`. Returns `None` if the
+/// value is invalid, or is too large to be a duration (e.g. `1e300`).
+pub(crate) fn parse_timeout(value: &Node) -> Option<Duration> {
+    let seconds = match value {
+        Node::Integer(seconds) => *seconds as f64,
+        Node::UnsignedInteger(seconds) => *seconds as f64,
+        Node::Number(seconds) => *seconds,
+        Node::String(string) => {
+            let string = string.trim();
+            let split = string
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(string.len());
+            let (number, unit) = string.split_at(split);
+            let number: f64 = number.parse().ok()?;
+            match unit.trim() {
+                "ms" => number / 1000.,
+                "" | "s" | "sec" | "secs" => number,
+                "m" | "min" | "mins" => number * 60.,
+                "h" => number * 3600.,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    if seconds <= 0. {
+        return None;
+    }
+
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Parse the value of a `retries` argument
+pub(crate) fn parse_retries(value: &Node) -> Option<u32> {
+    match value {
+        Node::Integer(retries) => u32::try_from(*retries).ok(),
+        Node::UnsignedInteger(retries) => u32::try_from(*retries).ok(),
+        Node::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use tokio::sync::RwLock;
+
+    use stencila_kernels::Kernels;
+    use stencila_schema::ExecutionBounds;
+
+    use super::*;
+
+    #[test]
+    fn timeouts_retries_and_limits() {
+        let string = |value: &str| Node::String(value.into());
+
+        assert_eq!(parse_timeout(&string("60s")), Some(Duration::from_secs(60)));
+        assert_eq!(
+            parse_timeout(&string("500ms")),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(parse_timeout(&string("2m")), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_timeout(&string("1.5")),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            parse_timeout(&Node::Integer(30)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_timeout(&string("60 fortnights")), None);
+        assert_eq!(parse_timeout(&string("soon")), None);
+        assert_eq!(parse_timeout(&Node::Integer(0)), None);
+        assert_eq!(parse_timeout(&Node::Number(1e300)), None);
+
+        assert_eq!(parse_retries(&Node::Integer(5)), Some(5));
+        assert_eq!(parse_retries(&string("3")), Some(3));
+        assert_eq!(parse_retries(&Node::Integer(-1)), None);
+        assert_eq!(parse_retries(&Node::Boolean(true)), None);
+
+        assert_eq!(parse_limit(&Node::Integer(100)), Some(100));
+        assert_eq!(parse_limit(&string("10")), Some(10));
+        assert_eq!(parse_limit(&Node::Integer(-1)), None);
+    }
+
+    #[tokio::test]
+    async fn arguments_scope() -> Result<()> {
+        let kernels = Arc::new(RwLock::new(Kernels::new_here(ExecutionBounds::Main)));
+        kernels
+            .write()
+            .await
+            .set("existing", &Node::Integer(1), None)
+            .await?;
+        let mut executor = Executor::new(PathBuf::new(), kernels.clone(), None);
+
+        let scope = ArgumentsScope::enter(vec!["existing".into(), "new".into()], &executor).await;
+        let mut guard = kernels.write().await;
+        guard.set("existing", &Node::Integer(2), None).await?;
+        guard.set("new", &Node::Integer(3), None).await?;
+        drop(guard);
+        scope.exit(&mut executor).await;
+
+        let guard = kernels.read().await;
+        assert_eq!(guard.get("existing").await?, Some(Node::Integer(1)));
+        assert_eq!(guard.get("new").await?, None);
+
+        Ok(())
+    }
+}
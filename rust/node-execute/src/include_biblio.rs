@@ -0,0 +1,104 @@
+//! Decoding of bibliography sources (BibTeX and CSL-JSON) into references
+//!
+//! Rather than being rendered as content, the references in these sources are added
+//! to the bibliography of the document so that its citations can be resolved against them.
+
+use eyre::{Result, bail};
+use serde_json::Value;
+
+use stencila_codecs::{DecodeOptions, Format};
+use stencila_schema::Reference;
+
+/// The media types of BibTeX sources
+const BIBTEX_MEDIA_TYPES: [&str; 3] = ["application/x-bibtex", "text/x-bibtex", "bibtex"];
+
+/// The media types of CSL-JSON sources
+const CSL_MEDIA_TYPES: [&str; 2] = ["application/vnd.citationstyles.csl+json", "csl"];
+
+/// Get the format of a bibliography source, based on its media type or, if none, its path
+///
+/// Returns `None` if the source is not a bibliography.
+pub(crate) fn bibliography_format(media_type: &Option<String>, path: &str) -> Option<Format> {
+    match media_type {
+        Some(media_type) => {
+            let media_type = media_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            if BIBTEX_MEDIA_TYPES.contains(&media_type.as_str()) {
+                Some(Format::Bibtex)
+            } else if CSL_MEDIA_TYPES.contains(&media_type.as_str()) {
+                Some(Format::Csl)
+            } else {
+                None
+            }
+        }
+        None => {
+            let path = path.to_lowercase();
+            if path.ends_with(".bib") || path.ends_with(".bibtex") {
+                Some(Format::Bibtex)
+            } else if path.ends_with(".csl.json") {
+                Some(Format::Csl)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Decode the bytes of a bibliography source into references
+pub(crate) async fn bytes_to_references(bytes: &[u8], format: Format) -> Result<Vec<Reference>> {
+    let text = String::from_utf8_lossy(bytes);
+
+    match format {
+        Format::Bibtex => stencila_codec_biblio::decode::bibtex(&text),
+        Format::Csl => {
+            // A CSL-JSON bibliography is an array of items but also allow a single item
+            let items = match serde_json::from_str(&text)? {
+                Value::Array(items) => items,
+                item @ Value::Object(..) => vec![item],
+                _ => bail!("Expected CSL-JSON to be an array of items"),
+            };
+
+            let mut references = Vec::with_capacity(items.len());
+            for item in items {
+                let node = stencila_codecs::from_str(
+                    &item.to_string(),
+                    Some(DecodeOptions {
+                        format: Some(Format::Csl),
+                        ..Default::default()
+                    }),
+                )
+                .await?;
+                references.push(Reference::from(&node));
+            }
+            Ok(references)
+        }
+        _ => bail!("Unsupported bibliography format `{format}`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!(bibliography_format(&None, "refs.bib"), Some(Format::Bibtex));
+        assert_eq!(
+            bibliography_format(&None, "/refs.CSL.json"),
+            Some(Format::Csl)
+        );
+        assert_eq!(bibliography_format(&None, "refs.json"), None);
+        assert_eq!(
+            bibliography_format(&Some("application/x-bibtex".into()), "refs"),
+            Some(Format::Bibtex)
+        );
+        assert_eq!(
+            bibliography_format(&Some("text/markdown".into()), "refs.bib"),
+            None
+        );
+    }
+}
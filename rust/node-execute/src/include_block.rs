@@ -1,16 +1,88 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::PathBuf,
+    sync::{Arc, LazyLock, Mutex, atomic::Ordering},
+};
 
-use stencila_codecs::DecodeOptions;
-use stencila_schema::{Block, CompilationMessage, IncludeBlock};
+use eyre::{Result, bail, eyre};
+use flate2::read::MultiGzDecoder;
+use itertools::Itertools;
+use reqwest::Url;
+use tempfile::tempdir;
+use tokio::sync::Notify;
 
-use crate::prelude::*;
+use stencila_codec_cbor::r#trait::CborCodec;
+use stencila_codec_text_trait::to_text;
+use stencila_codecs::{DecodeOptions, Format};
+use stencila_schema::{
+    Block, CallArgument, CompilationDigest, CompilationMessage, ExecutionBounds, File, FileOptions,
+    IncludeBlock, NodeId, Reference, StripTargets, replicate, strip,
+};
+
+use crate::{
+    include_arguments::{
+        ArgumentsScope, CONTINUE_LISTS_ARGUMENT, ENCODING_ARGUMENT, RAW_ARGUMENT,
+        RESERVED_ARGUMENTS, RETRIES_ARGUMENT, STRIP_OUTPUTS_ARGUMENT, TIMEOUT_ARGUMENT,
+        decode_overrides, encoding_argument, evaluate_argument, evaluate_code, has_argument,
+        limit_argument, parse_retries, parse_timeout, set_arguments, should_include,
+        truthy_argument,
+    },
+    include_biblio::{bibliography_format, bytes_to_references},
+    include_cache::{CachedContent, ContentVariant, IncludeCachePolicy},
+    include_checksum::{SHA256_ARGUMENT, parse_sha256},
+    include_cycle::{include_cycle, include_too_deep},
+    include_deps::{IncludeDependency, include_dependencies},
+    include_diagram::{Diagram, diagram_kind, diagram_to_block},
+    include_dir::{IncludeDirScope, has_code, include_dir},
+    include_encoding::{is_transcodable, transcode},
+    include_error::IncludeError,
+    include_export::{EXPORT_ARGUMENT, ExportScope, export_names},
+    include_fetch::{
+        FetchOptions, Fetched, accept_header, fetch_remote, file_url_path, head_remote,
+        is_accepted, is_network, is_remote, join_url_dir, url_dir,
+    },
+    include_filter::filter_blocks,
+    include_isolate::{ISOLATE_ARGUMENT, IsolationScope},
+    include_jsonl::{is_jsonl, jsonl_to_datatable, read_jsonl},
+    include_markers::{MARKERS_PREFIX, Markers, Unmatched},
+    include_memory::IncludeSource,
+    include_provenance::record_source,
+    include_rebase::rebase_paths,
+    include_select::{
+        AFTER_PREFIX, AfterHeading, HeadingSections, HeadingSlug, IdRange, Selector, TEXT_PREFIX,
+        TextMatch, block_ids, has_execution_status, select_fragment,
+    },
+    include_sniff::{is_ambiguous, sniff_format},
+    include_tags::{TAG_PREFIX, tagged_region},
+    include_trace::IncludeTrace,
+    include_validate::validate_blocks,
+    include_version::{IncludeVersioning, VERSION_ARGUMENT},
+    include_xpath::{XPath, is_xpath, xpath_fragment},
+    prelude::*,
+    state_digest,
+};
 
 impl Executable for IncludeBlock {
     #[tracing::instrument(skip_all)]
     async fn compile(&mut self, executor: &mut Executor) -> WalkControl {
         // Return early if no source
-        // TODO: should also return early if source has not changed since last compile
         if self.source.trim().is_empty() {
+            if let Some(level) = executor.include_empty_policy.message_level() {
+                let mut messages = vec![CompilationMessage::new(
+                    level,
+                    "Include has no source".to_string(),
+                )];
+                set_depth(&mut messages, executor);
+
+                let messages = Some(messages);
+                self.options.compilation_messages = messages.clone();
+                executor.patch(
+                    &self.node_id(),
+                    [set(NodeProperty::CompilationMessages, messages)],
+                );
+            }
+
             // Continue walk to compile any existing `content`
             return WalkControl::Continue;
         }
@@ -18,13 +90,246 @@ impl Executable for IncludeBlock {
         let node_id = self.node_id();
         tracing::trace!("Compiling IncludeBlock {node_id}");
 
-        // Get the content from the source
-        let (content, pop_dir, mut messages) =
-            source_to_content(&self.source, &self.media_type, executor).await;
+        // If only specific includes are being recompiled (e.g. because a file that they
+        // depend upon has changed) and this is not one of them, then do not refetch the
+        // source but do compile any existing content (which may contain those includes)
+        if let Some(node_ids) = &executor.node_ids
+            && !node_ids.contains(&node_id)
+        {
+            let (identifier, pop_dir) = resolve_source(&self.source, executor);
+
+            if let Err(error) = compile_content(&mut self.content, &identifier, executor).await {
+                tracing::debug!("While compiling content of IncludeBlock {node_id}: {error}");
+            }
+
+            if pop_dir {
+                executor.directory_stack.pop();
+            }
+
+            return WalkControl::Break;
+        }
+
+        // If there is an `if` argument, and it is not truthy, then remove any
+        // existing content and return early without fetching the source
+        let (include, condition_message) = should_include(&self.arguments, executor).await;
+        let mut messages: Vec<CompilationMessage> = condition_message.into_iter().collect();
+        if !include {
+            tracing::trace!("Excluding IncludeBlock {node_id}");
+
+            set_depth(&mut messages, executor);
+            let messages = (!messages.is_empty()).then_some(messages);
+
+            self.content = None;
+            self.options.content_from = None;
+            self.options.compilation_messages = messages.clone();
+            executor.patch(
+                &node_id,
+                [
+                    none(NodeProperty::Content),
+                    none(NodeProperty::ContentFrom),
+                    set(NodeProperty::CompilationMessages, messages),
+                ],
+            );
+
+            return WalkControl::Break;
+        }
+
+        // If tracing, record the directory that the source is resolved against
+        let mut trace = executor.include_trace.then(|| {
+            let mut trace = IncludeTrace::new(&self.source);
+            let dir = executor.directory_stack.last().map_or_else(
+                || "none".to_string(),
+                |dir| format!("`{}`", dir.to_string_lossy()),
+            );
+            trace.step("directory", dir);
+            trace
+        });
+
+        // Resolve the source into an identifier, pushing onto the directory stack if necessary
+        let (identifier, pop_dir) = resolve_source(&self.source, executor);
+
+        if let Some(trace) = &mut trace {
+            trace.step("resolved", format!("`{identifier}`"));
+            let redirected = redirect_identifier(&identifier, executor);
+            if redirected != identifier {
+                trace.step("redirected", format!("`{redirected}`"));
+            }
+            trace.step("media type", describe_media_type(self, &redirected));
+            if let Some(select) = &self.select {
+                trace.step("select", format!("`{select}`"));
+            }
+        }
+
+        // Record whether the source is remote so that it can be indicated to users
+        let remote = is_remote(&identifier);
+        self.options.is_remote = Some(remote);
+        executor.patch(&node_id, [set(NodeProperty::IsRemote, Some(remote))]);
+
+        // If the source is already being included by an ancestor of this include then
+        // do not include it again, since that would recurse indefinitely. Likewise, do
+        // not include it if it would be nested deeper than the maximum depth.
+        let ancestors = &executor.include_ancestors;
+        if let Some(error) = include_cycle(&identifier, ancestors)
+            .or_else(|| include_too_deep(&identifier, ancestors, executor.include_max_depth))
+        {
+            tracing::debug!("Not including IncludeBlock {node_id}: {error}");
+
+            messages.push(CompilationMessage::new(
+                MessageLevel::Error,
+                error.to_string(),
+            ));
+            if executor.strict_includes && executor.include_failure.is_none() {
+                executor.include_failure = Some(error);
+            }
+
+            if pop_dir {
+                executor.directory_stack.pop();
+            }
+
+            if let Some(trace) = trace {
+                messages.push(trace.message());
+            }
+            set_depth(&mut messages, executor);
+            let messages = Some(messages);
+
+            self.content = None;
+            self.options.content_from = None;
+            self.options.compilation_digest = None;
+            self.options.compilation_messages = messages.clone();
+            executor.patch(
+                &node_id,
+                [
+                    none(NodeProperty::Content),
+                    none(NodeProperty::ContentFrom),
+                    none(NodeProperty::CompilationDigest),
+                    set(NodeProperty::CompilationMessages, messages),
+                ],
+            );
+
+            return WalkControl::Break;
+        }
+
+        // If this is a dry run then only report what would be included, leaving any
+        // existing `content` unchanged and continuing to compile it (so that nested
+        // includes are also reported)
+        if executor
+            .compile_options
+            .as_ref()
+            .map(|opts| opts.dry_run)
+            .unwrap_or_default()
+        {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Info,
+                format!("Would include `{identifier}`"),
+            ));
+
+            if let Err(error) = compile_content(&mut self.content, &identifier, executor).await {
+                messages.push(error_to_compilation_message(error));
+            };
+
+            if pop_dir {
+                executor.directory_stack.pop();
+            }
+
+            set_depth(&mut messages, executor);
+            self.options.compilation_messages = Some(messages.clone());
+            executor.patch(
+                &node_id,
+                [set(NodeProperty::CompilationMessages, Some(messages))],
+            );
+
+            return WalkControl::Break;
+        }
+
+        // If none of the inputs that affect the content of the include have changed since it
+        // was last compiled, then do not refetch the source but do compile the existing content
+        let digest = inputs_digest(self, &redirect_identifier(&identifier, executor)).await;
+        if digest.is_some()
+            && digest == self.options.compilation_digest
+            && self.content.is_some()
+            && executor.include_cache_policy != IncludeCachePolicy::ForceFresh
+        {
+            tracing::trace!("Skipping fetching source of IncludeBlock {node_id}");
+
+            executor.list_continuation = truthy_argument(
+                &self.arguments,
+                CONTINUE_LISTS_ARGUMENT,
+                executor,
+                &mut messages,
+            )
+            .await;
+            if let Err(error) = compile_content(&mut self.content, &identifier, executor).await {
+                tracing::debug!("While compiling content of IncludeBlock {node_id}: {error}");
+            }
+            executor.list_continuation = false;
+
+            if pop_dir {
+                executor.directory_stack.pop();
+            }
+
+            executor.include_from_cache = true;
+            return WalkControl::Break;
+        }
+
+        // Get the content from the source, unless cancelled by an interrupt. If the same
+        // source has already been included with identical messages then those are not
+        // repeated on this include.
+        let cache_hits = executor.include_cache_hits;
+        let Some((content, content_messages)) =
+            cancellable_source_to_content(&identifier, self, executor).await
+        else {
+            if pop_dir {
+                executor.directory_stack.pop();
+            }
+            set_cancelled(self, executor);
+            return WalkControl::Break;
+        };
+        clear_cancelled(self, executor);
+        let from_cache = executor.include_cache_hits > cache_hits;
+        if let Some(trace) = &mut trace {
+            let detail = match &content {
+                Some(content) => format!(
+                    "{} block{}{}",
+                    content.len(),
+                    if content.len() == 1 { "" } else { "s" },
+                    if from_cache { " (from cache)" } else { "" }
+                ),
+                None => "none".to_string(),
+            };
+            trace.step("content", detail);
+        }
+        if !is_repeated_messages(&identifier, &node_id, &content_messages, executor) {
+            messages.extend(content_messages);
+        }
+
+        // If deduplicating, and this content has already been included, then reference
+        // the earlier include instead of repeating the content
+        let content_from = if executor.include_dedup && content.is_some() {
+            dedup_content(&content, &self.arguments, &node_id, executor)
+        } else {
+            None
+        };
+        let content = if content_from.is_some() {
+            None
+        } else {
+            content
+        };
+        self.options.content_from = content_from.clone();
+        executor.patch(&node_id, [set(NodeProperty::ContentFrom, content_from)]);
 
-        // Add the content to the include block
-        if let Some(content) = content {
+        // Add the content to the include block, and the digest of the inputs used to get it.
+        // If the content is unchanged (e.g. a remote source, which has no digest, or a local
+        // file that was saved without changes) then the existing content, and the ids of its
+        // nodes, are retained rather than being replaced with identical content.
+        if let Some(content) = content
+            && self.content.as_ref() == Some(&content)
+        {
+            tracing::trace!("Content of IncludeBlock {node_id} is unchanged");
+            self.options.compilation_digest = digest.clone();
+            executor.patch(&node_id, [set(NodeProperty::CompilationDigest, digest)]);
+        } else if let Some(content) = content {
             self.content = Some(content.clone());
+            self.options.compilation_digest = digest.clone();
             executor.patch(
                 &node_id,
                 [
@@ -32,29 +337,62 @@ impl Executable for IncludeBlock {
                     // the later retains node ids so they are the same as in `self.content`
                     none(NodeProperty::Content),
                     append(NodeProperty::Content, content),
+                    set(NodeProperty::CompilationDigest, digest),
                 ],
             );
         } else {
             self.content = None;
-            executor.patch(&node_id, [none(NodeProperty::Content)])
+            self.options.compilation_digest = None;
+            executor.patch(
+                &node_id,
+                [
+                    none(NodeProperty::Content),
+                    none(NodeProperty::CompilationDigest),
+                ],
+            )
         };
 
         // Compile the content. This needs to be done here between (possibly)
         // pushing and popping from the directory stack.
-        if let Err(error) = self.content.walk_async(executor).await {
+        executor.list_continuation = truthy_argument(
+            &self.arguments,
+            CONTINUE_LISTS_ARGUMENT,
+            executor,
+            &mut messages,
+        )
+        .await;
+        if let Err(error) = compile_content(&mut self.content, &identifier, executor).await {
             messages.push(error_to_compilation_message(error));
         };
+        executor.list_continuation = false;
 
         // Pop off the directory stack if necessary
         if pop_dir {
             executor.directory_stack.pop();
         }
 
+        if let Some(trace) = trace {
+            messages.push(trace.message());
+        }
+
+        // Record the include nesting depth so that messages can be grouped by it
+        set_depth(&mut messages, executor);
+
+        if let Some(entry) = executor.include_messages.get_mut(&identifier)
+            && entry.node_id == node_id
+        {
+            entry.messages = messages.clone();
+        }
+
         let messages = (!messages.is_empty()).then_some(messages);
 
         self.options.compilation_messages = messages.clone();
         executor.patch(&node_id, [set(NodeProperty::CompilationMessages, messages)]);
 
+        // Record whether the content was from the cache after compiling the content,
+        // so that it is not overwritten by any nested includes
+        executor.include_from_cache = from_cache;
+
         // Break because `content` already compiled above
         WalkControl::Break
     }
@@ -69,12 +407,37 @@ impl Executable for IncludeBlock {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn execute(&mut self, _executor: &mut Executor) -> WalkControl {
+    async fn execute(&mut self, executor: &mut Executor) -> WalkControl {
         let node_id = self.node_id();
         tracing::debug!("Executing IncludeBlock {node_id}: {}", self.source);
 
-        // Continue walk to execute nodes in `content`
-        WalkControl::Continue
+        // Execute within a fork of the kernels if the include is isolated, walking the content
+        // here (rather than continuing the walk) so that all of it is executed in the fork
+        let mut messages = Vec::new();
+        if truthy_argument(&self.arguments, ISOLATE_ARGUMENT, executor, &mut messages).await {
+            match IsolationScope::enter(executor).await {
+                Ok(scope) => {
+                    if matches!(execute_include(self, executor).await, WalkControl::Continue)
+                        && let Err(error) = walk_content(&mut self.content, executor).await
+                    {
+                        tracing::debug!(
+                            "While executing content of IncludeBlock {node_id}: {error}"
+                        );
+                    }
+                    scope.exit(executor);
+                }
+                Err(error) => messages.push(CompilationMessage::new(
+                    MessageLevel::Error,
+                    format!("Unable to isolate content so not executing it: {error}"),
+                )),
+            }
+            add_messages(self, messages, executor);
+            return WalkControl::Break;
+        }
+
+        let control = execute_include(self, executor).await;
+        add_messages(self, messages, executor);
+        control
     }
 
     #[tracing::instrument(skip_all)]
@@ -82,76 +445,2803 @@ impl Executable for IncludeBlock {
         let node_id = self.node_id();
         tracing::debug!("Interrupting IncludeBlock {node_id}");
 
+        // Cancel any in-flight fetch of the source
+        if let Ok(cancellations) = CANCELLATIONS.lock()
+            && let Some(cancel) = cancellations.get(&node_id)
+        {
+            cancel.notify_one();
+        }
+
         // Continue walk to interrupt nodes in `content`
         WalkControl::Continue
     }
 }
 
-// Get the content from a source
-async fn source_to_content(
-    source: &str,
-    media_type: &Option<String>,
+/// Execute an include, and its content, within the kernels of the executor
+async fn execute_include(include: &mut IncludeBlock, executor: &mut Executor) -> WalkControl {
+    let node_id = include.node_id();
+
+    // Re-evaluate any `if` argument because the variables that it uses may
+    // have been changed by the execution of preceding nodes
+    if include
+        .arguments
+        .iter()
+        .flatten()
+        .any(|arg| arg.name == IncludeBlock::IF_ARGUMENT)
+    {
+        let (included, ..) = should_include(&include.arguments, executor).await;
+
+        if !included {
+            if include.content.is_some() {
+                include.content = None;
+                executor.patch(&node_id, [none(NodeProperty::Content)]);
+            }
+
+            return WalkControl::Break;
+        }
+
+        // If the content was excluded when compiled then fetch it now
+        // and compile, prepare and execute it
+        if include.content.is_none()
+            && include.options.content_from.is_none()
+            && !include.source.trim().is_empty()
+        {
+            let (identifier, pop_dir) = resolve_source(&include.source, executor);
+
+            let Some((content, mut messages)) =
+                cancellable_source_to_content(&identifier, include, executor).await
+            else {
+                if pop_dir {
+                    executor.directory_stack.pop();
+                }
+                set_cancelled(include, executor);
+                return WalkControl::Break;
+            };
+            clear_cancelled(include, executor);
+            let arguments_scope = enter_arguments_scope(&include.arguments, executor).await;
+            let export_scope =
+                enter_export_scope(&include.arguments, executor, &mut messages).await;
+            messages.extend(set_arguments(&include.arguments, executor).await);
+
+            if let Some(content) = content {
+                include.content = Some(content.clone());
+                executor.patch(
+                    &node_id,
+                    [
+                        none(NodeProperty::Content),
+                        append(NodeProperty::Content, content),
+                    ],
+                );
+
+                let scope = enter_include_dir(&identifier, &include.content, executor).await;
+                executor.include_depth += 1;
+                executor.include_ancestors.push(identifier.clone());
+                if let Err(error) = executor.compile_prepare_execute(&mut include.content).await {
+                    messages.push(error_to_compilation_message(error));
+                }
+                executor.include_ancestors.pop();
+                executor.include_depth -= 1;
+                if let Some(scope) = scope {
+                    scope.exit(executor).await;
+                }
+            }
+            messages.extend(exit_scopes(export_scope, arguments_scope, executor).await);
+
+            if pop_dir {
+                executor.directory_stack.pop();
+            }
+
+            set_depth(&mut messages, executor);
+
+            let messages = (!messages.is_empty()).then_some(messages);
+
+            include.options.compilation_messages = messages.clone();
+            executor.patch(&node_id, [set(NodeProperty::CompilationMessages, messages)]);
+
+            // Break because `content` already executed above
+            return WalkControl::Break;
+        }
+    }
+
+    // Set arguments as variables in the kernels so that they are available
+    // to the nodes in `content`
+    let arguments_scope = enter_arguments_scope(&include.arguments, executor).await;
+    let mut messages = Vec::new();
+    let export_scope = enter_export_scope(&include.arguments, executor, &mut messages).await;
+    messages.extend(set_arguments(&include.arguments, executor).await);
+    add_messages(include, messages, executor);
+
+    // If the content has code then execute it here, rather than continuing the walk, so
+    // that the `include_dir` variable is only set while it is executed
+    if include.content.as_deref().is_some_and(has_code) && !include.source.trim().is_empty() {
+        let (identifier, pop_dir) = resolve_source(&include.source, executor);
+
+        let scope = enter_include_dir(&identifier, &include.content, executor).await;
+        if let Err(error) = walk_content(&mut include.content, executor).await {
+            tracing::debug!("While executing content of IncludeBlock {node_id}: {error}");
+        }
+        if let Some(scope) = scope {
+            scope.exit(executor).await;
+        }
+        let messages = exit_scopes(export_scope, arguments_scope, executor).await;
+        add_messages(include, messages, executor);
+
+        if pop_dir {
+            executor.directory_stack.pop();
+        }
+
+        return WalkControl::Break;
+    }
+
+    // If the arguments are to be restored, or only some symbols exported, then execute the
+    // content here, rather than continuing the walk, so that the scopes can be exited once
+    // it has been executed
+    if arguments_scope.is_some() || export_scope.is_some() {
+        if let Err(error) = walk_content(&mut include.content, executor).await {
+            tracing::debug!("While executing content of IncludeBlock {node_id}: {error}");
+        }
+        let messages = exit_scopes(export_scope, arguments_scope, executor).await;
+        add_messages(include, messages, executor);
+
+        return WalkControl::Break;
+    }
+
+    // Continue walk to execute nodes in `content`
+    WalkControl::Continue
+}
+
+/// Add messages to the compilation messages of an include
+///
+/// Messages that the include already has are not added again.
+fn add_messages(
+    include: &mut IncludeBlock,
+    mut messages: Vec<CompilationMessage>,
+    executor: &mut Executor,
+) {
+    if messages.is_empty() {
+        return;
+    }
+
+    set_depth(&mut messages, executor);
+    let existing = include.options.compilation_messages.get_or_insert_default();
+    for message in messages {
+        if !existing.contains(&message) {
+            existing.push(message);
+        }
+    }
+    executor.patch(
+        &include.node_id(),
+        [set(
+            NodeProperty::CompilationMessages,
+            include.options.compilation_messages.clone(),
+        )],
+    );
+}
+
+/// What to do when an include has no source, or its source has no content
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyIncludePolicy {
+    /// Do nothing
+    #[default]
+    Silent,
+
+    /// Add a warning message to the include
+    Warn,
+
+    /// Add an error message to the include
+    Error,
+}
+
+impl EmptyIncludePolicy {
+    /// The level of the compilation message to add to an empty include, if any
+    fn message_level(&self) -> Option<MessageLevel> {
+        match self {
+            Self::Silent => None,
+            Self::Warn => Some(MessageLevel::Warning),
+            Self::Error => Some(MessageLevel::Error),
+        }
+    }
+}
+
+/// Set the `include_dir` variable to the directory of the source of an include
+///
+/// Only set if the content of the include has code that could use it. Returns the
+/// scope that should be exited after the content is executed to restore the variable.
+async fn enter_include_dir(
+    identifier: &str,
+    content: &Option<Vec<Block>>,
+    executor: &mut Executor,
+) -> Option<IncludeDirScope> {
+    if !content.as_deref().is_some_and(has_code) {
+        return None;
+    }
+
+    let dir = include_dir(identifier, executor)?;
+    IncludeDirScope::enter(dir, executor).await
+}
+
+/// Walk the content of an include, as one more level of include nesting
+async fn walk_content(content: &mut Option<Vec<Block>>, executor: &mut Executor) -> Result<()> {
+    executor.include_depth += 1;
+    let result = content.walk_async(executor).await;
+    executor.include_depth -= 1;
+    result
+}
+
+/// Compile the content of an include, with its source as an ancestor of any nested includes
+async fn compile_content(
+    content: &mut Option<Vec<Block>>,
+    identifier: &str,
+    executor: &mut Executor,
+) -> Result<()> {
+    executor.include_ancestors.push(identifier.to_string());
+    let result = walk_content(content, executor).await;
+    executor.include_ancestors.pop();
+    result
+}
+
+/// Set the include nesting depth on the messages of an include
+///
+/// The depth is `1` for an include in the root document, `2` for an include within
+/// the content of that include, and so on. Messages that already have a depth are
+/// left unchanged.
+fn set_depth(messages: &mut [CompilationMessage], executor: &Executor) {
+    let depth = i64::from(executor.include_depth) + 1;
+    for message in messages {
+        message.depth.get_or_insert(depth);
+    }
+}
+
+/// Snapshot the variables for the arguments of an include, if they are to be restored
+///
+/// Returns `None` if restoring arguments is not enabled, or the include has no arguments
+/// that are set as variables. See [`Executor::set_include_restore_arguments`].
+async fn enter_arguments_scope(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &Executor,
+) -> Option<ArgumentsScope> {
+    if !executor.include_restore_arguments {
+        return None;
+    }
+
+    let names = arguments
+        .iter()
+        .flatten()
+        .filter(|arg| !RESERVED_ARGUMENTS.contains(&arg.name.as_str()))
+        .map(|arg| arg.name.clone())
+        .collect_vec();
+    if names.is_empty() {
+        return None;
+    }
+
+    Some(ArgumentsScope::enter(names, executor).await)
+}
+
+/// Snapshot the variables in the kernels if an include has an `export` argument
+///
+/// If the argument can not be evaluated, is not a list of names, or the variables can
+/// not be listed, then a warning is added to `messages` and all symbols are kept.
+async fn enter_export_scope(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Option<ExportScope> {
+    let arg = arguments
+        .iter()
+        .flatten()
+        .find(|arg| arg.name == EXPORT_ARGUMENT)?;
+
+    let mut warn = |message: String| {
+        messages.push(CompilationMessage::new(MessageLevel::Warning, message));
+        None
+    };
+
+    let value = match evaluate_argument(arg, executor).await {
+        Ok(value) => value,
+        Err(error) => {
+            return warn(format!(
+                "Unable to evaluate `export` argument so keeping all symbols: {error}"
+            ));
+        }
+    };
+    let Some(names) = export_names(&value) else {
+        return warn("Argument `export` is not a list of names so keeping all symbols".to_string());
+    };
+
+    match ExportScope::enter(names, executor).await {
+        Ok(scope) => Some(scope),
+        Err(error) => warn(format!(
+            "Unable to list variables so keeping all symbols: {error}"
+        )),
+    }
+}
+
+/// Exit the scopes entered for executing the content of an include
+///
+/// Unexported symbols are removed before arguments are restored. Returns any
+/// messages from exiting the scopes.
+async fn exit_scopes(
+    export_scope: Option<ExportScope>,
+    arguments_scope: Option<ArgumentsScope>,
     executor: &mut Executor,
-) -> (Option<Vec<Block>>, bool, Vec<CompilationMessage>) {
+) -> Vec<CompilationMessage> {
     let mut messages = Vec::new();
+    if let Some(scope) = export_scope {
+        messages = scope.exit(executor).await;
+    }
+    if let Some(scope) = arguments_scope {
+        scope.exit(executor).await;
+    }
+    messages
+}
 
-    // Resolve the source into a fully qualified URL (including `file://` URL)
-    let (identifier, pop_dir) = if source.starts_with("https://") || source.starts_with("http://") {
-        (source.to_string(), false)
-    } else {
-        // Make the path relative to the last directory in the executor's directory stack
-        // and update the stack if necessary.
-        let last_dir = executor.directory_stack.last();
-        let path = last_dir
-            .map(|dir| dir.join(source))
-            .unwrap_or_else(|| PathBuf::from(source));
-        let pop_dir = if let Some(dir) = path.parent() {
-            if Some(dir) != last_dir.map(|path_buf| path_buf.as_ref()) {
-                executor.directory_stack.push(dir.to_path_buf());
+/// Compilation messages from the source of an include
+///
+/// Recorded for the first include of each source in a compile so that
+/// identical messages for later includes of the same source are not repeated.
+#[derive(Debug, Clone)]
+pub(crate) struct IncludeMessages {
+    /// The id of the first `IncludeBlock` that included the source
+    node_id: NodeId,
+
+    /// The messages arising from getting the content from the source
+    source_messages: Vec<CompilationMessage>,
+
+    /// All the compilation messages of the first `IncludeBlock`
+    messages: Vec<CompilationMessage>,
+
+    /// The number of other includes of the source that had identical messages
+    repeats: usize,
+}
+
+/// Whether the messages from the source of an include repeat those of an earlier include
+///
+/// Records the messages if this is the first include of the source with messages.
+fn is_repeated_messages(
+    identifier: &str,
+    node_id: &NodeId,
+    source_messages: &[CompilationMessage],
+    executor: &mut Executor,
+) -> bool {
+    if source_messages.is_empty() {
+        return false;
+    }
+
+    match executor.include_messages.get_mut(identifier) {
+        Some(entry) => {
+            if &entry.node_id != node_id && entry.source_messages == source_messages {
+                entry.repeats += 1;
                 true
             } else {
                 false
             }
-        } else {
+        }
+        None => {
+            executor.include_messages.insert(
+                identifier.to_string(),
+                IncludeMessages {
+                    node_id: node_id.clone(),
+                    source_messages: source_messages.to_vec(),
+                    messages: Vec::new(),
+                    repeats: 0,
+                },
+            );
             false
-        };
+        }
+    }
+}
 
-        (path.to_string_lossy().to_string(), pop_dir)
-    };
+/// Update the messages of the first include of each source that was repeated
+///
+/// Called at the end of the compile phase so that each message from a source that is
+/// included in multiple places is shown once, with the number of other includes of
+/// the source, rather than on every include.
+pub(crate) fn report_repeated_include_messages(executor: &mut Executor) {
+    for (identifier, entry) in std::mem::take(&mut executor.include_messages) {
+        if entry.repeats == 0 {
+            continue;
+        }
 
-    // Decode the identifier
-    let content: Option<Vec<Block>> = match stencila_codecs::from_identifier(
-        &identifier,
-        Some(DecodeOptions {
-            media_type: media_type.clone(),
-            // Set format to None so that the format of the executor's decode options
-            // (that of the executor's document) is not used when decoding
-            format: None,
-            ..executor.decode_options.clone().unwrap_or_default()
-        }),
-    )
-    .await
-    {
-        Ok(node) => {
-            // Transform the decoded node into a blocks
-            match node.try_into() {
-                Ok(blocks) => Some(blocks),
-                Err(error) => {
-                    messages.push(CompilationMessage::new(
-                        MessageLevel::Error,
-                        format!("Unable to convert source into block content: {error}"),
+        let messages = entry
+            .messages
+            .into_iter()
+            .map(|mut message| {
+                // Compare without the depth, which is not set on the messages from the source
+                let source_message = CompilationMessage {
+                    depth: None,
+                    ..message.clone()
+                };
+                if entry.source_messages.contains(&source_message) {
+                    let plural = if entry.repeats == 1 { "" } else { "s" };
+                    message.message.push_str(&format!(
+                        " (also occurs in {} other include{plural} of `{identifier}`)",
+                        entry.repeats
                     ));
-                    None
                 }
+                message
+            })
+            .collect_vec();
+
+        executor.patch(
+            &entry.node_id,
+            [set(NodeProperty::CompilationMessages, Some(messages))],
+        );
+    }
+}
+
+/// The separator between the alternatives of a fallback chain of sources
+/// e.g. `primary.md || backup.md`
+const FALLBACK_SEPARATOR: &str = "||";
+
+/// Split a source, or resolved identifier, into the alternatives of a fallback chain
+///
+/// Returns a single alternative if the source is not a chain.
+pub(crate) fn source_alternatives(source: &str) -> Vec<&str> {
+    source
+        .split(FALLBACK_SEPARATOR)
+        .map(str::trim)
+        .filter(|alternative| !alternative.is_empty())
+        .collect()
+}
+
+/// Resolve a source into a fully qualified identifier (a URL or file path)
+///
+/// Relative file paths are resolved against the last directory in the executor's
+/// directory stack. If the resolved path is in a different directory then that
+/// directory is pushed onto the stack and the returned flag is `true` to indicate
+/// that the caller should pop it off the stack when done.
+///
+/// Sources that are `file://` URLs are resolved to absolute paths and the directory
+/// stack is left unchanged.
+///
+/// For HTTP(S) sources the URL of the "directory" of the source is pushed onto the
+/// stack instead, so that relative sources within the content of a remote source are
+/// resolved against its URL (e.g. `part.md` within `https://example.org/docs/main.md`
+/// resolves to `https://example.org/docs/part.md`), as they are for local sources.
+///
+/// For a fallback chain of sources, each alternative is resolved and the identifier
+/// is the chain of resolved alternatives. Only the directory of the first alternative
+/// is pushed onto the stack.
+pub(crate) fn resolve_source(source: &str, executor: &mut Executor) -> (String, bool) {
+    if let [first, fallbacks @ ..] = source_alternatives(source).as_slice()
+        && !fallbacks.is_empty()
+    {
+        // Resolve the fallbacks before the first alternative, which may push onto
+        // the directory stack, so that all are resolved against the same directory
+        let fallbacks = fallbacks
+            .iter()
+            .map(|fallback| {
+                let (identifier, pop_dir) = resolve_source(fallback, executor);
+                if pop_dir {
+                    executor.directory_stack.pop();
+                }
+                identifier
+            })
+            .collect_vec();
+        let (first, pop_dir) = resolve_source(first, executor);
+
+        let separator = format!(" {FALLBACK_SEPARATOR} ");
+        let identifier = [first].into_iter().chain(fallbacks).join(&separator);
+        return (identifier, pop_dir);
+    }
+
+    if let Some(path) = file_url_path(source) {
+        return (path.to_string_lossy().to_string(), false);
+    }
+
+    let last_dir = executor.directory_stack.last();
+    let url = if is_remote(source) {
+        Some(source.to_string())
+    } else {
+        last_dir.and_then(|dir| join_url_dir(dir, source))
+    };
+    if let Some(url) = url {
+        let pop_dir = match url_dir(&url) {
+            Some(dir) if Some(&dir) != last_dir => {
+                executor.directory_stack.push(dir);
+                true
             }
+            _ => false,
+        };
+        return (url, pop_dir);
+    }
+
+    let path = last_dir
+        .map(|dir| dir.join(source))
+        .unwrap_or_else(|| PathBuf::from(source));
+    let pop_dir = if let Some(dir) = path.parent() {
+        if Some(dir) != last_dir.map(|path_buf| path_buf.as_ref()) {
+            executor.directory_stack.push(dir.to_path_buf());
+            true
+        } else {
+            false
         }
+    } else {
+        false
+    };
+
+    (path.to_string_lossy().to_string(), pop_dir)
+}
+
+/// Apply the executor's redirects to a resolved identifier
+///
+/// For a fallback chain, each alternative is redirected separately. Identifiers
+/// without a redirect are returned unchanged.
+fn redirect_identifier(identifier: &str, executor: &Executor) -> String {
+    if executor.include_redirects.is_empty() {
+        return identifier.to_string();
+    }
+
+    let separator = format!(" {FALLBACK_SEPARATOR} ");
+    source_alternatives(identifier)
+        .into_iter()
+        .map(|alternative| {
+            executor
+                .include_redirects
+                .get(alternative)
+                .map_or(alternative, String::as_str)
+        })
+        .join(&separator)
+}
+
+/// Calculate a digest of all the inputs that affect the content of an include
+///
+/// Combines the `source`, `media_type`, `select`, and `arguments` of the include with the
+/// modification time and size of the resolved source file. Returns `None` for remote sources,
+/// which have no equivalent state that can be obtained without a request, and so are always
+/// refetched (albeit usually using conditional requests and cached content).
+async fn inputs_digest(include: &IncludeBlock, identifier: &str) -> Option<CompilationDigest> {
+    if is_remote(identifier) {
+        return None;
+    }
+
+    // The value of an expression may have changed since last compile
+    if include
+        .media_type
+        .as_deref()
+        .and_then(media_type_expression)
+        .is_some()
+        || include
+            .select
+            .as_deref()
+            .is_some_and(has_select_expressions)
+    {
+        return None;
+    }
+
+    let metadata = tokio::fs::metadata(identifier).await.ok()?;
+    let mut digest = state_digest!(
+        identifier,
+        include.source,
+        include.media_type,
+        include.select,
+        metadata.modified().ok(),
+        metadata.len()
+    );
+
+    // Use CBOR for arguments since they are not hashable
+    match include.arguments.to_cbor() {
+        Ok(bytes) => add_to_digest(&mut digest, &bytes),
         Err(error) => {
-            messages.push(error_to_compilation_message(error));
-            None
+            tracing::error!("While encoding `arguments` to CBOR: {error}");
+            return None;
         }
-    };
+    }
+
+    Some(CompilationDigest::new(digest))
+}
 
-    // TODO: Implement sub-selecting from included based on `select`
+/// Get the id of an earlier include with identical content and arguments, if any
+///
+/// If there is no such include then the digest of the content and arguments is
+/// recorded against `node_id` so that later includes can reference it.
+fn dedup_content(
+    content: &Option<Vec<Block>>,
+    arguments: &Option<Vec<CallArgument>>,
+    node_id: &NodeId,
+    executor: &mut Executor,
+) -> Option<String> {
+    // Node uids are not serialized so identical content has identical CBOR
+    let mut digest = 0;
+    for cbor in [content.to_cbor(), arguments.to_cbor()] {
+        match cbor {
+            Ok(bytes) => add_to_digest(&mut digest, &bytes),
+            Err(error) => {
+                tracing::error!("While encoding content to CBOR: {error}");
+                return None;
+            }
+        }
+    }
 
-    (content, pop_dir, messages)
+    match executor.include_contents.get(&digest) {
+        Some(first) if first != node_id => Some(first.to_string()),
+        Some(..) => None,
+        None => {
+            executor.include_contents.insert(digest, node_id.clone());
+            None
+        }
+    }
+}
+
+/// Signals used to cancel the in-flight fetches of the sources of includes, keyed by node id
+///
+/// Process-wide because interrupts are made by a different executor to the one compiling.
+static CANCELLATIONS: LazyLock<Mutex<HashMap<NodeId, Arc<Notify>>>> = LazyLock::new(Mutex::default);
+
+/// Get the content from a resolved source identifier, unless the include is interrupted
+///
+/// Returns `None` if the include was interrupted (see [`IncludeBlock::interrupt`])
+/// before the content was fetched and decoded.
+async fn cancellable_source_to_content(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> Option<(Option<Vec<Block>>, Vec<CompilationMessage>)> {
+    let node_id = include.node_id();
+
+    let cancel = Arc::new(Notify::new());
+    if let Ok(mut cancellations) = CANCELLATIONS.lock() {
+        cancellations.insert(node_id.clone(), cancel.clone());
+    }
+
+    let result = tokio::select! {
+        result = source_to_content(identifier, include, executor) => Some(result),
+        _ = cancel.notified() => {
+            tracing::debug!("Cancelled fetching source of IncludeBlock {node_id}");
+            None
+        }
+    };
+
+    // Only remove the signal if it has not been replaced by another compile of the include
+    if let Ok(mut cancellations) = CANCELLATIONS.lock()
+        && cancellations
+            .get(&node_id)
+            .is_some_and(|current| Arc::ptr_eq(current, &cancel))
+    {
+        cancellations.remove(&node_id);
+    }
+
+    result
+}
+
+/// Mark an include as having had the fetch of its source cancelled
+///
+/// Any existing `content` is left unchanged.
+fn set_cancelled(include: &mut IncludeBlock, executor: &Executor) {
+    let status = Some(ExecutionStatus::Cancelled);
+    let messages = Some(vec![ExecutionMessage::new(
+        MessageLevel::Warning,
+        "Fetching of source was cancelled".to_string(),
+    )]);
+
+    include.options.execution_status = status;
+    include.options.execution_messages = messages.clone();
+    executor.patch(
+        &include.node_id(),
+        [
+            set(NodeProperty::ExecutionStatus, status),
+            set(NodeProperty::ExecutionMessages, messages),
+        ],
+    );
+}
+
+/// Clear any cancellation of an include, after its source has been successfully fetched
+fn clear_cancelled(include: &mut IncludeBlock, executor: &Executor) {
+    if include.options.execution_status != Some(ExecutionStatus::Cancelled) {
+        return;
+    }
+
+    include.options.execution_status = None;
+    include.options.execution_messages = None;
+    executor.patch(
+        &include.node_id(),
+        [
+            none(NodeProperty::ExecutionStatus),
+            none(NodeProperty::ExecutionMessages),
+        ],
+    );
+}
+
+/// Get the content of an include from its source, without compiling it
+///
+/// Used by embedders (via [`crate::resolve_include_content`]) to get the content
+/// of an include, or a structured error, without walking the document.
+pub(crate) async fn resolve_content(
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> Result<Vec<Block>, IncludeError> {
+    let (identifier, pop_dir) = resolve_source(&include.source, executor);
+    let redirected = redirect_identifier(&identifier, executor);
+
+    let (result, ..) = chain_to_result(&redirected, include, executor).await;
+
+    if pop_dir {
+        executor.directory_stack.pop();
+    }
+
+    result.map(|blocks| apply_filter(blocks, executor))
+}
+
+/// Fetch and decode the source of an include dependency so that it is cached
+///
+/// Uses a fork of the executor so that sources can be fetched concurrently. The include
+/// has the media type, selector, and arguments of the dependency so that the content is
+/// cached as the same variant as when the include is compiled. The content is not returned
+/// because the aim is only to populate the cache of decoded content that is shared by all
+/// executors.
+pub(crate) async fn prefetch_source(
+    dependency: &IncludeDependency,
+    executor: &Executor,
+) -> Result<(), IncludeError> {
+    let mut executor = executor.fork_for_compile();
+    let include = IncludeBlock {
+        media_type: dependency.media_type.clone(),
+        select: dependency.select.clone(),
+        arguments: dependency.arguments.clone(),
+        ..IncludeBlock::new(dependency.identifier.clone())
+    };
+
+    let identifier = redirect_identifier(&dependency.identifier, &executor);
+    let (result, ..) = source_to_result(&identifier, &include, &mut executor).await;
+    result.map(|_| ())
+}
+
+/// Get the content from a resolved source identifier
+///
+/// The source that the content came from is recorded on each of its blocks
+/// (see [`crate::include_provenance`]).
+async fn source_to_content(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> (Option<Vec<Block>>, Vec<CompilationMessage>) {
+    let identifier = redirect_identifier(identifier, executor);
+    let (result, mut messages) = chain_to_source(&identifier, include, executor).await;
+
+    let content = match result {
+        Ok((source, blocks)) => {
+            let mut blocks = limit_blocks(apply_filter(blocks, executor), executor, &mut messages);
+            if let Some(validator) = &executor.include_validator {
+                validate_blocks(&blocks, validator, &mut messages);
+            }
+            record_source(&mut blocks, &source);
+            Some(blocks)
+        }
+        Err(error) => {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Error,
+                error.to_string(),
+            ));
+            if executor.strict_includes && executor.include_failure.is_none() {
+                executor.include_failure = Some(error);
+            }
+            None
+        }
+    };
+
+    (content, messages)
+}
+
+/// Remove the outputs of code chunks, at all levels of nesting, from blocks
+///
+/// Applied after decoding (and caching), so the cached content retains outputs.
+fn strip_outputs(blocks: &mut Vec<Block>) {
+    strip(
+        blocks,
+        StripTargets::new(Vec::new(), Vec::new(), vec!["CodeChunk.outputs".into()]),
+    );
+}
+
+/// Remove the blocks that do not pass the executor's include filter, if any
+fn apply_filter(mut blocks: Vec<Block>, executor: &Executor) -> Vec<Block> {
+    if let Some(filter) = &executor.include_filter {
+        filter_blocks(&mut blocks, filter);
+    }
+    blocks
+}
+
+/// Truncate the content of an include to the block limits of the executor, if any
+///
+/// The content is truncated to the limit for a single include, or to the number of blocks
+/// remaining within the limit for all includes in the compile, whichever is lower. A warning
+/// is added to `messages` if the content is truncated.
+fn limit_blocks(
+    mut blocks: Vec<Block>,
+    executor: &Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Vec<Block> {
+    let total = executor.include_total_blocks.load(Ordering::Relaxed);
+    let per_include = executor
+        .include_max_blocks
+        .map(|max| (max, format!("{max} blocks per include")));
+    let for_all = executor.include_max_total_blocks.map(|max| {
+        (
+            max.saturating_sub(total),
+            format!("{max} blocks for all includes"),
+        )
+    });
+
+    if let Some((limit, reason)) = [per_include, for_all]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(limit, ..)| *limit)
+        && blocks.len() > limit
+    {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Warning,
+            format!(
+                "Content truncated to {limit} of {} blocks because of the limit of {reason}",
+                blocks.len()
+            ),
+        ));
+        blocks.truncate(limit);
+    }
+
+    executor
+        .include_total_blocks
+        .fetch_add(blocks.len(), Ordering::Relaxed);
+
+    blocks
+}
+
+/// Get the content from a resolved source identifier which may be a fallback chain
+///
+/// Each alternative of the chain is tried in order until one succeeds. The failures
+/// of earlier alternatives are returned as warnings. If all alternatives fail then
+/// the error of the last is returned.
+async fn chain_to_result(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> (Result<Vec<Block>, IncludeError>, Vec<CompilationMessage>) {
+    let (result, messages) = chain_to_source(identifier, include, executor).await;
+    (result.map(|(.., blocks)| blocks), messages)
+}
+
+/// Get the content from a resolved source identifier which may be a fallback chain,
+/// along with the identifier of the alternative that the content came from
+async fn chain_to_source(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> (
+    Result<(String, Vec<Block>), IncludeError>,
+    Vec<CompilationMessage>,
+) {
+    let alternatives = source_alternatives(identifier);
+    let [preferred @ .., last] = alternatives.as_slice() else {
+        return alternative_to_source(identifier, include, executor).await;
+    };
+    if preferred.is_empty() {
+        return alternative_to_source(identifier, include, executor).await;
+    }
+
+    let mut messages = Vec::new();
+    for alternative in preferred {
+        let (result, alternative_messages) =
+            alternative_to_source(alternative, include, executor).await;
+        match result {
+            Ok(content) => {
+                messages.extend(alternative_messages);
+                return (Ok(content), messages);
+            }
+            Err(error) => messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("Falling back from source `{alternative}`: {error}"),
+            )),
+        }
+    }
+
+    let (result, last_messages) = alternative_to_source(last, include, executor).await;
+    messages.extend(last_messages);
+
+    (result, messages)
+}
+
+/// Get the content from a single alternative of a chain of sources, along with its identifier
+async fn alternative_to_source(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> (
+    Result<(String, Vec<Block>), IncludeError>,
+    Vec<CompilationMessage>,
+) {
+    let (result, messages) = alternative_to_result(identifier, include, executor).await;
+    (
+        result.map(|blocks| (identifier.to_string(), blocks)),
+        messages,
+    )
+}
+
+/// Get the content from a single alternative of a chain of sources
+///
+/// Rebases relative paths in the content onto the alternative if that is enabled. This is
+/// done here, rather than for the chain as a whole, so that the paths are rebased onto the
+/// alternative that the content actually came from. Also removes the outputs of code chunks
+/// if the include has a truthy `strip-outputs` argument.
+async fn alternative_to_result(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> (Result<Vec<Block>, IncludeError>, Vec<CompilationMessage>) {
+    let (mut result, mut messages) = source_to_result(identifier, include, executor).await;
+
+    if executor.include_rebase_paths
+        && let (Ok(blocks), Some(home)) = (&mut result, executor.directory_stack.first())
+    {
+        rebase_paths(blocks, identifier, home);
+    }
+
+    if let Ok(blocks) = &mut result
+        && truthy_argument(
+            &include.arguments,
+            STRIP_OUTPUTS_ARGUMENT,
+            executor,
+            &mut messages,
+        )
+        .await
+    {
+        strip_outputs(blocks);
+    }
+
+    (result, messages)
+}
+
+/// Get the content from a resolved source identifier, or a structured error
+///
+/// Also returns any warnings or other messages arising from getting the content.
+async fn source_to_result(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+) -> (Result<Vec<Block>, IncludeError>, Vec<CompilationMessage>) {
+    let mut messages = Vec::new();
+
+    // Refuse to fetch remote sources when executing within restricted bounds, regardless
+    // of whether there is cached content, so that includes honor the same sandboxing as
+    // the rest of execution
+    let bounds = executor.kernels.read().await.bounds();
+    if matches!(bounds, ExecutionBounds::Box) && is_network(identifier) {
+        let error = IncludeError::Forbidden(format!(
+            "Remote source `{identifier}` was not fetched because network access is not \
+             allowed within `{bounds}` execution bounds"
+        ));
+        return (Err(error), messages);
+    }
+
+    let media_type = &evaluate_media_type(include, executor, &mut messages).await;
+    let select = &evaluate_select(include, executor, &mut messages).await;
+
+    // Reject sources whose media type is not allowed, before fetching them
+    if let Some(allowed) = &executor.include_allowed_media_types
+        && let Err(error) = check_media_type(media_type, identifier, allowed)
+    {
+        return (Err(error), messages);
+    }
+
+    let decode_options = DecodeOptions {
+        media_type: media_type.clone(),
+        // Set format to None so that the format of the executor's decode options
+        // (that of the executor's document) is not used when decoding
+        format: None,
+        ..executor.decode_options.clone().unwrap_or_default()
+    };
+    let decode_options = decode_overrides(&include.arguments, executor, &mut messages)
+        .await
+        .apply(decode_options);
+
+    // Include the source as an attachment, rather than decoding it, if the `raw` argument is truthy
+    if truthy_argument(&include.arguments, RAW_ARGUMENT, executor, &mut messages).await {
+        if select
+            .as_ref()
+            .is_some_and(|select| !select.trim().is_empty())
+        {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                "Selector is ignored for sources included as raw attachments".to_string(),
+            ));
+        }
+
+        let result = source_to_attachment(identifier, media_type, include, executor, &mut messages)
+            .await
+            .map(|file| vec![Block::File(file)])
+            .map_err(IncludeError::classify);
+
+        return (result, messages);
+    }
+
+    // Bibliography sources are decoded into references which are added to the document's
+    // bibliography, so that citations can be resolved against them, rather than rendered
+    if let Some(format) = bibliography_format(media_type, &identifier_path(identifier)) {
+        let result = match source_text(identifier, include, executor, &mut messages).await {
+            Ok(text) => bytes_to_references(text.as_bytes(), format).await,
+            Err(error) => Err(error),
+        };
+        let result = result
+            .map(|references| {
+                add_references(references, identifier, executor, &mut messages);
+                Vec::new()
+            })
+            .map_err(IncludeError::classify);
+
+        return (result, messages);
+    }
+
+    // Tagged regions are selected from the text of the source before it is decoded
+    if let Some(tag) = select
+        .as_ref()
+        .and_then(|select| select.trim().strip_prefix(TAG_PREFIX))
+    {
+        let result = tag_to_blocks(
+            identifier,
+            tag.trim(),
+            decode_options,
+            include,
+            executor,
+            &mut messages,
+        )
+        .await
+        .map_err(IncludeError::classify);
+
+        return (result, messages);
+    }
+
+    // Regions delimited by marker lines are also selected from the text of the source
+    if let Some(markers) = select
+        .as_ref()
+        .and_then(|select| select.trim().strip_prefix(MARKERS_PREFIX))
+    {
+        let result = markers_to_blocks(
+            identifier,
+            markers,
+            decode_options,
+            include,
+            executor,
+            &mut messages,
+        )
+        .await
+        .map_err(IncludeError::classify);
+
+        return (result, messages);
+    }
+
+    // XPath expressions select elements from the XML of XML-family sources (e.g. JATS)
+    // before it is decoded
+    if let Some(xpath) = select.as_deref().filter(|select| is_xpath(select))
+        && source_format(media_type, identifier).is_xml_flavor()
+    {
+        let result = xpath_to_blocks(
+            identifier,
+            xpath.trim(),
+            decode_options,
+            include,
+            executor,
+            &mut messages,
+        )
+        .await
+        .map_err(IncludeError::classify);
+
+        return (result, messages);
+    }
+
+    // JSON Lines and diagram sources are decoded here (there are no codecs for them), as are
+    // text sources with an `encoding` argument (which need to be transcoded before decoding)
+    // and in-memory sources registered with the executor, otherwise remote sources are fetched
+    // here (so that conditional requests can be made) unless a codec specifically handles the
+    // URL (e.g. GitHub, arXiv)
+    let result = if is_jsonl(media_type, &identifier_path(identifier)) {
+        jsonl_to_blocks(identifier, include, executor, &mut messages).await
+    } else if let Some(diagram) = diagram_kind(media_type, &identifier_path(identifier)) {
+        diagram_to_blocks(identifier, diagram, include, executor, &mut messages).await
+    } else if has_argument(&include.arguments, ENCODING_ARGUMENT)
+        && is_transcodable(&source_format(media_type, identifier))
+    {
+        text_to_blocks(identifier, decode_options, include, executor, &mut messages).await
+    } else if let Some(source) = executor.include_sources.get(identifier).cloned() {
+        memory_to_blocks(identifier, source, decode_options).await
+    } else if is_network(identifier) && stencila_codecs::codec_for_identifier(identifier).is_none()
+    {
+        // Any fragment is not part of the resource, so is removed before fetching, and
+        // for HTML, is used to select the content under the element with that id
+        let (url, fragment) = split_fragment(identifier);
+        let mut fetch_options = fetch_options(&include.arguments, executor, &mut messages).await;
+        fetch_options.accept = media_type.as_deref().and_then(accept_header);
+        let url = apply_version(url, include, executor, &mut messages, &mut fetch_options).await;
+        let variant = ContentVariant::new(media_type, &decode_options, select, &include.arguments);
+        let result = remote_to_blocks(
+            &url,
+            media_type,
+            decode_options,
+            variant,
+            &fetch_options,
+            executor,
+            &mut messages,
+        )
+        .await;
+        match (result, fragment) {
+            (Ok((blocks, Some(Format::Html))), Some(fragment)) => {
+                Ok(fragment_content(blocks, fragment, executor, &mut messages))
+            }
+            (result, ..) => result.map(|(blocks, ..)| blocks),
+        }
+    } else {
+        let variant = ContentVariant::new(media_type, &decode_options, select, &include.arguments);
+        identifier_to_blocks(identifier, media_type, decode_options, variant, executor).await
+    };
+
+    // Apply the empty include policy before any selection (which has its own
+    // warnings for when nothing is selected)
+    if let Ok(blocks) = &result
+        && blocks.is_empty()
+        && let Some(level) = executor.include_empty_policy.message_level()
+    {
+        messages.push(CompilationMessage::new(
+            level,
+            format!("Source `{identifier}` has no content"),
+        ));
+    }
+
+    let result = match (result.map_err(IncludeError::classify), select) {
+        // Sub-select from the content if there is a `select` selector
+        (Ok(blocks), Some(select)) if !select.trim().is_empty() => {
+            select_content(&blocks, select, &mut messages).inspect(|selected| {
+                // Authors often think that a selection has failed when it selects everything
+                if !selected.is_empty() && *selected == blocks {
+                    messages.push(CompilationMessage::new(
+                        MessageLevel::Info,
+                        format!(
+                            "Selector `{}` selected the whole source, it may be too broad",
+                            select.trim()
+                        ),
+                    ));
+                }
+            })
+        }
+        (result, ..) => result,
+    };
+
+    (result, messages)
+}
+
+/// Check that the media type of a source is one of those allowed
+///
+/// The media type is the explicit media type of the include or, if none, that inferred from
+/// the path of the source. Allowed media types may also be format names (e.g. `md`).
+fn check_media_type(
+    media_type: &Option<String>,
+    identifier: &str,
+    allowed: &[String],
+) -> Result<(), IncludeError> {
+    let path = identifier_path(identifier);
+    let path = path.strip_suffix(GZIP_EXTENSION).unwrap_or(&path);
+    let format = source_format(media_type, path);
+
+    let name = match media_type {
+        Some(media_type) => media_type.clone(),
+        None if format.is_unknown() => {
+            return Err(IncludeError::Forbidden(format!(
+                "Source `{identifier}` was not included because its media type could not be \
+                 determined and only some media types are allowed"
+            )));
+        }
+        None => format.media_type(),
+    };
+
+    let is_allowed = !format.is_unknown()
+        && allowed.iter().any(|allowed| {
+            Format::from_media_type(resolve_media_type(allowed))
+                .is_ok_and(|allowed| allowed == format)
+        });
+    if is_allowed {
+        return Ok(());
+    }
+
+    Err(IncludeError::Forbidden(format!(
+        "Source `{identifier}` was not included because media type `{name}` is not allowed \
+         (allowed: {})",
+        allowed
+            .iter()
+            .map(|allowed| format!("`{allowed}`"))
+            .join(", ")
+    )))
+}
+
+/// Read the bytes of a source and create a [`File`] referencing it as an attachment
+///
+/// The source is not decoded, so this can be used for sources that have no codec
+/// (e.g. `.zip` files) or that should be linked to rather than included as content
+/// (e.g. a `.pdf` for download). The media type is the `media_type` of the include,
+/// the `Content-Type` of the response for remote sources, the media type for the
+/// extension of the source, or that sniffed from the bytes, in that order.
+async fn source_to_attachment(
+    identifier: &str,
+    media_type: &Option<String>,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<File> {
+    let (bytes, content_type) = source_bytes(identifier, include, executor, messages).await?;
+    let path = identifier_path(identifier);
+    let url = is_remote(identifier).then(|| identifier.to_string());
+
+    let name = PathBuf::from(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| include.source.trim().to_string());
+
+    let media_type = media_type
+        .clone()
+        .or_else(|| {
+            content_type
+                .and_then(|content_type| content_type.split(';').next().map(String::from))
+                .map(|content_type| content_type.trim().to_string())
+                .filter(|content_type| !content_type.is_empty())
+        })
+        .or_else(|| {
+            let format = Format::from_path(&PathBuf::from(&path));
+            (!is_ambiguous(&format)).then(|| format.media_type())
+        })
+        .or_else(|| sniff_format(&bytes).map(|format| format.media_type()))
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    Ok(File {
+        name,
+        path: identifier.to_string(),
+        media_type: Some(media_type),
+        size: Some(bytes.len() as u64),
+        options: Box::new(FileOptions {
+            url,
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Read the bytes of a source, without decoding them
+///
+/// Returns the bytes and, for remote sources, the `Content-Type` header of the response.
+async fn source_bytes(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<(Vec<u8>, Option<String>)> {
+    match executor.include_sources.get(identifier) {
+        Some(IncludeSource::Bytes(bytes)) => return Ok((bytes.clone(), None)),
+        Some(IncludeSource::Blocks(..)) => {
+            bail!("In-memory source `{identifier}` is blocks so its bytes can not be read")
+        }
+        None => {}
+    }
+
+    if !is_remote(identifier) {
+        return Ok((tokio::fs::read(identifier).await?, None));
+    }
+
+    if executor.include_cache_policy == IncludeCachePolicy::Offline && is_network(identifier) {
+        return Err(offline_error(identifier).into());
+    }
+
+    let mut fetch_options = fetch_options(&include.arguments, executor, messages).await;
+    let url = apply_version(identifier, include, executor, messages, &mut fetch_options).await;
+    match fetch_remote(&url, None, &mut None, &fetch_options).await? {
+        Fetched::Content {
+            bytes,
+            content_type,
+            ..
+        } => Ok((bytes, content_type)),
+        Fetched::NotModified => {
+            bail!("Server responded not modified to an unconditional request for {identifier}")
+        }
+    }
+}
+
+/// Read the text of a source, transcoding it to UTF-8 if there is an `encoding` argument
+///
+/// If the source has byte sequences that are not valid in the encoding then they are
+/// replaced and a warning is added to `messages`.
+async fn source_text(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<String> {
+    let encoding = encoding_argument(&include.arguments, executor).await?;
+    let (bytes, ..) = source_bytes(identifier, include, executor, messages).await?;
+
+    let Some(encoding) = encoding else {
+        return Ok(String::from_utf8_lossy(&bytes).to_string());
+    };
+
+    let (text, malformed) = transcode(&bytes, encoding);
+    if malformed {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Warning,
+            format!(
+                "Source `{identifier}` has bytes that are not valid `{}` so they were replaced",
+                encoding.name()
+            ),
+        ));
+    }
+
+    Ok(text)
+}
+
+/// Describe the media type used to decode the source of an include, for its trace
+fn describe_media_type(include: &IncludeBlock, identifier: &str) -> String {
+    if let Some(media_type) = &include.media_type {
+        return format!("`{media_type}` (explicit)");
+    }
+
+    let path = identifier_path(identifier);
+    let path = path.strip_suffix(GZIP_EXTENSION).unwrap_or(&path);
+    let format = Format::from_path(&PathBuf::from(path));
+    if is_ambiguous(&format) {
+        "determined when decoded (e.g. from the `Content-Type` header or by sniffing)".to_string()
+    } else {
+        format!("`{}` (inferred from extension)", format.media_type())
+    }
+}
+
+/// Get the format to decode sources as when it can not otherwise be determined
+///
+/// See [`Executor::set_include_default_media_type`].
+fn default_format(executor: &Executor) -> Option<Format> {
+    executor
+        .include_default_media_type
+        .as_deref()
+        .and_then(|media_type| Format::from_media_type(&resolve_media_type(media_type)).ok())
+        .filter(|format| !is_ambiguous(format))
+}
+
+/// Get the format of a source, based on its media type or, if none, its path
+fn source_format(media_type: &Option<String>, identifier: &str) -> Format {
+    media_type
+        .as_ref()
+        .and_then(|media_type| Format::from_media_type(media_type).ok())
+        .unwrap_or_else(|| Format::from_path(&PathBuf::from(identifier_path(identifier))))
+}
+
+/// Decode the text of a source, after transcoding it from the encoding of the `encoding` argument
+async fn text_to_blocks(
+    identifier: &str,
+    decode_options: DecodeOptions,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>> {
+    let text = source_text(identifier, include, executor, messages).await?;
+    let format = source_format(&decode_options.media_type, identifier);
+    let node = bytes_to_node(
+        text.as_bytes(),
+        DecodeOptions {
+            format: Some(format),
+            ..decode_options
+        },
+    )
+    .await?;
+
+    node_to_blocks(node)
+}
+
+/// Decode an in-memory source registered with the executor
+async fn memory_to_blocks(
+    identifier: &str,
+    source: IncludeSource,
+    decode_options: DecodeOptions,
+) -> Result<Vec<Block>> {
+    let bytes = match source {
+        IncludeSource::Blocks(blocks) => return replicate(&blocks),
+        IncludeSource::Bytes(bytes) => bytes,
+    };
+
+    let format = source_format(&decode_options.media_type, identifier);
+    let node = bytes_to_node(
+        &bytes,
+        DecodeOptions {
+            format: Some(format),
+            ..decode_options
+        },
+    )
+    .await?;
+
+    node_to_blocks(node)
+}
+
+/// Add references from a bibliography source to the executor's bibliography
+///
+/// As for the references of an article, references are keyed by both their id and DOI.
+/// A reference whose id is the same as that of a different reference already in the
+/// bibliography (e.g. one of the article's own references) is not added and a warning
+/// is added to `messages`.
+fn add_references(
+    references: Vec<Reference>,
+    identifier: &str,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) {
+    for reference in references {
+        if let Some(id) = &reference.id {
+            match executor.bibliography.get(id) {
+                Some(existing) if *existing == reference => continue,
+                Some(..) => {
+                    messages.push(CompilationMessage::new(
+                        MessageLevel::Warning,
+                        format!(
+                            "Reference `{id}` in `{identifier}` has the same id as another \
+                             reference in the document so was ignored"
+                        ),
+                    ));
+                    continue;
+                }
+                None => {
+                    executor.bibliography.insert(id.into(), reference.clone());
+                }
+            }
+        }
+
+        if let Some(doi) = &reference.doi
+            && !executor.bibliography.contains_key(doi)
+        {
+            executor.bibliography.insert(doi.into(), reference);
+        }
+    }
+}
+
+/// Add the references from the bibliography sources of includes within content
+///
+/// Called before the content of an article is compiled so that citations can be resolved
+/// against references in bibliography sources that are included after them. Arguments of the
+/// includes (e.g. `if`) are not evaluated. Any messages are reported when each include is compiled.
+pub(crate) async fn add_bibliographies<T: WalkNode>(content: &T, executor: &mut Executor) {
+    let Some(home) = executor.directory_stack.last().cloned() else {
+        return;
+    };
+
+    for dependency in include_dependencies(&home, content) {
+        let media_type = dependency.media_type.as_deref().map(resolve_media_type);
+        if bibliography_format(&media_type, &identifier_path(&dependency.identifier)).is_none() {
+            continue;
+        }
+
+        let include = IncludeBlock {
+            media_type,
+            ..IncludeBlock::new(dependency.identifier.clone())
+        };
+        source_to_result(&dependency.identifier, &include, executor).await;
+    }
+}
+
+/// Get the path of an identifier: the path of the URL for remote sources
+fn identifier_path(identifier: &str) -> String {
+    if is_remote(identifier) {
+        Url::parse(identifier)
+            .map(|url| url.path().to_string())
+            .unwrap_or_else(|_| identifier.to_string())
+    } else {
+        identifier.to_string()
+    }
+}
+
+/// Decode a JSON Lines source into a datatable, with a row for each line
+///
+/// Malformed lines are skipped, with a warning counting them, and the number of rows
+/// is capped by any `limit` argument (e.g. `--limit=100`).
+async fn jsonl_to_blocks(
+    identifier: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>> {
+    let limit = limit_argument(&include.arguments, executor, messages).await;
+
+    // Stream local files, unless they need to be transcoded, to reduce peak memory usage
+    let (datatable, skipped) = if !is_remote(identifier)
+        && !executor.include_sources.contains_key(identifier)
+        && !has_argument(&include.arguments, ENCODING_ARGUMENT)
+    {
+        read_jsonl(identifier, limit).await?
+    } else {
+        let text = source_text(identifier, include, executor, messages).await?;
+        jsonl_to_datatable(&text, limit)
+    };
+    if skipped > 0 {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Warning,
+            format!(
+                "Skipped {skipped} malformed line{} of JSON Lines source",
+                if skipped == 1 { "" } else { "s" }
+            ),
+        ));
+    }
+
+    Ok(vec![Block::Datatable(datatable)])
+}
+
+/// Decode the region of a source with a tag e.g. `tag::setup[]`
+///
+/// If the source has no region with the tag, then a warning listing the
+/// tags that it does have is added to `messages`.
+async fn tag_to_blocks(
+    identifier: &str,
+    tag: &str,
+    decode_options: DecodeOptions,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>> {
+    if tag.is_empty() {
+        bail!("Expected tag name in selector `{TAG_PREFIX}`")
+    }
+
+    let text = source_text(identifier, include, executor, messages).await?;
+
+    let region = match tagged_region(&text, tag) {
+        Ok(region) => region,
+        Err(tags) => {
+            let available = if tags.is_empty() {
+                "source has no tags".to_string()
+            } else {
+                format!(
+                    "available tags: {}",
+                    tags.iter().map(|tag| format!("`{tag}`")).join(", ")
+                )
+            };
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("Tag `{tag}` not found in source ({available})"),
+            ));
+            return Ok(Vec::new());
+        }
+    };
+
+    let format = source_format(&decode_options.media_type, identifier);
+    let node = bytes_to_node(
+        region.as_bytes(),
+        DecodeOptions {
+            format: Some(format),
+            ..decode_options
+        },
+    )
+    .await?;
+
+    node_to_blocks(node)
+}
+
+/// Decode the first region of a source between lines matching start and end markers
+///
+/// If the start or end marker is not matched, then a warning is added to `messages`
+/// and there is no content.
+async fn markers_to_blocks(
+    identifier: &str,
+    markers: &str,
+    decode_options: DecodeOptions,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>> {
+    let markers = Markers::parse(markers)?;
+
+    let text = source_text(identifier, include, executor, messages).await?;
+
+    let region = match markers.region(&text) {
+        Ok(region) => region,
+        Err(unmatched) => {
+            let marker = match unmatched {
+                Unmatched::Start => "Start marker not found in source",
+                Unmatched::End => "End marker not found after start marker in source",
+            };
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("{marker} so nothing was included"),
+            ));
+            return Ok(Vec::new());
+        }
+    };
+
+    let format = source_format(&decode_options.media_type, identifier);
+    let node = bytes_to_node(
+        region.as_bytes(),
+        DecodeOptions {
+            format: Some(format),
+            ..decode_options
+        },
+    )
+    .await?;
+
+    node_to_blocks(node)
+}
+
+/// Decode the elements of an XML source that match an XPath expression
+///
+/// An invalid expression is an error. If no elements match then an error message is
+/// added to `messages` and there is no content.
+async fn xpath_to_blocks(
+    identifier: &str,
+    xpath: &str,
+    decode_options: DecodeOptions,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>> {
+    let expression = match xpath.parse::<XPath>() {
+        Ok(expression) => expression,
+        Err(error) => bail!("Invalid XPath `{xpath}`: {error}"),
+    };
+
+    let text = source_text(identifier, include, executor, messages).await?;
+
+    let Some(fragment) = xpath_fragment(&text, &expression)? else {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Error,
+            format!("XPath `{xpath}` did not match any elements in source"),
+        ));
+        return Ok(Vec::new());
+    };
+
+    let format = source_format(&decode_options.media_type, identifier);
+    let node = bytes_to_node(
+        fragment.as_bytes(),
+        DecodeOptions {
+            format: Some(format),
+            ..decode_options
+        },
+    )
+    .await?;
+
+    node_to_blocks(node)
+}
+
+/// Decode a diagram source into an image of the diagram
+///
+/// If there is no renderer for the kind of diagram, then it is included as a code
+/// block with a message noting that.
+async fn diagram_to_blocks(
+    identifier: &str,
+    diagram: Diagram,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>> {
+    let text = source_text(identifier, include, executor, messages).await?;
+
+    let (block, rendered) = diagram_to_block(&text, diagram);
+    if !rendered {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Info,
+            format!("No renderer is available for {diagram} diagrams so included as code"),
+        ));
+    }
+
+    Ok(vec![block])
+}
+
+/// Select a subset of the content of a source using the `select` of an include
+fn select_content(
+    blocks: &[Block],
+    select: &str,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<Vec<Block>, IncludeError> {
+    // Select everything after a heading e.g. `after:## Notes`
+    if let Some(heading) = select.trim().strip_prefix(AFTER_PREFIX) {
+        return match heading.parse::<AfterHeading>() {
+            Ok(after) => match after.select(blocks) {
+                Some(selected) => Ok(selected),
+                None => {
+                    messages.push(CompilationMessage::new(
+                        MessageLevel::Warning,
+                        format!("Heading `{}` not found in source", heading.trim()),
+                    ));
+                    Ok(Vec::new())
+                }
+            },
+            Err(error) => Err(IncludeError::ParseError(error.to_string())),
+        };
+    }
+
+    // Select the first block containing some text e.g. `text:installation`
+    if let Some(text) = select.trim().strip_prefix(TEXT_PREFIX) {
+        return match text.parse::<TextMatch>() {
+            Ok(matcher) => match matcher.select(blocks) {
+                Some(selected) => Ok(selected),
+                None => {
+                    messages.push(CompilationMessage::new(
+                        MessageLevel::Warning,
+                        format!("Text `{}` not found in source", text.trim()),
+                    ));
+                    Ok(Vec::new())
+                }
+            },
+            Err(error) => Err(IncludeError::ParseError(error.to_string())),
+        };
+    }
+
+    // Select the sections under headings e.g. `## Methods; ## Results`
+    if let Some(sections) = HeadingSections::parse(select) {
+        let (selected, missing) = sections.select(blocks);
+        for heading in missing {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("Heading `{heading}` not found in source"),
+            ));
+        }
+        return Ok(selected);
+    }
+
+    // Select a range of blocks between two ids e.g. `#intro .. #methods`
+    if let Some(range) = IdRange::parse(select) {
+        return match range.select(blocks) {
+            Ok(selected) => Ok(selected),
+            Err(error) => {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Error,
+                    format!("Range `{}` could not be selected: {error}", select.trim()),
+                ));
+                Ok(Vec::new())
+            }
+        };
+    }
+
+    match select.parse::<Selector>() {
+        Ok(selector) => {
+            let (selected, wrapped) = selector.select_wrapped(blocks);
+            if wrapped > 0 {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Info,
+                    format!(
+                        "Wrapped {wrapped} inline node{} selected by `{select}` in paragraphs",
+                        if wrapped == 1 { "" } else { "s" }
+                    ),
+                ));
+            }
+
+            if selected.is_empty()
+                && let Some(slug) = HeadingSlug::parse(select)
+                && let Some((selected, count)) = slug.select(blocks)
+            {
+                // Fallback to the anchor slugs of headings e.g. `#installation`
+                if count > 1 {
+                    messages.push(CompilationMessage::new(
+                        MessageLevel::Warning,
+                        format!(
+                            "Slug `{}` matches {count} headings, selecting the first",
+                            select.trim()
+                        ),
+                    ));
+                }
+                return Ok(selected);
+            }
+
+            if selected.is_empty() && selector.has_status() && !has_execution_status(blocks) {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Warning,
+                    format!(
+                        "Selector `{select}` did not match any content because the source has no \
+                         execution status information (e.g. it has not been executed)"
+                    ),
+                ));
+                return Ok(selected);
+            }
+
+            if selected.is_empty() && selector.has_id() {
+                // Missing ids are an error (rather than a warning) since they are
+                // most likely a typo or a cell that has been removed from the source
+                let ids = block_ids(blocks);
+                let available = if ids.is_empty() {
+                    "no content has an id".to_string()
+                } else {
+                    format!(
+                        "available ids: {}",
+                        ids.iter().map(|id| format!("`{id}`")).join(", ")
+                    )
+                };
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Error,
+                    format!("Selector `{select}` did not match any content ({available})"),
+                ));
+            } else if selected.is_empty()
+                && let Some(position) = selector.position()
+            {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Warning,
+                    format!("Selector `{select}` has no match at position {position}"),
+                ));
+            } else if selected.is_empty() {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Warning,
+                    format!("Selector `{select}` did not match any content"),
+                ));
+            }
+            Ok(selected)
+        }
+        Err(error) => Err(IncludeError::ParseError(error.to_string())),
+    }
+}
+
+/// Split a URL into the URL without any fragment, and the fragment, if any
+fn split_fragment(url: &str) -> (&str, Option<&str>) {
+    match url.split_once('#') {
+        Some((url, fragment)) => (url, (!fragment.is_empty()).then_some(fragment)),
+        None => (url, None),
+    }
+}
+
+/// Select the content under the element with the id of a URL fragment
+///
+/// If no element has the id then a warning is added to `messages` and, depending upon
+/// the `exclude_on_missing_fragment` compile option, all or none of the content is returned.
+fn fragment_content(
+    blocks: Vec<Block>,
+    fragment: &str,
+    executor: &Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Vec<Block> {
+    if let Some(selected) = select_fragment(&blocks, fragment) {
+        return selected;
+    }
+
+    let exclude = executor
+        .compile_options
+        .as_ref()
+        .map(|opts| opts.exclude_on_missing_fragment)
+        .unwrap_or_default();
+
+    messages.push(CompilationMessage::new(
+        MessageLevel::Warning,
+        format!(
+            "No element with id `{fragment}` in source so {} the whole document",
+            if exclude { "excluding" } else { "including" }
+        ),
+    ));
+
+    if exclude { Vec::new() } else { blocks }
+}
+
+/// Decode blocks from an identifier, using the cache for unmodified local files
+///
+/// Cached content is only used if it is the same `variant` (see [`ContentVariant`]).
+async fn identifier_to_blocks(
+    identifier: &str,
+    media_type: &Option<String>,
+    decode_options: DecodeOptions,
+    variant: ContentVariant,
+    executor: &mut Executor,
+) -> Result<Vec<Block>> {
+    if executor.include_cache_policy == IncludeCachePolicy::Offline && is_network(identifier) {
+        return Err(offline_error(identifier).into());
+    }
+
+    let modified = tokio::fs::metadata(identifier)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.modified().ok());
+
+    if let Some(modified) = modified
+        && executor.include_cache_policy != IncludeCachePolicy::ForceFresh
+        && let Ok(cache) = executor.include_cache.lock()
+        && let Some(cached) = cache.content(identifier, &variant)
+        && cached.modified == Some(modified)
+    {
+        tracing::trace!("Using cached content for {identifier}");
+        executor.include_cache_hits += 1;
+        return replicate(&cached.blocks);
+    }
+
+    let node = if let Some(path) = identifier.strip_suffix(GZIP_EXTENSION) {
+        let bytes = gunzip(&tokio::fs::read(identifier).await?, identifier)?;
+        let format = media_type
+            .as_ref()
+            .and_then(|media_type| Format::from_media_type(media_type).ok())
+            .unwrap_or_else(|| Format::from_path(&PathBuf::from(path)));
+        let format = if is_ambiguous(&format) {
+            sniff_format(&bytes)
+                .or_else(|| default_format(executor))
+                .unwrap_or(format)
+        } else {
+            format
+        };
+        bytes_to_node(
+            &bytes,
+            DecodeOptions {
+                format: Some(format),
+                ..decode_options
+            },
+        )
+        .await?
+    } else if media_type.is_none()
+        && is_ambiguous(&Format::from_path(&PathBuf::from(identifier)))
+        && tokio::fs::metadata(identifier)
+            .await
+            .is_ok_and(|metadata| metadata.is_file())
+    {
+        // As a last resort, for files with a missing or unknown extension, sniff the
+        // format from the leading bytes of the file, or failing that, use the default
+        let bytes = tokio::fs::read(identifier).await?;
+        match sniff_format(&bytes).or_else(|| default_format(executor)) {
+            Some(format) => {
+                tracing::debug!("Decoding {identifier} as {format}");
+                bytes_to_node(
+                    &bytes,
+                    DecodeOptions {
+                        format: Some(format),
+                        ..decode_options
+                    },
+                )
+                .await?
+            }
+            None => stencila_codecs::from_identifier(identifier, Some(decode_options)).await?,
+        }
+    } else {
+        match stencila_codecs::from_identifier(identifier, Some(decode_options)).await {
+            Ok(node) => node,
+            Err(error) => {
+                // Codecs do not necessarily return an IO error for missing files so check here
+                if modified.is_none()
+                    && stencila_codecs::codec_for_identifier(identifier).is_none()
+                    && matches!(tokio::fs::try_exists(identifier).await, Ok(false))
+                {
+                    return Err(IncludeError::NotFound(error.to_string()).into());
+                }
+                return Err(error);
+            }
+        }
+    };
+    let blocks = node_to_blocks(node)?;
+
+    if modified.is_some()
+        && let Ok(mut cache) = executor.include_cache.lock()
+    {
+        cache.insert_content(
+            identifier,
+            CachedContent {
+                variant,
+                format: None,
+                modified,
+                blocks: blocks.clone(),
+            },
+        );
+    }
+
+    Ok(blocks)
+}
+
+/// The error for a remote source that can not be fetched because includes are offline
+fn offline_error(url: &str) -> IncludeError {
+    IncludeError::Network(format!(
+        "Remote source `{url}` was not fetched because includes are offline and it is not cached"
+    ))
+}
+
+/// Fetch and decode blocks from a remote URL, making a conditional request if cached
+///
+/// If the server returns a content type other than that requested in the `Accept`
+/// header (i.e. the media type of the include) then a warning is added to `messages`
+/// and the content is decoded from the returned type. Generic content types
+/// (e.g. `text/plain`) are assumed to not be negotiated and are ignored.
+async fn remote_to_blocks(
+    url: &str,
+    media_type: &Option<String>,
+    decode_options: DecodeOptions,
+    variant: ContentVariant,
+    fetch_options: &FetchOptions,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Result<(Vec<Block>, Option<Format>)> {
+    // Content fetched with a version header is cached separately from other versions
+    let key = match &fetch_options.version_header {
+        Some((name, value)) => format!("{url}#{name}={value}"),
+        None => url.to_string(),
+    };
+    let key = key.as_str();
+
+    // When offline, only use cached content of the same variant
+    if executor.include_cache_policy == IncludeCachePolicy::Offline {
+        if let Ok(cache) = executor.include_cache.lock()
+            && let Some(cached) = cache.content(key, &variant)
+        {
+            tracing::trace!("Using cached content for {url} (offline)");
+            executor.include_cache_hits += 1;
+            return Ok((replicate(&cached.blocks)?, cached.format.clone()));
+        }
+        return Err(offline_error(url).into());
+    }
+
+    // Only use validators if there is cached content, of the same variant, for them to
+    // validate, the content does not need to be verified against a checksum, and fresh
+    // content is not being forced
+    let validators = if fetch_options.sha256.is_some()
+        || executor.include_cache_policy == IncludeCachePolicy::ForceFresh
+    {
+        None
+    } else {
+        executor.include_cache.lock().ok().and_then(|cache| {
+            cache
+                .content(key, &variant)
+                .and(cache.validators(key).cloned())
+        })
+    };
+
+    // If enabled, check freshness using a `HEAD` request and use the cached
+    // content if unchanged. Errors are ignored (some servers do not support
+    // `HEAD` well) and fall through to the `GET` request.
+    if executor.include_head_check
+        && let Some(cached_validators) = &validators
+    {
+        match head_remote(url, fetch_options).await {
+            Ok(current) if current.matches(cached_validators) => {
+                if let Ok(cache) = executor.include_cache.lock()
+                    && let Some(cached) = cache.content(key, &variant)
+                {
+                    tracing::trace!("Using cached content for {url} (unchanged since HEAD)");
+                    executor.include_cache_hits += 1;
+                    return Ok((replicate(&cached.blocks)?, cached.format.clone()));
+                }
+            }
+            Ok(..) => {}
+            Err(error) => tracing::debug!("HEAD request for {url} failed: {error}"),
+        }
+    }
+
+    // Resume any previously interrupted download, and keep the bytes received
+    // if this download is also interrupted
+    let mut partial = executor
+        .include_cache
+        .lock()
+        .ok()
+        .and_then(|mut cache| cache.take_partial(key));
+    let fetched = fetch_remote(url, validators.as_ref(), &mut partial, fetch_options).await;
+    if let Some(partial) = partial
+        && let Ok(mut cache) = executor.include_cache.lock()
+    {
+        cache.insert_partial(key, partial);
+    }
+
+    let (bytes, content_type, content_encoding, validators) = match fetched? {
+        Fetched::NotModified => {
+            if let Ok(cache) = executor.include_cache.lock()
+                && let Some(cached) = cache.content(key, &variant)
+            {
+                tracing::trace!("Using cached content for {url}");
+                executor.include_cache_hits += 1;
+                return Ok((replicate(&cached.blocks)?, cached.format.clone()));
+            }
+            bail!("Server responded not modified but there is no cached content for {url}")
+        }
+        Fetched::Content {
+            bytes,
+            content_type,
+            content_encoding,
+            validators,
+        } => (bytes, content_type, content_encoding, validators),
+    };
+
+    let path = Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_else(|_| url.to_string());
+
+    // Decompress if gzipped, as indicated by the `Content-Encoding` or
+    // `Content-Type` headers, or the extension of the URL path. In the latter
+    // cases the `Content-Type` is of the compressed bytes so is ignored.
+    let is_gzip_type = content_type.as_ref().is_some_and(|content_type| {
+        content_type.starts_with("application/gzip")
+            || content_type.starts_with("application/x-gzip")
+    });
+    let is_gzip_path = path.ends_with(GZIP_EXTENSION);
+    let is_gzip_encoding = content_encoding
+        .as_ref()
+        .is_some_and(|encoding| encoding.trim().eq_ignore_ascii_case("gzip"));
+    let (bytes, content_type, path) = if is_gzip_type || is_gzip_path || is_gzip_encoding {
+        let content_type = (!is_gzip_type && !is_gzip_path)
+            .then_some(content_type)
+            .flatten();
+        let path = path
+            .strip_suffix(GZIP_EXTENSION)
+            .map(String::from)
+            .unwrap_or(path);
+        (gunzip(&bytes, url)?, content_type, path)
+    } else {
+        (bytes, content_type, path)
+    };
+
+    // If the server did not return the requested media type then use what it did return
+    let returned = fetch_options
+        .accept
+        .as_ref()
+        .zip(content_type.as_ref())
+        .filter(|(accept, content_type)| !is_accepted(accept, content_type))
+        .and_then(|(accept, content_type)| {
+            let format = Format::from_content_type(content_type)
+                .ok()
+                .filter(|format| !is_ambiguous(format))?;
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Requested `{accept}` from {url} but the server returned `{content_type}` \
+                     so decoding that"
+                ),
+            ));
+            Some(format)
+        });
+
+    // Determine format from the media type, the `Content-Type` header, or the URL path,
+    // and as a last resort, by sniffing the leading bytes or using the default
+    let format = returned
+        .or_else(|| {
+            media_type
+                .as_ref()
+                .and_then(|media_type| Format::from_media_type(media_type).ok())
+                .filter(|format| !is_ambiguous(format))
+        })
+        .or_else(|| {
+            content_type
+                .as_ref()
+                .and_then(|content_type| Format::from_content_type(content_type).ok())
+                .filter(|format| !is_ambiguous(format))
+        })
+        .or_else(|| Some(Format::from_url(&path)).filter(|format| !is_ambiguous(format)))
+        .or_else(|| sniff_format(&bytes))
+        .or_else(|| default_format(executor))
+        .unwrap_or_else(|| Format::from_url(path));
+
+    let node = bytes_to_node(
+        &bytes,
+        DecodeOptions {
+            format: Some(format.clone()),
+            ..decode_options
+        },
+    )
+    .await?;
+    let blocks = node_to_blocks(node)?;
+
+    // Insert the validators first, so that any other variants of the content that they
+    // no longer validate are removed
+    if let Ok(mut cache) = executor.include_cache.lock() {
+        cache.insert_validators(key, validators);
+        cache.insert_content(
+            key,
+            CachedContent {
+                variant,
+                format: Some(format.clone()),
+                modified: None,
+                blocks: blocks.clone(),
+            },
+        );
+    }
+
+    Ok((blocks, Some(format)))
+}
+
+/// Get the options for fetching the remote source of an include
+///
+/// The settings of the executor are overridden by any `timeout` (e.g. `--timeout=60s`)
+/// or `retries` (e.g. `--retries=5`) arguments of the include. If the value of one of
+/// these arguments is invalid then a warning is added to `messages` and the setting
+/// of the executor is used.
+async fn fetch_options(
+    arguments: &Option<Vec<CallArgument>>,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> FetchOptions {
+    let mut options = FetchOptions {
+        proxy: executor.include_proxy.clone(),
+        user_agent: executor.include_user_agent.clone(),
+        version_header: None,
+        permits: executor.include_fetch_permits.clone(),
+        rate_limits: executor.include_rate_limits.clone(),
+        retries: executor.include_retries,
+        timeout: None,
+        sha256: None,
+        accept: None,
+    };
+
+    for arg in arguments
+        .iter()
+        .flatten()
+        .filter(|arg| arg.name == TIMEOUT_ARGUMENT || arg.name == RETRIES_ARGUMENT)
+    {
+        let name = &arg.name;
+        let value = match evaluate_argument(arg, executor).await {
+            Ok(value) => value,
+            Err(error) => {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Warning,
+                    format!("Unable to evaluate `{name}` argument so using default: {error}"),
+                ));
+                continue;
+            }
+        };
+
+        let valid = if name == TIMEOUT_ARGUMENT {
+            parse_timeout(&value).map(|timeout| options.timeout = Some(timeout))
+        } else {
+            parse_retries(&value).map(|retries| options.retries = retries)
+        };
+        if valid.is_none() {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Invalid, or out of range, `{name}` argument `{}` so using default",
+                    to_text(&value).trim()
+                ),
+            ));
+        }
+    }
+
+    // An invalid checksum is an error, rather than being ignored, and is kept
+    // so that the content fails verification rather than being unverified
+    if let Some(arg) = arguments
+        .iter()
+        .flatten()
+        .find(|arg| arg.name == SHA256_ARGUMENT)
+    {
+        let value = match evaluate_argument(arg, executor).await {
+            Ok(value) => to_text(&value).trim().to_string(),
+            Err(error) => {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Error,
+                    format!("Unable to evaluate `{SHA256_ARGUMENT}` argument: {error}"),
+                ));
+                options.sha256 = Some(String::new());
+                return options;
+            }
+        };
+        options.sha256 = match parse_sha256(&value) {
+            Some(sha256) => Some(sha256),
+            None => {
+                messages.push(CompilationMessage::new(
+                    MessageLevel::Error,
+                    format!(
+                        "Invalid `{SHA256_ARGUMENT}` argument `{value}`, expected 64 \
+                         hexadecimal characters"
+                    ),
+                ));
+                Some(value)
+            }
+        };
+    }
+
+    options
+}
+
+/// Apply any `version` argument of an include to the URL, and options, for fetching its source
+///
+/// How the version is requested (e.g. using a query parameter or a header) depends upon
+/// the versioning set for the host of the URL. Returns the URL to fetch. If the argument
+/// can not be evaluated, or is empty, then a warning is added to `messages` and the
+/// latest version of the source is fetched.
+async fn apply_version(
+    url: &str,
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+    options: &mut FetchOptions,
+) -> String {
+    let Some(arg) = include
+        .arguments
+        .iter()
+        .flatten()
+        .find(|arg| arg.name == VERSION_ARGUMENT)
+        .filter(|_| is_network(url))
+    else {
+        return url.to_string();
+    };
+
+    let version = match evaluate_argument(arg, executor).await {
+        Ok(value) => to_text(&value).trim().to_string(),
+        Err(error) => {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!("Unable to evaluate `version` argument so fetching latest: {error}"),
+            ));
+            return url.to_string();
+        }
+    };
+    if version.is_empty() {
+        messages.push(CompilationMessage::new(
+            MessageLevel::Warning,
+            "Empty `version` argument so fetching latest".to_string(),
+        ));
+        return url.to_string();
+    }
+
+    let (url, header) =
+        IncludeVersioning::for_url(url, &executor.include_versioning).apply(url, &version);
+    options.version_header = header;
+    url
+}
+
+/// Friendly names for formats that may be used for the `media_type` of an include
+///
+/// e.g. `format=markdown` rather than `format=text/markdown`
+const MEDIA_TYPE_ALIASES: &[(&str, Format)] = &[
+    ("markdown", Format::Markdown),
+    ("json", Format::Json),
+    ("csv", Format::Csv),
+    ("python", Format::Python),
+    ("r", Format::R),
+];
+
+/// Resolve the `media_type` of an include, mapping any alias to its canonical media type
+///
+/// Media types that are not aliases are returned unchanged.
+fn resolve_media_type(media_type: &str) -> String {
+    let name = media_type.trim().to_lowercase();
+    MEDIA_TYPE_ALIASES
+        .iter()
+        .find(|(alias, ..)| *alias == name)
+        .map(|(.., format)| format.media_type())
+        .unwrap_or_else(|| media_type.to_string())
+}
+
+/// Get the code of the `media_type` of an include, if it is an expression e.g. `{{fmt}}`
+fn media_type_expression(media_type: &str) -> Option<&str> {
+    media_type
+        .trim()
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        .map(str::trim)
+}
+
+/// Get the `media_type` of an include, evaluating it if it is an expression
+///
+/// Expressions (e.g. `format={{fmt}}`) are evaluated in the kernels. If the expression can not
+/// be evaluated, or does not evaluate to a non-empty string, then a warning is added to
+/// `messages` and `None` is returned so that the format is inferred (e.g. from the extension).
+async fn evaluate_media_type(
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Option<String> {
+    let media_type = include.media_type.as_deref()?;
+
+    let Some(code) = media_type_expression(media_type) else {
+        return Some(resolve_media_type(media_type));
+    };
+
+    match evaluate_code(code, &None, executor).await {
+        Ok(Node::String(value)) if !value.trim().is_empty() => {
+            Some(resolve_media_type(value.trim()))
+        }
+        Ok(value) => {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Format expression `{code}` evaluated to `{}` which is not a format \
+                     so inferring format",
+                    to_text(&value).trim()
+                ),
+            ));
+            None
+        }
+        Err(error) => {
+            messages.push(CompilationMessage::new(
+                MessageLevel::Warning,
+                format!(
+                    "Unable to evaluate format expression `{code}` so inferring format: {error}"
+                ),
+            ));
+            None
+        }
+    }
+}
+
+/// Whether the `select` of an include has any expressions e.g. `#{{id}}`
+fn has_select_expressions(select: &str) -> bool {
+    select
+        .find("{{")
+        .is_some_and(|start| select[start..].contains("}}"))
+}
+
+/// Get the `select` of an include, evaluating any expressions within it
+///
+/// Expressions (e.g. `select=#{{id}}` or `select=h2:{{section}}`) are evaluated in the kernels,
+/// and replaced with the text of their value, so that what is selected can depend on arguments
+/// or loop variables. If an expression can not be evaluated, or evaluates to null, then a
+/// warning is added to `messages` and `None` is returned so that all content is selected.
+async fn evaluate_select(
+    include: &IncludeBlock,
+    executor: &mut Executor,
+    messages: &mut Vec<CompilationMessage>,
+) -> Option<String> {
+    let select = include.select.as_deref()?;
+    if !has_select_expressions(select) {
+        return Some(select.to_string());
+    }
+
+    let mut evaluated = String::new();
+    let mut rest = select;
+    while let Some(start) = rest.find("{{")
+        && let Some(length) = rest[start + 2..].find("}}")
+    {
+        let code = rest[start + 2..start + 2 + length].trim();
+        evaluated.push_str(&rest[..start]);
+        rest = &rest[start + 2 + length + 2..];
+
+        let error = match evaluate_code(code, &None, executor).await {
+            Ok(Node::Null(..)) => "it evaluated to null".to_string(),
+            Ok(value) => {
+                evaluated.push_str(to_text(&value).trim());
+                continue;
+            }
+            Err(error) => error.to_string(),
+        };
+        messages.push(CompilationMessage::new(
+            MessageLevel::Warning,
+            format!(
+                "Unable to evaluate expression `{code}` in selector so selecting all content: \
+                 {error}"
+            ),
+        ));
+        return None;
+    }
+    evaluated.push_str(rest);
+
+    Some(evaluated)
+}
+
+/// The file extension of gzip compressed sources
+const GZIP_EXTENSION: &str = ".gz";
+
+/// Decompress gzip compressed bytes from a source
+fn gunzip(bytes: &[u8], identifier: &str) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    MultiGzDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .map_err(|error| eyre!("Unable to decompress gzipped source `{identifier}`: {error}"))?;
+    Ok(decompressed)
+}
+
+/// Decode the bytes fetched from a source into a node
+///
+/// Binary formats are written to a temporary file and decoded from there.
+async fn bytes_to_node(bytes: &[u8], options: DecodeOptions) -> Result<Node> {
+    let format = options.format.clone().unwrap_or_default();
+    if format.is_binary() {
+        let temp_dir = tempdir()?;
+        let path = temp_dir
+            .path()
+            .join(format!("download.{}", format.extension()));
+        tokio::fs::write(&path, bytes).await?;
+        stencila_codecs::from_path(&path, Some(options)).await
+    } else {
+        let text = String::from_utf8_lossy(bytes);
+        stencila_codecs::from_str(&text, Some(options)).await
+    }
+}
+
+/// Transform a decoded node into blocks
+fn node_to_blocks(node: Node) -> Result<Vec<Block>> {
+    node.try_into()
+        .map_err(|error| eyre!("Unable to convert source into block content: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use stencila_kernels::Kernels;
+    use stencila_schema::{
+        Array, Article, Cord, ForBlock, Primitive, StripScope,
+        shortcuts::{p, t},
+    };
+
+    use crate::{CompileOptions, include_provenance::included_from};
+
+    use super::*;
+
+    /// Create an executor, with kernels for the main execution bounds, for a home directory
+    fn test_executor(home: impl Into<PathBuf>) -> Executor {
+        let kernels = Arc::new(RwLock::new(Kernels::new_here(ExecutionBounds::Main)));
+        Executor::new(home.into(), kernels, None)
+    }
+
+    /// Remove the authorship, including the recorded source, of included content
+    fn unattributed(mut blocks: Vec<Block>) -> Vec<Block> {
+        strip(
+            &mut blocks,
+            StripTargets::scopes(vec![StripScope::Authors, StripScope::Provenance]),
+        );
+        blocks
+    }
+
+    #[tokio::test]
+    async fn dependent_arguments() -> Result<()> {
+        let mut executor = test_executor(PathBuf::new());
+        let kernels = executor.kernels.clone();
+
+        let arguments = Some(vec![
+            CallArgument {
+                name: "base".into(),
+                value: Some(Box::new(Node::Integer(10))),
+                ..Default::default()
+            },
+            CallArgument {
+                name: "scaled".into(),
+                code: Cord::from("base*2"),
+                is_expression: Some(true),
+                ..Default::default()
+            },
+        ]);
+
+        let messages = set_arguments(&arguments, &mut executor).await;
+        assert_eq!(messages, vec![]);
+
+        let kernels = kernels.read().await;
+        assert_eq!(kernels.get("base").await?, Some(Node::Integer(10)));
+        assert_eq!(kernels.get("scaled").await?, Some(Node::Integer(20)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restored_arguments() -> Result<()> {
+        let mut executor = test_executor("/memory");
+        let kernels = executor.kernels.clone();
+        let mut guard = kernels.write().await;
+        guard
+            .set(
+                "items",
+                &Node::Array(Array(vec![Primitive::Integer(1), Primitive::Integer(2)])),
+                None,
+            )
+            .await?;
+        guard.set("existing", &Node::Integer(0), None).await?;
+        drop(guard);
+
+        executor.set_include_source("/memory/part.md", "Part\n".into());
+        executor.set_include_restore_arguments(true);
+
+        let include = IncludeBlock {
+            arguments: Some(vec![
+                CallArgument {
+                    name: "scaled".into(),
+                    code: Cord::from("item*10"),
+                    is_expression: Some(true),
+                    ..Default::default()
+                },
+                CallArgument {
+                    name: "existing".into(),
+                    value: Some(Box::new(Node::Integer(1))),
+                    ..Default::default()
+                },
+            ]),
+            ..IncludeBlock::new("part.md".into())
+        };
+        let mut blocks = vec![Block::ForBlock(ForBlock::new(
+            "items".into(),
+            "item".into(),
+            vec![Block::IncludeBlock(include)],
+        ))];
+        executor.compile_prepare_execute(&mut blocks).await?;
+
+        let Block::ForBlock(for_block) = &blocks[0] else {
+            bail!("expected for block")
+        };
+        assert_eq!(for_block.iterations.as_ref().map(Vec::len), Some(2));
+
+        let kernels = kernels.read().await;
+        assert_eq!(kernels.get("scaled").await?, None);
+        assert_eq!(kernels.get("existing").await?, Some(Node::Integer(0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_url_sources() {
+        let mut executor = test_executor("/home/docs");
+
+        let (identifier, pop_dir) = resolve_source("file:///data/My%20Part.md", &mut executor);
+        assert_eq!(PathBuf::from(identifier), PathBuf::from("/data/My Part.md"));
+        assert!(!pop_dir);
+        assert_eq!(executor.directory_stack, vec![PathBuf::from("/home/docs")]);
+
+        let (identifier, pop_dir) = resolve_source("parts/part.md", &mut executor);
+        assert_eq!(
+            PathBuf::from(identifier),
+            PathBuf::from("/home/docs/parts/part.md")
+        );
+        assert!(pop_dir);
+    }
+
+    #[tokio::test]
+    async fn cached_variants() -> Result<()> {
+        let dir = tempdir()?;
+        tokio::fs::write(
+            dir.path().join("doc.md"),
+            "# One\n\nFirst\n\n# Two\n\nSecond\n",
+        )
+        .await?;
+
+        let executor = test_executor(dir.path());
+
+        let include = |select: &str, media_type: Option<&str>| IncludeBlock {
+            select: Some(select.into()),
+            media_type: media_type.map(String::from),
+            ..IncludeBlock::new("doc.md".into())
+        };
+        let texts = |blocks: Vec<Block>| blocks.iter().map(to_text).join(" ");
+
+        // The same source with different selections, and media types, are each got correctly,
+        // including when they are got from the cache, and do not serve each other's content
+        for _ in 0..2 {
+            let one = crate::materialize_include(&include("# One", None), &executor).await?;
+            assert!(texts(one).contains("First"));
+
+            let two = crate::materialize_include(&include("# Two", None), &executor).await?;
+            let two = texts(two);
+            assert!(two.contains("Second") && !two.contains("First"));
+
+            let plain =
+                crate::materialize_include(&include("text:second", Some("text/plain")), &executor)
+                    .await?;
+            assert!(texts(plain).contains("Second"));
+        }
+
+        // Each selection is a separate variant in the cache
+        let identifier = dir.path().join("doc.md").to_string_lossy().to_string();
+        let cache = executor.include_cache.lock().map_err(|_| eyre!("lock"))?;
+        let variant = |select: &str, media_type: Option<&str>| ContentVariant {
+            media_type: media_type.map(String::from),
+            select: Some(select.into()),
+            ..Default::default()
+        };
+        for (select, media_type) in [
+            ("# One", None),
+            ("# Two", None),
+            ("text:second", Some("text/plain")),
+        ] {
+            assert!(
+                cache
+                    .content(&identifier, &variant(select, media_type))
+                    .is_some()
+            );
+        }
+        assert!(
+            cache
+                .content(&identifier, &ContentVariant::default())
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unchanged_content() -> Result<()> {
+        let dir = tempdir()?;
+        tokio::fs::write(dir.path().join("part.md"), "One\n\nTwo\n").await?;
+
+        let mut executor = test_executor(dir.path());
+        executor.set_include_cache_policy(IncludeCachePolicy::ForceFresh);
+
+        let ids = |include: &IncludeBlock| -> Vec<Option<NodeId>> {
+            include
+                .content
+                .iter()
+                .flatten()
+                .map(Block::node_id)
+                .collect()
+        };
+
+        // Refetched but unchanged so the existing content is retained
+        let mut include = IncludeBlock::new("part.md".into());
+        executor.compile_include(&mut include).await;
+        let first = ids(&include);
+        executor.compile_include(&mut include).await;
+        assert_eq!(ids(&include), first);
+        assert!(include.options.compilation_digest.is_some());
+
+        tokio::fs::write(dir.path().join("part.md"), "One\n\nThree\n").await?;
+        executor.compile_include(&mut include).await;
+        assert_ne!(ids(&include), first);
+        assert_eq!(
+            include.content.map(unattributed),
+            Some(vec![p([t("One")]), p([t("Three")])])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn include_cycles() -> Result<()> {
+        let dir = tempdir()?;
+        tokio::fs::write(dir.path().join("a.md"), "A\n\n::: include b.md\n").await?;
+        tokio::fs::write(dir.path().join("b.md"), "B\n\n::: include a.md\n").await?;
+        tokio::fs::write(dir.path().join("c.md"), "C\n\n::: include c.md\n").await?;
+
+        let mut executor = test_executor(dir.path());
+
+        let mut blocks = vec![
+            Block::IncludeBlock(IncludeBlock::new("a.md".into())),
+            Block::IncludeBlock(IncludeBlock::new("c.md".into())),
+        ];
+        executor.compile(&mut blocks).await?;
+
+        let nested = |block: &Block| -> Option<IncludeBlock> {
+            let Block::IncludeBlock(include) = block else {
+                return None;
+            };
+            include
+                .content
+                .iter()
+                .flatten()
+                .find_map(|block| match block {
+                    Block::IncludeBlock(include) => Some(include.clone()),
+                    _ => None,
+                })
+        };
+        let path = |name: &str| format!("`{}`", dir.path().join(name).to_string_lossy());
+
+        // a.md -> b.md -> a.md
+        let Some(b) = nested(&blocks[0]) else {
+            bail!("expected nested include of b.md")
+        };
+        let Some(a) = nested(&Block::IncludeBlock(b.clone())) else {
+            bail!("expected nested include of a.md")
+        };
+        assert!(b.content.is_some());
+        assert!(a.content.is_none());
+        let messages = a.options.compilation_messages.unwrap_or_default();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].level, MessageLevel::Error);
+        assert!(messages[0].message.ends_with(&format!(
+            "{} -> {} -> {}",
+            path("a.md"),
+            path("b.md"),
+            path("a.md")
+        )));
+
+        // c.md -> c.md
+        let Some(c) = nested(&blocks[1]) else {
+            bail!("expected nested include of c.md")
+        };
+        assert!(c.content.is_none());
+        let messages = c.options.compilation_messages.unwrap_or_default();
+        assert!(
+            messages[0]
+                .message
+                .ends_with(&format!("{} -> {}", path("c.md"), path("c.md")))
+        );
+
+        assert!(executor.include_ancestors.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn flattened_includes() -> Result<()> {
+        let mut executor = test_executor("/memory");
+        executor.set_include_source("/memory/outer.md", "Outer\n\n::: include inner.md\n".into());
+        executor.set_include_source("/memory/inner.md", "Inner\n".into());
+        executor.compile_options = Some(CompileOptions {
+            flatten_includes: true,
+            ..Default::default()
+        });
+
+        let mut root = Node::Article(Article::new(vec![Block::IncludeBlock(IncludeBlock::new(
+            "outer.md".into(),
+        ))]));
+        executor.compile(&mut root).await?;
+
+        let Node::Article(Article { content, .. }) = &root else {
+            bail!("expected article")
+        };
+        let [Block::Excerpt(outer)] = content.as_slice() else {
+            bail!("expected a single excerpt")
+        };
+        assert_eq!(outer.source.url.as_deref(), Some("/memory/outer.md"));
+        let [Block::Paragraph(..), Block::Excerpt(inner)] = outer.content.as_slice() else {
+            bail!("expected a paragraph and nested excerpt")
+        };
+        assert_eq!(inner.source.url.as_deref(), Some("/memory/inner.md"));
+        assert_eq!(unattributed(inner.content.clone()), vec![p([t("Inner")])]);
+        assert_eq!(
+            included_from(&inner.content[0]).as_deref(),
+            Some("/memory/inner.md")
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,133 @@
+//! Listing of the includes that could not be resolved when compiling
+//!
+//! After a document has been compiled, the errors for each `IncludeBlock` (e.g. a source
+//! that was not found, or a selector that did not match) are in its `compilationMessages`.
+//! Collecting those includes gives a concise summary of the broken includes in a document
+//! (e.g. for a build report in CI) without needing to monitor the stream of diagnostics.
+
+use std::{collections::HashMap, path::Path};
+
+use serde::Serialize;
+
+use stencila_schema::{
+    Block, CompilationMessage, MessageLevel, NodeId, Visitor, WalkControl, WalkNode,
+};
+
+use crate::include_deps::include_dependencies;
+
+/// An `IncludeBlock` with errors from when it was last compiled
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenInclude {
+    /// The id of the `IncludeBlock`
+    pub node_id: NodeId,
+
+    /// The `source` of the `IncludeBlock`
+    pub source: String,
+
+    /// The resolved identifier of the source (a URL or file path)
+    ///
+    /// For a fallback chain of sources, the identifier of the first alternative.
+    pub identifier: Option<String>,
+
+    /// The compilation messages of the `IncludeBlock`, including any warnings
+    pub messages: Vec<CompilationMessage>,
+}
+
+/// Get the `IncludeBlock`s within a node that have error compilation messages
+///
+/// Sources are resolved relative to `home` in the same way as when compiling.
+pub(crate) fn broken_includes<T: WalkNode>(home: &Path, node: &T) -> Vec<BrokenInclude> {
+    let mut identifiers = HashMap::new();
+    for dependency in include_dependencies(home, node) {
+        identifiers
+            .entry(dependency.node_id)
+            .or_insert(dependency.identifier);
+    }
+
+    let mut collector = Collector {
+        identifiers,
+        broken: Vec::new(),
+    };
+    collector.walk(node);
+    collector.broken
+}
+
+/// A visitor that collects the `IncludeBlock`s that have errors
+struct Collector {
+    /// The resolved identifiers of the sources of includes, keyed by node id
+    identifiers: HashMap<NodeId, String>,
+
+    /// The broken includes collected so far
+    broken: Vec<BrokenInclude>,
+}
+
+impl Visitor for Collector {
+    fn visit_block(&mut self, block: &Block) -> WalkControl {
+        let Block::IncludeBlock(include) = block else {
+            return WalkControl::Continue;
+        };
+
+        let messages = include.options.compilation_messages.iter().flatten();
+        if messages
+            .clone()
+            .any(|message| matches!(message.level, MessageLevel::Error | MessageLevel::Exception))
+        {
+            let node_id = include.node_id();
+            self.broken.push(BrokenInclude {
+                identifier: self.identifiers.remove(&node_id),
+                node_id,
+                source: include.source.clone(),
+                messages: messages.cloned().collect(),
+            });
+        }
+
+        // Continue walking because the content of an include with errors
+        // (e.g. from a fallback source) may contain other broken includes
+        WalkControl::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::{IncludeBlock, IncludeBlockOptions};
+
+    use super::*;
+
+    #[test]
+    fn broken() {
+        let include = |source: &str, level: MessageLevel, content: Option<Vec<Block>>| {
+            Block::IncludeBlock(IncludeBlock {
+                content,
+                options: Box::new(IncludeBlockOptions {
+                    compilation_messages: Some(vec![CompilationMessage::new(
+                        level,
+                        "message".into(),
+                    )]),
+                    ..Default::default()
+                }),
+                ..IncludeBlock::new(source.into())
+            })
+        };
+
+        let nested = include("missing.md", MessageLevel::Error, None);
+        let blocks = vec![
+            include("warned.md", MessageLevel::Warning, Some(vec![nested])),
+            include("https://example.org/gone.md", MessageLevel::Exception, None),
+        ];
+
+        let broken = broken_includes(Path::new("/home"), &blocks);
+        assert_eq!(
+            broken
+                .iter()
+                .map(|include| include.identifier.as_deref())
+                .collect::<Vec<_>>(),
+            [
+                Some("/home/missing.md"),
+                Some("https://example.org/gone.md")
+            ]
+        );
+        assert_eq!(broken[0].source, "missing.md");
+        assert_eq!(broken[0].messages.len(), 1);
+    }
+}
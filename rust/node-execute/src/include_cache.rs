@@ -0,0 +1,356 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use stencila_codecs::{DecodeOptions, Format};
+use stencila_schema::{Block, CallArgument};
+
+use crate::{include_arguments::content_arguments, include_decode::DecodeOverrides};
+
+/// The process-wide cache of content decoded from the sources of `IncludeBlock`s
+///
+/// Shared by all executors (a new executor is created for each compile) so that
+/// unchanged sources do not need to be refetched and decoded on each compile.
+pub(crate) static INCLUDE_CACHE: LazyLock<Arc<Mutex<IncludeCache>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(IncludeCache::default())));
+
+/// How the include cache is used when getting the content of includes
+///
+/// See [`crate::Executor::set_include_cache_policy`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IncludeCachePolicy {
+    /// Reuse cached content, revalidating remote content using conditional requests
+    #[default]
+    Default,
+
+    /// Do not use cached content, fetching and decoding all sources afresh and
+    /// repopulating the cache with them
+    ForceFresh,
+
+    /// Do not make network requests, using cached content for remote sources
+    /// and failing for those that are not cached
+    Offline,
+}
+
+/// A cache of content decoded from the sources of `IncludeBlock`s
+#[derive(Debug, Default)]
+pub(crate) struct IncludeCache {
+    /// Decoded content, keyed by resolved identifier
+    ///
+    /// The same source may be included differently (e.g. with a different media type,
+    /// decode options, selector, or arguments) by different includes, so there may be
+    /// more than one variant of the content of each identifier.
+    content: HashMap<String, Vec<CachedContent>>,
+
+    /// Validators for making conditional requests for remote sources, keyed by URL
+    validators: HashMap<String, RemoteValidators>,
+
+    /// Interrupted downloads of remote sources, keyed by URL
+    partials: HashMap<String, PartialDownload>,
+}
+
+/// The inputs, other than its source, that the content of an include depends upon
+///
+/// Cached content is only reused for an include with the same variant so that, for
+/// example, two includes of the same source with different selectors never serve each
+/// other's content.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct ContentVariant {
+    /// The media type that the content was decoded from
+    pub media_type: Option<String>,
+
+    /// The overridable decode options that the content was decoded with
+    pub decoded_with: DecodeOverrides,
+
+    /// The selector of the include, if any, trimmed of whitespace
+    pub select: Option<String>,
+
+    /// The arguments of the include that can affect its content, normalized
+    /// (see [`content_arguments`])
+    pub arguments: Vec<(String, String)>,
+}
+
+impl ContentVariant {
+    /// Create the variant of the content of an include
+    pub fn new(
+        media_type: &Option<String>,
+        decode_options: &DecodeOptions,
+        select: &Option<String>,
+        arguments: &Option<Vec<CallArgument>>,
+    ) -> Self {
+        Self {
+            media_type: media_type.clone(),
+            decoded_with: DecodeOverrides::of(decode_options),
+            select: select
+                .as_deref()
+                .map(str::trim)
+                .filter(|select| !select.is_empty())
+                .map(String::from),
+            arguments: content_arguments(arguments),
+        }
+    }
+}
+
+/// Content decoded from an include source
+#[derive(Debug, Clone)]
+pub(crate) struct CachedContent {
+    /// The variant of the content
+    pub variant: ContentVariant,
+
+    /// The format that the content was decoded from, if known
+    pub format: Option<Format>,
+
+    /// The last modification time of the file that the content was decoded from
+    ///
+    /// `None` for remote sources, which use [`RemoteValidators`] instead.
+    pub modified: Option<SystemTime>,
+
+    /// The decoded blocks
+    pub blocks: Vec<Block>,
+}
+
+/// Validators returned by a server for a remote source
+///
+/// Used to make conditional requests so that a remote source is only
+/// refetched and decoded if it has changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct RemoteValidators {
+    /// The `ETag` header of the last response
+    pub etag: Option<String>,
+
+    /// The `Last-Modified` header of the last response
+    pub last_modified: Option<String>,
+
+    /// The `Content-Length` header of the last response
+    pub content_length: Option<String>,
+}
+
+impl RemoteValidators {
+    /// Whether there are any validators
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none() && self.content_length.is_none()
+    }
+
+    /// Whether these validators indicate that a source is unchanged from `cached`
+    ///
+    /// Only validators present in both are compared, and at least one must be
+    /// present in both, so that a server omitting a header is not treated as
+    /// indicating that the source is unchanged.
+    pub fn matches(&self, cached: &RemoteValidators) -> bool {
+        let pairs = [
+            (&self.etag, &cached.etag),
+            (&self.last_modified, &cached.last_modified),
+            (&self.content_length, &cached.content_length),
+        ];
+
+        let mut compared = false;
+        for (current, cached) in pairs {
+            if let (Some(current), Some(cached)) = (current, cached) {
+                if current != cached {
+                    return false;
+                }
+                compared = true;
+            }
+        }
+        compared
+    }
+}
+
+/// The bytes of a remote source received before its download was interrupted
+///
+/// Used to make a range request to resume the download rather than restarting it.
+#[derive(Debug, Clone)]
+pub(crate) struct PartialDownload {
+    /// The bytes received so far
+    pub bytes: Vec<u8>,
+
+    /// The value for the `If-Range` header (a strong `ETag` or the `Last-Modified` date)
+    /// so that the server only sends the remaining bytes if the source is unchanged
+    pub if_range: String,
+}
+
+impl IncludeCache {
+    /// Get the cached content for an identifier with a variant
+    pub fn content(&self, identifier: &str, variant: &ContentVariant) -> Option<&CachedContent> {
+        self.content
+            .get(identifier)?
+            .iter()
+            .find(|cached| cached.variant == *variant)
+    }
+
+    /// Get the validators for a remote identifier
+    pub fn validators(&self, identifier: &str) -> Option<&RemoteValidators> {
+        self.validators.get(identifier)
+    }
+
+    /// Cache content for an identifier
+    ///
+    /// Replaces any existing content for the identifier with the same variant.
+    pub fn insert_content(&mut self, identifier: &str, content: CachedContent) {
+        let variants = self.content.entry(identifier.to_string()).or_default();
+        variants.retain(|cached| cached.variant != content.variant);
+        variants.push(content);
+    }
+
+    /// Cache validators for a remote identifier
+    ///
+    /// If there are cached validators that differ then the source has changed, so the
+    /// cached content for the identifier (which may have been decoded from the previous
+    /// version of the source) is removed.
+    pub fn insert_validators(&mut self, identifier: &str, validators: RemoteValidators) {
+        if let Some(existing) = self.validators.get(identifier)
+            && *existing != validators
+        {
+            self.content.remove(identifier);
+        }
+
+        if validators.is_empty() {
+            self.validators.remove(identifier);
+        } else {
+            self.validators.insert(identifier.to_string(), validators);
+        }
+    }
+
+    /// Take the interrupted download of a remote identifier, if any
+    pub fn take_partial(&mut self, identifier: &str) -> Option<PartialDownload> {
+        self.partials.remove(identifier)
+    }
+
+    /// Cache the interrupted download of a remote identifier
+    pub fn insert_partial(&mut self, identifier: &str, partial: PartialDownload) {
+        self.partials.insert(identifier.to_string(), partial);
+    }
+
+    /// Remove all cached content, validators, and interrupted downloads
+    pub fn clear(&mut self) {
+        self.content.clear();
+        self.validators.clear();
+        self.partials.clear();
+    }
+
+    /// Remove the cached content (all variants), validators, and any interrupted download
+    /// for an identifier
+    pub fn invalidate(&mut self, identifier: &str) {
+        self.content.remove(identifier);
+        self.validators.remove(identifier);
+        self.partials.remove(identifier);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::shortcuts::{p, t};
+
+    use super::*;
+
+    #[test]
+    fn variants() {
+        let content = |variant: &ContentVariant, text: &str| CachedContent {
+            variant: variant.clone(),
+            format: None,
+            modified: None,
+            blocks: vec![p([t(text)])],
+        };
+        let text = |cached: Option<&CachedContent>| cached.map(|cached| cached.blocks.clone());
+
+        let inferred = ContentVariant::default();
+        let markdown = ContentVariant {
+            media_type: Some("text/markdown".into()),
+            ..Default::default()
+        };
+        let coarse = ContentVariant {
+            decoded_with: DecodeOverrides {
+                coarse: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut cache = IncludeCache::default();
+        cache.insert_content("/a.txt", content(&inferred, "inferred"));
+        cache.insert_content("/a.txt", content(&markdown, "markdown"));
+
+        // Variants with different media types do not evict each other
+        assert_eq!(
+            text(cache.content("/a.txt", &inferred)),
+            Some(vec![p([t("inferred")])])
+        );
+        assert_eq!(
+            text(cache.content("/a.txt", &markdown)),
+            Some(vec![p([t("markdown")])])
+        );
+        assert!(cache.content("/a.txt", &coarse).is_none());
+
+        // Variants are replaced
+        cache.insert_content("/a.txt", content(&inferred, "updated"));
+        assert_eq!(
+            text(cache.content("/a.txt", &inferred)),
+            Some(vec![p([t("updated")])])
+        );
+
+        // All variants are invalidated
+        cache.invalidate("/a.txt");
+        assert!(cache.content("/a.txt", &inferred).is_none());
+        assert!(cache.content("/a.txt", &markdown).is_none());
+    }
+
+    #[test]
+    fn select_and_argument_variants() {
+        let variant = |select: Option<&str>, arguments: Vec<CallArgument>| {
+            ContentVariant::new(
+                &None,
+                &DecodeOptions::default(),
+                &select.map(String::from),
+                &Some(arguments),
+            )
+        };
+        let arg = |name: &str, code: &str| CallArgument {
+            name: name.into(),
+            code: code.into(),
+            ..Default::default()
+        };
+
+        let one = variant(Some("#one"), Vec::new());
+        let two = variant(Some("#two"), Vec::new());
+
+        let mut cache = IncludeCache::default();
+        cache.insert_content(
+            "/doc.md",
+            CachedContent {
+                variant: one.clone(),
+                format: None,
+                modified: None,
+                blocks: vec![p([t("One")])],
+            },
+        );
+
+        // The same source with a different selector is not served the same content
+        assert!(cache.content("/doc.md", &one).is_some());
+        assert!(cache.content("/doc.md", &two).is_none());
+        assert!(
+            cache
+                .content("/doc.md", &variant(None, Vec::new()))
+                .is_none()
+        );
+
+        // Selectors are normalized
+        assert_eq!(variant(Some("  #one "), Vec::new()), one);
+        assert_eq!(variant(Some(" "), Vec::new()), variant(None, Vec::new()));
+
+        // Arguments that can affect content are normalized, and others are ignored
+        assert_eq!(
+            variant(None, vec![arg("b", "2"), arg("a", " 1 ")]),
+            variant(None, vec![arg("a", "1"), arg("b", "2")])
+        );
+        assert_ne!(
+            variant(None, vec![arg("a", "1")]),
+            variant(None, vec![arg("a", "2")])
+        );
+        assert_eq!(
+            variant(None, vec![arg("timeout", "30s"), arg("retries", "2")]),
+            variant(None, Vec::new())
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Verification of the checksums of remote include sources
+//!
+//! A remote include can be pinned to known content using the `sha256` argument
+//! e.g. `--sha256=9f86d08...`. The bytes fetched from the server (before any
+//! decompression) must have that SHA-256 digest, otherwise the include fails with
+//! an error, so that tampering with, or unexpected changes to, remote content are
+//! caught at render time. Includes with a checksum are always fetched in full,
+//! rather than using conditional requests, so that the bytes can be verified.
+
+use sha2::{Digest, Sha256};
+
+use crate::IncludeError;
+
+/// The name of the argument used to specify the expected SHA-256 digest of a remote source
+pub(crate) const SHA256_ARGUMENT: &str = "sha256";
+
+/// Parse a SHA-256 digest, returning it as lowercase hexadecimal
+///
+/// An optional `sha256:` prefix is allowed. Returns `None` if the digest
+/// is not 64 hexadecimal characters.
+pub(crate) fn parse_sha256(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value.strip_prefix("sha256:").unwrap_or(value);
+
+    (value.len() == 64 && value.chars().all(|char| char.is_ascii_hexdigit()))
+        .then(|| value.to_lowercase())
+}
+
+/// Verify that bytes fetched from a URL have the expected SHA-256 digest
+pub(crate) fn verify_sha256(bytes: &[u8], expected: &str, url: &str) -> Result<(), IncludeError> {
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(IncludeError::ChecksumMismatch(format!(
+            "Content of {url} has SHA-256 digest `{actual}` but `{expected}` was expected"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn checksums() {
+        assert_eq!(parse_sha256(HELLO), Some(HELLO.to_string()));
+        assert_eq!(
+            parse_sha256(&format!(" sha256:{} ", HELLO.to_uppercase())),
+            Some(HELLO.to_string())
+        );
+        assert_eq!(parse_sha256("2cf24dba"), None);
+        assert_eq!(parse_sha256(&HELLO.replace('2', "g")), None);
+
+        assert!(verify_sha256(b"hello", HELLO, "https://example.org").is_ok());
+        assert!(matches!(
+            verify_sha256(b"hello!", HELLO, "https://example.org"),
+            Err(IncludeError::ChecksumMismatch(..))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+//! Detection of cycles, and excessive depth, in nested includes
+//!
+//! If a source includes itself, directly or via other sources (e.g. `a.smd` includes `b.smd`
+//! which includes `a.smd`), then compiling it would recurse indefinitely. To prevent that, the
+//! executor has a stack of the resolved identifiers of the includes whose content is currently
+//! being compiled (see [`crate::include_block`]). An include whose source is already on that
+//! stack is not fetched and instead gets an error message with the path of the cycle. The same
+//! stack is used to refuse includes nested deeper than the executor's maximum depth, if any
+//! (see [`crate::Executor::set_include_max_depth`]).
+
+use crate::include_error::IncludeError;
+
+/// Get a cycle error if an identifier is one of the ancestors of an include
+///
+/// The message of the error is the path of the cycle, starting and ending at the identifier.
+pub(crate) fn include_cycle(identifier: &str, ancestors: &[String]) -> Option<IncludeError> {
+    let start = ancestors
+        .iter()
+        .position(|ancestor| ancestor == identifier)?;
+
+    let path = ancestors[start..]
+        .iter()
+        .map(String::as_str)
+        .chain([identifier])
+        .map(|identifier| format!("`{identifier}`"))
+        .collect::<Vec<_>>()
+        .join(" -> ");
+
+    Some(IncludeError::Cycle(format!(
+        "Not including `{identifier}` because it would include itself: {path}"
+    )))
+}
+
+/// Get a depth error if an include would be nested deeper than the maximum depth
+///
+/// The depth of the include is one more than the number of its ancestors. The message of
+/// the error is the path of nested includes, ending at the identifier.
+pub(crate) fn include_too_deep(
+    identifier: &str,
+    ancestors: &[String],
+    max_depth: Option<usize>,
+) -> Option<IncludeError> {
+    let max_depth = max_depth?;
+    if ancestors.len() < max_depth {
+        return None;
+    }
+
+    let path = ancestors
+        .iter()
+        .map(String::as_str)
+        .chain([identifier])
+        .map(|identifier| format!("`{identifier}`"))
+        .collect::<Vec<_>>()
+        .join(" -> ");
+
+    Some(IncludeError::DepthExceeded(format!(
+        "Not including `{identifier}` because includes are nested more than {max_depth} deep: \
+         {path}"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles() {
+        let ancestors = [
+            "/docs/main.md".to_string(),
+            "/docs/a.md".into(),
+            "/docs/b.md".into(),
+        ];
+
+        assert_eq!(include_cycle("/docs/c.md", &ancestors), None);
+        assert_eq!(include_cycle("/docs/a.md", &[]), None);
+
+        assert_eq!(
+            include_cycle("/docs/a.md", &ancestors),
+            Some(IncludeError::Cycle(
+                "Not including `/docs/a.md` because it would include itself: \
+                 `/docs/a.md` -> `/docs/b.md` -> `/docs/a.md`"
+                    .into()
+            ))
+        );
+
+        assert_eq!(
+            include_cycle("/docs/b.md", &ancestors).map(|error| error.to_string()),
+            Some(
+                "Not including `/docs/b.md` because it would include itself: \
+                 `/docs/b.md` -> `/docs/b.md`"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn too_deep() {
+        let ancestors = ["/docs/main.md".to_string(), "/docs/a.md".into()];
+
+        assert_eq!(include_too_deep("/docs/b.md", &ancestors, None), None);
+        assert_eq!(include_too_deep("/docs/b.md", &ancestors, Some(3)), None);
+        assert_eq!(include_too_deep("/docs/main.md", &[], Some(1)), None);
+
+        assert_eq!(
+            include_too_deep("/docs/b.md", &ancestors, Some(2)),
+            Some(IncludeError::DepthExceeded(
+                "Not including `/docs/b.md` because includes are nested more than 2 deep: \
+                 `/docs/main.md` -> `/docs/a.md` -> `/docs/b.md`"
+                    .into()
+            ))
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Overrides of the options used to decode the source of an include
+//!
+//! The sources of includes are decoded using the decode options of the executor (other
+//! than `format` and `media_type`). Reserved arguments allow some of those options to be
+//! overridden for a single include e.g. `--decode-codec=pandoc` to decode using a different
+//! codec, `--decode-strict=true` to decode in strict mode, or `--decode-coarse=true` to
+//! decode coarsely.
+
+use stencila_codecs::DecodeOptions;
+use stencila_schema::Node;
+
+use crate::prelude::is_truthy;
+
+/// The name of the argument used to override the codec used to decode a source
+pub(crate) const DECODE_CODEC_ARGUMENT: &str = "decode-codec";
+
+/// The name of the argument used to override whether a source is decoded in strict mode
+pub(crate) const DECODE_STRICT_ARGUMENT: &str = "decode-strict";
+
+/// The name of the argument used to override whether a source is decoded coarsely
+pub(crate) const DECODE_COARSE_ARGUMENT: &str = "decode-coarse";
+
+/// The names of the arguments used to override decode options
+pub(crate) const DECODE_ARGUMENTS: [&str; 3] = [
+    DECODE_CODEC_ARGUMENT,
+    DECODE_STRICT_ARGUMENT,
+    DECODE_COARSE_ARGUMENT,
+];
+
+/// The decode options that can be overridden by the arguments of an include
+///
+/// Also used to record the options that cached content was decoded with, so that
+/// content decoded with different options is not reused.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct DecodeOverrides {
+    /// The name of the codec to use
+    pub codec: Option<String>,
+
+    /// Whether to decode in strict mode
+    pub strict: Option<bool>,
+
+    /// Whether to decode coarsely
+    pub coarse: Option<bool>,
+}
+
+impl DecodeOverrides {
+    /// Get the overridable options of a set of decode options
+    pub fn of(options: &DecodeOptions) -> Self {
+        Self {
+            codec: options.codec.clone(),
+            strict: options.strict,
+            coarse: options.coarse,
+        }
+    }
+
+    /// Set an override from the name and value of an argument
+    ///
+    /// Returns an error message if the value is not valid for the option.
+    pub fn set(&mut self, name: &str, value: &Node) -> Result<(), String> {
+        match name {
+            DECODE_CODEC_ARGUMENT => match value {
+                Node::String(codec) if !codec.trim().is_empty() => {
+                    self.codec = Some(codec.trim().to_string());
+                }
+                _ => return Err(format!("Argument `{name}` should be the name of a codec")),
+            },
+            DECODE_STRICT_ARGUMENT => self.strict = Some(is_truthy(value)),
+            DECODE_COARSE_ARGUMENT => self.coarse = Some(is_truthy(value)),
+            _ => return Err(format!("Argument `{name}` is not a decode option")),
+        }
+        Ok(())
+    }
+
+    /// Merge the overrides over a set of decode options
+    pub fn apply(&self, options: DecodeOptions) -> DecodeOptions {
+        DecodeOptions {
+            codec: self.codec.clone().or(options.codec),
+            strict: self.strict.or(options.strict),
+            coarse: self.coarse.or(options.coarse),
+            ..options
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides() -> Result<(), String> {
+        let defaults = DecodeOptions {
+            codec: Some("markdown".into()),
+            strict: Some(false),
+            ..Default::default()
+        };
+
+        let mut overrides = DecodeOverrides::default();
+        assert_eq!(
+            DecodeOverrides::of(&overrides.apply(defaults.clone())),
+            DecodeOverrides::of(&defaults)
+        );
+
+        overrides.set(DECODE_STRICT_ARGUMENT, &Node::Boolean(true))?;
+        overrides.set(DECODE_COARSE_ARGUMENT, &Node::String("yes".into()))?;
+        let options = overrides.apply(defaults.clone());
+        assert_eq!(options.codec.as_deref(), Some("markdown"));
+        assert_eq!(options.strict, Some(true));
+        assert_eq!(options.coarse, Some(true));
+
+        overrides.set(DECODE_CODEC_ARGUMENT, &Node::String(" pandoc ".into()))?;
+        assert_eq!(overrides.apply(defaults).codec.as_deref(), Some("pandoc"));
+
+        assert!(
+            overrides
+                .set(DECODE_CODEC_ARGUMENT, &Node::Integer(1))
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use stencila_schema::{Block, CallArgument, NodeId, NodeType, Visitor, WalkControl, WalkNode};
+
+use crate::{
+    include_block::source_alternatives,
+    include_fetch::{file_url_path, is_remote, join_url_dir, url_dir},
+};
+
+/// An `IncludeBlock` and the resolved identifier of its source
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IncludeDependency {
+    /// The id of the `IncludeBlock`
+    pub node_id: NodeId,
+
+    /// The resolved identifier of the source (a URL or file path)
+    pub identifier: String,
+
+    /// The media type of the source, if specified on the `IncludeBlock`
+    pub media_type: Option<String>,
+
+    /// The selector of the `IncludeBlock`, if any
+    pub select: Option<String>,
+
+    /// The arguments of the `IncludeBlock`, if any
+    pub arguments: Option<Vec<CallArgument>>,
+}
+
+impl IncludeDependency {
+    /// The local file path of the source, or `None` if it is remote
+    pub fn path(&self) -> Option<PathBuf> {
+        if is_remote(&self.identifier) {
+            return None;
+        }
+
+        let path = PathBuf::from(&self.identifier);
+        Some(path.canonicalize().unwrap_or(path))
+    }
+}
+
+/// Get the `IncludeBlock`s within a node, and the resolved identifiers of their sources
+///
+/// Sources are resolved in the same way as when compiling: relative to `home` and to
+/// the directories of any includes that the `IncludeBlock` is nested within. Only
+/// includes that have already been compiled (and so have `content`) can have nested includes.
+pub(crate) fn include_dependencies<T: WalkNode>(home: &Path, node: &T) -> Vec<IncludeDependency> {
+    let mut walker = Walker {
+        home: home.to_path_buf(),
+        dependencies: Vec::new(),
+        pending: None,
+        frames: Vec::new(),
+    };
+    walker.walk(node);
+    walker.dependencies
+}
+
+/// A visitor that collects the dependencies of `IncludeBlock`s
+struct Walker {
+    /// The directory that top-level sources are resolved against
+    home: PathBuf,
+
+    /// The dependencies collected so far
+    dependencies: Vec<IncludeDependency>,
+
+    /// The id of the `IncludeBlock` last visited, and the directory that
+    /// sources nested within it should be resolved against
+    pending: Option<(NodeId, PathBuf)>,
+
+    /// A frame for each struct currently being walked over, with the directory
+    /// to resolve nested sources against if the struct is an `IncludeBlock`
+    frames: Vec<Option<PathBuf>>,
+}
+
+impl Walker {
+    /// The directory that sources are currently resolved against
+    fn current_dir(&self) -> &Path {
+        self.frames
+            .iter()
+            .rev()
+            .flatten()
+            .next()
+            .unwrap_or(&self.home)
+    }
+}
+
+impl Visitor for Walker {
+    fn visit_block(&mut self, block: &Block) -> WalkControl {
+        let Block::IncludeBlock(include) = block else {
+            return WalkControl::Continue;
+        };
+
+        let source = include.source.trim();
+        if source.is_empty() {
+            return WalkControl::Continue;
+        }
+
+        // Each alternative of a fallback chain is a dependency, but, as when compiling,
+        // nested sources are resolved against the directory of the first
+        for (index, source) in source_alternatives(source).into_iter().enumerate() {
+            let (identifier, dir) = if let Some(path) = file_url_path(source) {
+                (path.to_string_lossy().to_string(), None)
+            } else if is_remote(source) {
+                (source.to_string(), url_dir(source))
+            } else if let Some(url) = join_url_dir(self.current_dir(), source) {
+                let dir = url_dir(&url);
+                (url, dir)
+            } else {
+                let path = self.current_dir().join(source);
+                let dir = path.parent().map(Path::to_path_buf);
+                (path.to_string_lossy().to_string(), dir)
+            };
+
+            if index == 0
+                && let Some(dir) = dir
+            {
+                self.pending = Some((include.node_id(), dir));
+            }
+
+            self.dependencies.push(IncludeDependency {
+                node_id: include.node_id(),
+                identifier,
+                media_type: include.media_type.clone(),
+                select: include.select.clone(),
+                arguments: include.arguments.clone(),
+            });
+        }
+
+        WalkControl::Continue
+    }
+
+    fn enter_struct(&mut self, _node_type: NodeType, node_id: NodeId) -> WalkControl {
+        let frame = self
+            .pending
+            .take()
+            .and_then(|(pending_id, dir)| (pending_id == node_id).then_some(dir));
+        self.frames.push(frame);
+
+        WalkControl::Continue
+    }
+
+    fn exit_struct(&mut self) {
+        self.frames.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::{IncludeBlock, shortcuts::sec};
+
+    use super::*;
+
+    #[test]
+    fn nested_sources() {
+        let nested = IncludeBlock::new("../c.md".into());
+        let mut include = IncludeBlock::new("sub/b.md".into());
+        include.content = Some(vec![sec([Block::IncludeBlock(nested)])]);
+
+        let mut remote = IncludeBlock::new("https://example.org/docs/d.md".into());
+        remote.content = Some(vec![Block::IncludeBlock(IncludeBlock::new(
+            "../g.md".into(),
+        ))]);
+
+        let blocks = vec![
+            Block::IncludeBlock(IncludeBlock::new("a.md".into())),
+            Block::IncludeBlock(include),
+            Block::IncludeBlock(remote),
+            Block::IncludeBlock(IncludeBlock::new("e.md || f.md".into())),
+        ];
+
+        let identifiers = include_dependencies(Path::new("/home"), &blocks)
+            .into_iter()
+            .map(|dependency| dependency.identifier)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            identifiers,
+            [
+                "/home/a.md",
+                "/home/sub/b.md",
+                "/home/sub/../c.md",
+                "https://example.org/docs/d.md",
+                "https://example.org/g.md",
+                "/home/e.md",
+                "/home/f.md"
+            ]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Decoding of diagram-as-code sources (Mermaid, Graphviz, and PlantUML) into blocks
+//!
+//! Mermaid diagrams become an `ImageObject` with the Mermaid media type and the
+//! diagram code as its `content_url` (as generated by the Mermaid kernel) which is
+//! rendered to an image by clients. There is no renderer for Graphviz or PlantUML
+//! diagrams so they are included as a code block with the language of the diagram
+//! (i.e. `dot` or `plantuml`) so that clients that can render them are able to.
+
+use std::fmt;
+
+use stencila_schema::{Block, CodeBlock, ImageObject};
+
+/// The kind of a diagram source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Diagram {
+    Mermaid,
+    Graphviz,
+    PlantUml,
+}
+
+impl fmt::Display for Diagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mermaid => "Mermaid",
+            Self::Graphviz => "Graphviz",
+            Self::PlantUml => "PlantUML",
+        })
+    }
+}
+
+/// The media types of Mermaid sources
+const MERMAID_MEDIA_TYPES: [&str; 3] = ["text/vnd.mermaid", "text/x-mermaid", "mermaid"];
+
+/// The file extensions of Mermaid sources
+const MERMAID_EXTENSIONS: [&str; 2] = [".mmd", ".mermaid"];
+
+/// The media types of Graphviz sources
+const GRAPHVIZ_MEDIA_TYPES: [&str; 4] = ["text/vnd.graphviz", "text/x-graphviz", "graphviz", "dot"];
+
+/// The file extensions of Graphviz sources
+const GRAPHVIZ_EXTENSIONS: [&str; 2] = [".dot", ".gv"];
+
+/// The media types of PlantUML sources
+const PLANTUML_MEDIA_TYPES: [&str; 3] = ["text/vnd.plantuml", "text/x-plantuml", "plantuml"];
+
+/// The file extensions of PlantUML sources
+const PLANTUML_EXTENSIONS: [&str; 3] = [".puml", ".plantuml", ".pu"];
+
+/// Get the kind of a diagram source, based on its media type or, if none, its path
+///
+/// Returns `None` if the source is not a diagram.
+pub(crate) fn diagram_kind(media_type: &Option<String>, path: &str) -> Option<Diagram> {
+    match media_type {
+        Some(media_type) => {
+            let media_type = media_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            if MERMAID_MEDIA_TYPES.contains(&media_type.as_str()) {
+                Some(Diagram::Mermaid)
+            } else if GRAPHVIZ_MEDIA_TYPES.contains(&media_type.as_str()) {
+                Some(Diagram::Graphviz)
+            } else if PLANTUML_MEDIA_TYPES.contains(&media_type.as_str()) {
+                Some(Diagram::PlantUml)
+            } else {
+                None
+            }
+        }
+        None => {
+            let path = path.to_lowercase();
+            if MERMAID_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+            {
+                Some(Diagram::Mermaid)
+            } else if GRAPHVIZ_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+            {
+                Some(Diagram::Graphviz)
+            } else if PLANTUML_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+            {
+                Some(Diagram::PlantUml)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Convert the code of a diagram into a block
+///
+/// Returns the block and whether the diagram was rendered as an image (`false`
+/// if there is no renderer for the kind of diagram and it is a code block).
+pub(crate) fn diagram_to_block(code: &str, diagram: Diagram) -> (Block, bool) {
+    match diagram {
+        Diagram::Mermaid => (
+            Block::ImageObject(ImageObject {
+                media_type: Some("text/vnd.mermaid".to_string()),
+                ..ImageObject::new(code.trim().to_string())
+            }),
+            true,
+        ),
+        Diagram::Graphviz => (code_block(code, "dot"), false),
+        Diagram::PlantUml => (code_block(code, "plantuml"), false),
+    }
+}
+
+/// Create a code block for a diagram that can not be rendered
+fn code_block(code: &str, language: &str) -> Block {
+    Block::CodeBlock(CodeBlock {
+        programming_language: Some(language.to_string()),
+        ..CodeBlock::new(code.into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagrams() {
+        assert_eq!(diagram_kind(&None, "arch.mmd"), Some(Diagram::Mermaid));
+        assert_eq!(diagram_kind(&None, "/arch.PUML"), Some(Diagram::PlantUml));
+        assert_eq!(diagram_kind(&None, "deps.gv"), Some(Diagram::Graphviz));
+        assert_eq!(diagram_kind(&None, "arch.md"), None);
+        assert_eq!(
+            diagram_kind(&Some("text/vnd.mermaid".into()), "arch"),
+            Some(Diagram::Mermaid)
+        );
+        assert_eq!(diagram_kind(&Some("text/plain".into()), "arch.mmd"), None);
+
+        let (block, rendered) = diagram_to_block("graph TD\n  A --> B\n", Diagram::Mermaid);
+        assert!(rendered);
+        let Block::ImageObject(image) = block else {
+            panic!("Expected an image")
+        };
+        assert_eq!(image.content_url, "graph TD\n  A --> B");
+        assert_eq!(image.media_type.as_deref(), Some("text/vnd.mermaid"));
+
+        let (block, rendered) = diagram_to_block("@startuml\n@enduml\n", Diagram::PlantUml);
+        assert!(!rendered);
+        assert!(matches!(block, Block::CodeBlock(..)));
+
+        let (block, rendered) = diagram_to_block("digraph { a -> b }\n", Diagram::Graphviz);
+        assert!(!rendered);
+        let Block::CodeBlock(code) = block else {
+            panic!("Expected a code block")
+        };
+        assert_eq!(code.programming_language.as_deref(), Some("dot"));
+    }
+}
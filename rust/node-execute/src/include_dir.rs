@@ -0,0 +1,197 @@
+//! The directory of the source of an include, exposed to its content as a variable
+//!
+//! While the content of an include is executed, the `include_dir` variable is set to the
+//! directory that its source was resolved from so that code within included content can
+//! construct paths relative to itself (e.g. to read sibling data files). The variable is
+//! restored to any previous value (e.g. that of an enclosing include) after the content
+//! is executed so that it does not leak into the including document.
+
+use reqwest::Url;
+
+use stencila_schema::{Block, CallBlock, IncludeBlock, Inline, Node, Visitor, WalkControl};
+
+use crate::{Executor, include_fetch::is_remote};
+
+/// The name of the variable for the directory of the source of an include
+pub(crate) const INCLUDE_DIR_VARIABLE: &str = "include_dir";
+
+/// Get the directory that the source of an include was resolved from
+///
+/// For local sources this is the last directory on the executor's directory stack
+/// (pushed when resolving the source). For remote sources it is the URL of the
+/// "directory" of the source (i.e. the URL without the last segment of its path).
+pub(crate) fn include_dir(identifier: &str, executor: &Executor) -> Option<String> {
+    if is_remote(identifier) {
+        return Url::parse(identifier)
+            .and_then(|url| url.join("."))
+            .map(|url| url.to_string())
+            .ok();
+    }
+
+    executor
+        .directory_stack
+        .last()
+        .map(|dir| dir.to_string_lossy().to_string())
+}
+
+/// Whether the content of an include has code that may use the `include_dir` variable
+///
+/// Used to avoid setting the variable, and so possibly starting a kernel, when
+/// included content has nothing to execute.
+pub(crate) fn has_code(content: &[Block]) -> bool {
+    let mut finder = CodeFinder { found: false };
+    for block in content {
+        finder.walk(block);
+        if finder.found {
+            return true;
+        }
+    }
+    false
+}
+
+/// A scope within which the `include_dir` variable is set
+pub(crate) struct IncludeDirScope {
+    /// The value of the variable before the scope was entered, if any
+    previous: Option<Node>,
+}
+
+impl IncludeDirScope {
+    /// Set the `include_dir` variable, recording any previous value
+    ///
+    /// Returns `None` if the variable could not be set.
+    pub async fn enter(dir: String, executor: &mut Executor) -> Option<Self> {
+        let lang = executor.programming_language(&None);
+        let mut kernels = executor.kernels.write().await;
+
+        let previous = kernels.get(INCLUDE_DIR_VARIABLE).await.ok().flatten();
+
+        if let Err(error) = kernels
+            .set(INCLUDE_DIR_VARIABLE, &Node::String(dir), lang.as_deref())
+            .await
+        {
+            tracing::debug!("While setting `{INCLUDE_DIR_VARIABLE}`: {error}");
+            return None;
+        }
+
+        Some(Self { previous })
+    }
+
+    /// Restore the `include_dir` variable to its previous value, or remove it if none
+    pub async fn exit(self, executor: &mut Executor) {
+        let lang = executor.programming_language(&None);
+        let mut kernels = executor.kernels.write().await;
+
+        let result = match &self.previous {
+            Some(previous) => {
+                kernels
+                    .set(INCLUDE_DIR_VARIABLE, previous, lang.as_deref())
+                    .await
+            }
+            None => kernels.remove(INCLUDE_DIR_VARIABLE).await,
+        };
+
+        if let Err(error) = result {
+            tracing::debug!("While restoring `{INCLUDE_DIR_VARIABLE}`: {error}");
+        }
+    }
+}
+
+/// A visitor that finds whether there are any nodes with code
+struct CodeFinder {
+    found: bool,
+}
+
+impl CodeFinder {
+    /// Break walk if code has been found
+    fn walk_control(&self) -> WalkControl {
+        match self.found {
+            true => WalkControl::Break,
+            false => WalkControl::Continue,
+        }
+    }
+}
+
+impl Visitor for CodeFinder {
+    fn visit_block(&mut self, block: &Block) -> WalkControl {
+        self.found |= match block {
+            Block::CodeChunk(..) | Block::ForBlock(..) | Block::IfBlock(..) => true,
+            Block::IncludeBlock(IncludeBlock { arguments, .. })
+            | Block::CallBlock(CallBlock { arguments, .. }) => arguments
+                .iter()
+                .flatten()
+                .any(|arg| arg.is_expression.unwrap_or_default()),
+            _ => false,
+        };
+        self.walk_control()
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) -> WalkControl {
+        self.found |= matches!(inline, Inline::CodeExpression(..));
+        self.walk_control()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use tokio::sync::RwLock;
+
+    use stencila_kernels::Kernels;
+    use stencila_schema::{
+        ExecutionBounds,
+        shortcuts::{cc, ce, p, sec, t},
+    };
+
+    use super::*;
+
+    async fn get(kernels: &Arc<RwLock<Kernels>>) -> Option<Node> {
+        kernels
+            .read()
+            .await
+            .get(INCLUDE_DIR_VARIABLE)
+            .await
+            .expect("get variable")
+    }
+
+    #[test]
+    fn code() {
+        assert!(!has_code(&[p([t("text")]), sec([p([t("nested")])])]));
+        assert!(has_code(&[sec([cc("x = 1", None::<String>)])]));
+        assert!(has_code(&[p([t("x is "), ce("x", None::<String>)])]));
+    }
+
+    #[tokio::test]
+    async fn scopes() {
+        let kernels = Arc::new(RwLock::new(Kernels::new_here(ExecutionBounds::Main)));
+        let mut executor = Executor::new(PathBuf::from("/docs"), kernels.clone(), None);
+
+        assert_eq!(
+            include_dir("https://example.org/docs/part.md", &executor),
+            Some("https://example.org/docs/".to_string())
+        );
+        assert_eq!(
+            include_dir("/docs/part.md", &executor),
+            Some("/docs".to_string())
+        );
+
+        let outer = IncludeDirScope::enter("/docs".into(), &mut executor)
+            .await
+            .expect("set");
+        assert_eq!(get(&kernels).await, Some(Node::String("/docs".into())));
+
+        let inner = IncludeDirScope::enter("/docs/parts".into(), &mut executor)
+            .await
+            .expect("set");
+        assert_eq!(
+            get(&kernels).await,
+            Some(Node::String("/docs/parts".into()))
+        );
+
+        inner.exit(&mut executor).await;
+        assert_eq!(get(&kernels).await, Some(Node::String("/docs".into())));
+
+        outer.exit(&mut executor).await;
+        assert_eq!(get(&kernels).await, None);
+    }
+}
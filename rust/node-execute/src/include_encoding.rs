@@ -0,0 +1,91 @@
+//! Transcoding of the text of sources from a character encoding to UTF-8
+//!
+//! Used when an include has an `encoding` argument (e.g. `--encoding=windows-1252`)
+//! for sources that are not UTF-8 encoded. Encodings are identified using the labels
+//! of the WHATWG Encoding Standard, so common aliases (e.g. `latin1`) are supported.
+
+use encoding_rs::Encoding;
+
+use stencila_codecs::Format;
+
+use crate::include_error::IncludeError;
+
+/// Some of the supported encodings, listed when an encoding is not supported
+const EXAMPLE_ENCODINGS: [&str; 6] = [
+    "utf-8",
+    "utf-16le",
+    "utf-16be",
+    "windows-1252",
+    "iso-8859-1",
+    "shift_jis",
+];
+
+/// Get the encoding with a label
+///
+/// Returns an error, listing some of the supported encodings, if there
+/// is no encoding with the label.
+pub(crate) fn encoding_for_label(label: &str) -> Result<&'static Encoding, IncludeError> {
+    Encoding::for_label_no_replacement(label.trim().as_bytes()).ok_or_else(|| {
+        IncludeError::ParseError(format!(
+            "Unsupported encoding `{label}`, supported encodings include {}",
+            EXAMPLE_ENCODINGS
+                .iter()
+                .map(|encoding| format!("`{encoding}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    })
+}
+
+/// Whether sources in a format can be transcoded
+///
+/// Sources in known binary formats (e.g. images, DOCX) are not text and so
+/// are never transcoded.
+pub(crate) fn is_transcodable(format: &Format) -> bool {
+    !format.is_binary() || format.is_other() || format.is_unknown()
+}
+
+/// Transcode bytes from an encoding to UTF-8
+///
+/// Any byte order mark takes precedence over the encoding (e.g. so that a UTF-16 source
+/// with a BOM is decoded correctly regardless of which UTF-16 variant is specified).
+/// Malformed sequences are replaced with the replacement character. Returns the text and
+/// whether there were any malformed sequences.
+pub(crate) fn transcode(bytes: &[u8], encoding: &'static Encoding) -> (String, bool) {
+    let (text, .., malformed) = encoding.decode(bytes);
+    (text.into_owned(), malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings() {
+        let windows = encoding_for_label("Windows-1252").expect("supported");
+        assert_eq!(transcode(b"Caf\xe9", windows), ("Café".to_string(), false));
+
+        let latin1 = encoding_for_label(" latin1 ").expect("supported");
+        assert_eq!(latin1, windows);
+
+        let utf8 = encoding_for_label("utf-8").expect("supported");
+        assert_eq!(
+            transcode(b"Caf\xe9", utf8),
+            ("Caf\u{fffd}".to_string(), true)
+        );
+
+        // A BOM takes precedence
+        assert_eq!(
+            transcode(b"\xff\xfeO\0k\0", windows),
+            ("Ok".to_string(), false)
+        );
+
+        let error = encoding_for_label("windos-1252").expect_err("unsupported");
+        assert!(error.message().contains("`windos-1252`"));
+        assert!(error.message().contains("`windows-1252`"));
+
+        assert!(is_transcodable(&Format::Markdown));
+        assert!(!is_transcodable(&Format::Png));
+        assert!(!is_transcodable(&Format::Docx));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fmt, io};
+
+use eyre::Report;
+use reqwest::StatusCode;
+
+/// An error getting the content of an `IncludeBlock` from its source
+///
+/// Each variant contains the human readable message that is used for the
+/// compilation message of the include. Allows those driving compilation
+/// programmatically to match on the kind of error and react accordingly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    /// The source could not be found (e.g. a missing file, or a `404` response)
+    NotFound(String),
+
+    /// The source could not be decoded, or the content could not be selected from it
+    ParseError(String),
+
+    /// The source includes itself, directly or via other sources
+    Cycle(String),
+
+    /// Includes are nested too deeply
+    DepthExceeded(String),
+
+    /// The source could not be fetched (e.g. connection errors, timeouts, server errors)
+    Network(String),
+
+    /// Access to the source is not allowed (e.g. file permissions, a `401` or `403` response,
+    /// or a remote source when executing within restricted execution bounds)
+    Forbidden(String),
+
+    /// The content of the source does not have the expected checksum
+    ChecksumMismatch(String),
+}
+
+impl IncludeError {
+    /// Create an error from the error status of a response
+    pub(crate) fn from_status(status: StatusCode, message: String) -> Self {
+        match status {
+            StatusCode::NOT_FOUND | StatusCode::GONE => Self::NotFound(message),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Forbidden(message),
+            _ => Self::Network(message),
+        }
+    }
+
+    /// Classify an error that occurred while getting content from a source
+    ///
+    /// Errors that are already an [`IncludeError`] are returned as is. File system errors
+    /// are classified by their kind. All other errors are assumed to be decoding errors.
+    pub(crate) fn classify(error: Report) -> Self {
+        let error = match error.downcast::<IncludeError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+
+        let message = error.to_string();
+        match error.downcast_ref::<io::Error>().map(|error| error.kind()) {
+            Some(io::ErrorKind::NotFound) => Self::NotFound(message),
+            Some(io::ErrorKind::PermissionDenied) => Self::Forbidden(message),
+            _ => Self::ParseError(message),
+        }
+    }
+
+    /// The human readable message for the error
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound(message)
+            | Self::ParseError(message)
+            | Self::Cycle(message)
+            | Self::DepthExceeded(message)
+            | Self::Network(message)
+            | Self::Forbidden(message)
+            | Self::ChecksumMismatch(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+#[cfg(test)]
+mod tests {
+    use eyre::eyre;
+
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let error = IncludeError::from_status(StatusCode::FORBIDDEN, "403 Forbidden".into());
+        assert_eq!(
+            IncludeError::classify(error.into()),
+            IncludeError::Forbidden("403 Forbidden".into())
+        );
+
+        let error = io::Error::new(io::ErrorKind::NotFound, "No such file");
+        assert!(matches!(
+            IncludeError::classify(error.into()),
+            IncludeError::NotFound(..)
+        ));
+
+        assert_eq!(
+            IncludeError::classify(eyre!("Invalid JSON")),
+            IncludeError::ParseError("Invalid JSON".into())
+        );
+    }
+}
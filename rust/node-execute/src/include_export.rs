@@ -0,0 +1,161 @@
+//! Exporting of a subset of the symbols defined by the content of an include
+//!
+//! An include of a template that defines reusable functions or variables can have an
+//! `export` argument listing the symbols that should be kept in the kernels of the
+//! including document e.g. `--export=clean,plot`. After the content of the include has
+//! been executed, any other variables that it defined are removed, giving the include a
+//! module-like interface. Variables that existed before the include was executed are left
+//! unchanged, even if the include assigned to them. A warning is added to the include for
+//! each exported name that its content did not define.
+
+use std::collections::HashSet;
+
+use eyre::Result;
+
+use stencila_schema::{CompilationMessage, MessageLevel, Node, Primitive};
+
+use crate::Executor;
+
+/// The name of the argument used to list the symbols exported by an include
+pub(crate) const EXPORT_ARGUMENT: &str = "export";
+
+/// Get the names of exported symbols from the value of an `export` argument
+///
+/// The value can be a comma separated string of names or an array of strings.
+/// Returns `None` if the value is neither.
+pub(crate) fn export_names(value: &Node) -> Option<Vec<String>> {
+    let names: Vec<String> = match value {
+        Node::String(string) => string
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect(),
+        Node::Array(array) => array
+            .iter()
+            .map(|item| match item {
+                Primitive::String(name) => Some(name.trim().to_string()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+
+    Some(names.into_iter().filter(|name| !name.is_empty()).collect())
+}
+
+/// A scope within which the content of an include is executed, with only
+/// the exported symbols that it defines kept once the scope is exited
+pub(crate) struct ExportScope {
+    /// The names of the exported symbols
+    exports: Vec<String>,
+
+    /// The names of the variables in the kernels before the scope was entered
+    existing: HashSet<String>,
+}
+
+impl ExportScope {
+    /// Snapshot the names of the variables in the kernels
+    ///
+    /// Should be called before the arguments of the include are set, so that variables
+    /// for those are also removed, unless exported. Errors if the variables can not be listed.
+    pub async fn enter(exports: Vec<String>, executor: &Executor) -> Result<Self> {
+        let existing = variable_names(executor).await?;
+        Ok(Self { exports, existing })
+    }
+
+    /// Remove the variables defined within the scope that are not exported
+    ///
+    /// Returns a warning for each exported symbol that was not defined.
+    pub async fn exit(self, executor: &mut Executor) -> Vec<CompilationMessage> {
+        let defined = match variable_names(executor).await {
+            Ok(defined) => defined,
+            Err(error) => {
+                return vec![CompilationMessage::new(
+                    MessageLevel::Warning,
+                    format!("Unable to list variables so not removing unexported ones: {error}"),
+                )];
+            }
+        };
+
+        let mut kernels = executor.kernels.write().await;
+        for name in defined.difference(&self.existing) {
+            if self.exports.contains(name) {
+                continue;
+            }
+
+            if let Err(error) = kernels.remove(name).await {
+                tracing::debug!("While removing unexported variable `{name}`: {error}");
+            }
+        }
+
+        self.exports
+            .iter()
+            .filter(|name| !defined.contains(*name))
+            .map(|name| {
+                CompilationMessage::new(
+                    MessageLevel::Warning,
+                    format!("Exported symbol `{name}` is not defined by the included content"),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Get the names of the variables in the kernels of an executor
+async fn variable_names(executor: &Executor) -> Result<HashSet<String>> {
+    let names = executor.kernels.read().await.names().await?;
+    Ok(names.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use tokio::sync::RwLock;
+
+    use stencila_kernels::Kernels;
+    use stencila_schema::{Array, ExecutionBounds};
+
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            export_names(&Node::String(" clean, plot ,".into())),
+            Some(vec!["clean".to_string(), "plot".to_string()])
+        );
+        assert_eq!(
+            export_names(&Node::Array(Array(vec![Primitive::String("clean".into())]))),
+            Some(vec!["clean".to_string()])
+        );
+        assert_eq!(export_names(&Node::Integer(1)), None);
+    }
+
+    #[tokio::test]
+    async fn exports() -> Result<()> {
+        let kernels = Arc::new(RwLock::new(Kernels::new_here(ExecutionBounds::Main)));
+        kernels
+            .write()
+            .await
+            .set("existing", &Node::Integer(1), None)
+            .await?;
+        let mut executor = Executor::new(PathBuf::new(), kernels.clone(), None);
+
+        let scope = ExportScope::enter(vec!["kept".into(), "missing".into()], &executor).await?;
+        let mut guard = kernels.write().await;
+        guard.set("existing", &Node::Integer(2), None).await?;
+        guard.set("kept", &Node::Integer(3), None).await?;
+        guard.set("helper", &Node::Integer(4), None).await?;
+        drop(guard);
+        let messages = scope.exit(&mut executor).await;
+
+        let guard = kernels.read().await;
+        assert_eq!(guard.get("existing").await?, Some(Node::Integer(2)));
+        assert_eq!(guard.get("kept").await?, Some(Node::Integer(3)));
+        assert_eq!(guard.get("helper").await?, None);
+
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].message.contains("`missing`"));
+
+        Ok(())
+    }
+}
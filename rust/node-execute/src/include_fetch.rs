@@ -0,0 +1,759 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use eyre::Result;
+use reqwest::{
+    Client, NoProxy, Proxy, RequestBuilder, Response, StatusCode, Url,
+    header::{
+        ACCEPT, ACCEPT_RANGES, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+        HeaderMap, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+        USER_AGENT,
+    },
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{sleep, timeout},
+};
+
+use stencila_version::STENCILA_USER_AGENT;
+
+use crate::{
+    include_cache::{PartialDownload, RemoteValidators},
+    include_checksum::verify_sha256,
+    include_error::IncludeError,
+    include_rate::{RateLimits, throttle},
+};
+
+/// The client used to fetch remote include sources
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .user_agent(STENCILA_USER_AGENT)
+        .build()
+        .expect("invalid HTTP client configuration")
+});
+
+/// Whether the identifier of an include source is remote (a URL) rather than a local file path
+pub(crate) fn is_remote(identifier: &str) -> bool {
+    identifier.starts_with("https://")
+        || identifier.starts_with("http://")
+        || identifier.starts_with("data:")
+}
+
+/// Whether getting an include source requires network access (i.e. it is a HTTP(S) URL)
+pub(crate) fn is_network(identifier: &str) -> bool {
+    identifier.starts_with("https://") || identifier.starts_with("http://")
+}
+
+/// Get the local file path of a `file://` URL e.g. `file:///docs/My%20Notes.md`
+///
+/// Returns `None` if the source is not a `file://` URL, or if the URL can not be converted
+/// to a path (e.g. because it has a host other than `localhost`). Percent-encoded characters
+/// in the path are decoded.
+pub(crate) fn file_url_path(source: &str) -> Option<PathBuf> {
+    let scheme = source.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("file://") {
+        return None;
+    }
+
+    Url::parse(source).ok()?.to_file_path().ok()
+}
+
+/// Get the URL of the "directory" of a remote source (i.e. the URL without the last
+/// segment of its path) for relative sources within its content to be resolved against
+///
+/// The URL is returned as a path so that it can be pushed onto the executor's directory
+/// stack. Returns `None` if the source is not a HTTP(S) URL.
+pub(crate) fn url_dir(source: &str) -> Option<PathBuf> {
+    if !is_network(source) {
+        return None;
+    }
+
+    let url = Url::parse(source).ok()?.join(".").ok()?;
+    Some(PathBuf::from(url.to_string()))
+}
+
+/// Resolve a relative source against the URL of the "directory" of a remote parent
+///
+/// Returns `None` if `dir` is not a HTTP(S) URL (i.e. it is a local directory).
+pub(crate) fn join_url_dir(dir: &Path, source: &str) -> Option<String> {
+    let dir = dir.to_str().filter(|dir| is_network(dir))?;
+    let url = Url::parse(dir).ok()?.join(source).ok()?;
+    Some(url.to_string())
+}
+
+/// Get the `Accept` header for requesting a remote source in a media type
+///
+/// Returns `None` if the media type is not a MIME type (e.g. it is a format name such as
+/// `md`) so that servers are not sent an invalid header.
+pub(crate) fn accept_header(media_type: &str) -> Option<String> {
+    let media_type = media_type.trim();
+    let (kind, subtype) = media_type.split_once('/')?;
+    (!kind.is_empty() && !subtype.is_empty()).then(|| media_type.to_string())
+}
+
+/// Whether the `Content-Type` of a response is the media type that was requested
+///
+/// Compares the essence of each (i.e. without parameters such as `charset`) ignoring case.
+pub(crate) fn is_accepted(accept: &str, content_type: &str) -> bool {
+    let essence = |media_type: &str| {
+        media_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    };
+    essence(accept) == essence(content_type)
+}
+
+/// Clients used to fetch remote include sources via an explicit proxy, keyed by proxy URL
+static PROXY_CLIENTS: LazyLock<Mutex<HashMap<String, Client>>> = LazyLock::new(Mutex::default);
+
+/// Get the client to use for fetching remote include sources
+///
+/// If `proxy` is `None` then the default client is used, which uses any proxy specified
+/// in the `HTTP_PROXY`, `HTTPS_PROXY`, or `ALL_PROXY` environment variables. Otherwise, `proxy`
+/// is used for all requests, overriding those variables. In both cases, hosts listed
+/// in the `NO_PROXY` environment variable are not proxied.
+fn client(proxy: Option<&str>) -> Result<Client> {
+    let Some(proxy) = proxy else {
+        return Ok(CLIENT.clone());
+    };
+
+    if let Ok(clients) = PROXY_CLIENTS.lock()
+        && let Some(client) = clients.get(proxy)
+    {
+        return Ok(client.clone());
+    }
+
+    let client = Client::builder()
+        .user_agent(STENCILA_USER_AGENT)
+        .proxy(
+            Proxy::all(proxy)
+                .map_err(|error| {
+                    IncludeError::Network(format!("Invalid include proxy `{proxy}`: {error}"))
+                })?
+                .no_proxy(NoProxy::from_env()),
+        )
+        .build()?;
+
+    if let Ok(mut clients) = PROXY_CLIENTS.lock() {
+        clients.insert(proxy.to_string(), client.clone());
+    }
+
+    Ok(client)
+}
+
+/// The default number of times to retry fetching a remote source after a transient failure
+pub(crate) const DEFAULT_RETRIES: u32 = 2;
+
+/// The delay before the first retry, doubled for each subsequent retry
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The default maximum number of remote include sources to fetch concurrently
+pub(crate) const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The process-wide permits for fetching remote include sources
+///
+/// Shared by all executors (unless one sets its own limit) so that documents being
+/// compiled concurrently do not, together, open too many connections.
+pub(crate) static FETCH_PERMITS: LazyLock<Arc<Semaphore>> =
+    LazyLock::new(|| Arc::new(Semaphore::new(DEFAULT_CONCURRENCY)));
+
+/// Options for fetching remote include sources
+#[derive(Debug, Clone)]
+pub(crate) struct FetchOptions {
+    /// The proxy to use, overriding any set in environment variables
+    pub proxy: Option<String>,
+
+    /// The `User-Agent` header of requests, overriding the default of the client
+    pub user_agent: Option<String>,
+
+    /// The name and value of the header used to request a specific version of a source
+    pub version_header: Option<(String, String)>,
+
+    /// The permits limiting the number of sources fetched concurrently
+    pub permits: Arc<Semaphore>,
+
+    /// The maximum rates of requests to hosts
+    pub rate_limits: RateLimits,
+
+    /// The number of times to retry after a transient failure
+    pub retries: u32,
+
+    /// The timeout for each request, if any
+    pub timeout: Option<Duration>,
+
+    /// The expected SHA-256 digest of the fetched bytes, if any
+    pub sha256: Option<String>,
+
+    /// The `Accept` header of requests, derived from the media type of the include, if any
+    pub accept: Option<String>,
+}
+
+impl FetchOptions {
+    /// Apply the user agent, accept and version headers, and timeout options to a request
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(user_agent) = &self.user_agent {
+            request = request.header(USER_AGENT, user_agent);
+        }
+        if let Some(accept) = &self.accept {
+            request = request.header(ACCEPT, accept);
+        }
+        if let Some((name, value)) = &self.version_header {
+            request = request.header(name.as_str(), value);
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        request
+    }
+
+    /// Describe the request for a URL in error messages
+    ///
+    /// Includes any version header so that it is clear which version of the source failed.
+    fn describe(&self, url: &str) -> String {
+        match &self.version_header {
+            Some((name, value)) => format!("{url} with `{name}: {value}`"),
+            None => url.to_string(),
+        }
+    }
+}
+
+/// Acquire a permit to fetch a remote source
+///
+/// The permit should be held until the response body has been read. If there is
+/// a timeout, then the time waiting for a permit counts towards it, so that a
+/// request is not left waiting indefinitely behind many others.
+async fn acquire(url: &str, options: &FetchOptions) -> Result<OwnedSemaphorePermit> {
+    let permit = options.permits.clone().acquire_owned();
+    let permit = match options.timeout {
+        Some(duration) => timeout(duration, permit).await.map_err(|_| {
+            IncludeError::Network(format!(
+                "Timed out after {duration:?} waiting to fetch {url} because the limit \
+                 on concurrent fetches of remote includes was reached"
+            ))
+        })?,
+        None => permit.await,
+    };
+
+    Ok(permit?)
+}
+
+/// Send a request, retrying with exponential backoff after transient failures
+///
+/// Timeouts, connection errors, and responses with a `408`, `429` or `5xx` status are
+/// considered transient. Other error statuses (e.g. `401`, `404`) fail immediately.
+/// Connection and timeout errors include a hint about proxies. Errors after more than
+/// one attempt state the number of attempts made.
+async fn send<F>(build: F, url: &str, retries: u32) -> Result<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let attempts = retries + 1;
+    let mut attempt = 1;
+    loop {
+        let result = build().send().await;
+
+        let is_transient = match &result {
+            Ok(response) => {
+                let status = response.status();
+                status.is_server_error()
+                    || status == StatusCode::REQUEST_TIMEOUT
+                    || status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(error) => error.is_timeout() || error.is_connect() || error.is_request(),
+        };
+        if is_transient && attempt < attempts {
+            let delay = RETRY_BACKOFF * 2u32.pow(attempt - 1);
+            tracing::debug!("Transient failure fetching {url}, retrying in {delay:?}");
+            sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+
+        let after = if attempt > 1 {
+            format!(" (after {attempt} attempts)")
+        } else {
+            String::new()
+        };
+
+        return match result {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_client_error() || status.is_server_error() {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(IncludeError::from_status(
+                        status,
+                        status_message(status, url, &body, &after),
+                    )
+                    .into());
+                }
+                Ok(response)
+            }
+            Err(error) if error.is_connect() && is_dns_error(&error) => {
+                Err(IncludeError::Network(format!(
+                    "Unable to resolve host for {url}{after}: {error}. Check that the URL is \
+                     correct and that there is network access"
+                ))
+                .into())
+            }
+            Err(error) if error.is_connect() || error.is_timeout() => {
+                Err(IncludeError::Network(format!(
+                    "Unable to connect to {url}{after}: {error}. If behind a proxy, set the \
+                     `HTTPS_PROXY` environment variable or configure the proxy for includes"
+                ))
+                .into())
+            }
+            Err(error) => Err(IncludeError::Network(format!("{error}{after}")).into()),
+        };
+    }
+}
+
+/// The maximum length of a response body to include in the message for an error status
+const MAX_ERROR_BODY: usize = 200;
+
+/// Create the message for an error status e.g. `HTTP 404 Not Found fetching https://...`
+///
+/// The body of the response is appended if it is short and not markup (e.g. a plain
+/// text or JSON error message, rather than an HTML error page).
+fn status_message(status: StatusCode, url: &str, body: &str, after: &str) -> String {
+    let reason = status
+        .canonical_reason()
+        .map(|reason| format!(" {reason}"))
+        .unwrap_or_default();
+    let mut message = format!("HTTP {}{reason} fetching {url}{after}", status.as_u16());
+
+    let body = body.trim();
+    if !body.is_empty() && body.len() <= MAX_ERROR_BODY && !body.starts_with('<') {
+        message.push_str(": ");
+        message.push_str(body);
+    }
+
+    message
+}
+
+/// Whether a connection error was because the host name could not be resolved
+fn is_dns_error(error: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(error);
+    while let Some(error) = source {
+        if error.to_string().starts_with("dns error") {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// The result of fetching a remote include source
+pub(crate) enum Fetched {
+    /// The source has not been modified since the validators were obtained
+    NotModified,
+
+    /// The source was fetched
+    Content {
+        /// The bytes of the response body
+        bytes: Vec<u8>,
+
+        /// The `Content-Type` header of the response
+        content_type: Option<String>,
+
+        /// The `Content-Encoding` header of the response
+        content_encoding: Option<String>,
+
+        /// The validators for the response, to be used for subsequent conditional requests
+        validators: RemoteValidators,
+    },
+}
+
+/// Fetch a remote include source
+///
+/// If `validators` are supplied then a conditional request is made and
+/// [`Fetched::NotModified`] is returned if the server indicates that the
+/// source has not changed.
+///
+/// If `partial` is supplied (i.e. a previous download was interrupted) then a range
+/// request is made to resume the download. If the download is interrupted again, and
+/// the server supports range requests, then `partial` is updated with the bytes received
+/// so far, and the download is resumed up to `retries` times before failing. Servers that
+/// do not support range requests, or for which the source has changed, respond with the
+/// entire source, in which case the bytes previously received are discarded.
+pub(crate) async fn fetch_remote(
+    url: &str,
+    validators: Option<&RemoteValidators>,
+    partial: &mut Option<PartialDownload>,
+    options: &FetchOptions,
+) -> Result<Fetched> {
+    tracing::debug!("Fetching include source {url}");
+
+    throttle(url, &options.rate_limits, options.timeout).await?;
+    let _permit = acquire(url, options).await?;
+    let client = client(options.proxy.as_deref())?;
+    let mut resumes = 0;
+    'request: loop {
+        let resume = partial
+            .as_ref()
+            .map(|partial| (partial.bytes.len(), partial.if_range.clone()));
+
+        let request = || {
+            let mut request = options.apply(client.get(url));
+            if let Some((offset, if_range)) = &resume {
+                request = request
+                    .header(RANGE, format!("bytes={offset}-"))
+                    .header(IF_RANGE, if_range);
+            } else if let Some(validators) = validators {
+                if let Some(etag) = &validators.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &validators.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            request
+        };
+
+        let mut response = send(request, &options.describe(url), options.retries).await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_MODIFIED {
+            *partial = None;
+            return Ok(Fetched::NotModified);
+        }
+
+        let headers = response.headers().clone();
+        let content_range = content_range(&headers);
+
+        let mut new_validators = validators_from_headers(&headers);
+        let mut bytes = match (status, partial.take()) {
+            (StatusCode::PARTIAL_CONTENT, Some(partial))
+                if content_range.map(|(start, ..)| start) == Some(partial.bytes.len() as u64) =>
+            {
+                tracing::debug!(
+                    "Resuming download of {url} from byte {}",
+                    partial.bytes.len()
+                );
+                new_validators.content_length = content_range
+                    .and_then(|(.., total)| total)
+                    .map(|total| total.to_string());
+                partial.bytes
+            }
+            (StatusCode::PARTIAL_CONTENT, previous) => {
+                if previous.is_none() {
+                    return Err(IncludeError::Network(format!(
+                        "Server sent partial content for {url} which was not requested"
+                    ))
+                    .into());
+                }
+                // The range is not the one requested so fall back to fetching the entire source
+                tracing::debug!("Unexpected content range for {url}, refetching entire source");
+                continue 'request;
+            }
+            _ => Vec::new(),
+        };
+
+        // Read the body in chunks so that, if interrupted, the bytes received
+        // so far can be used to resume the download
+        let if_range = if_range(&headers).or(resume.map(|(.., if_range)| if_range));
+        let resumable = status == StatusCode::PARTIAL_CONTENT || accepts_ranges(&headers);
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => bytes.extend_from_slice(&chunk),
+                Ok(None) => break,
+                Err(error) => {
+                    let received = bytes.len();
+                    if resumable && let Some(if_range) = if_range {
+                        *partial = Some(PartialDownload { bytes, if_range });
+                        if resumes < options.retries {
+                            resumes += 1;
+                            tracing::debug!("Download of {url} interrupted, resuming: {error}");
+                            continue 'request;
+                        }
+                    }
+                    return Err(IncludeError::Network(format!(
+                        "Download of {url} interrupted after {received} bytes: {error}"
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        if let Some(expected) = &options.sha256 {
+            verify_sha256(&bytes, expected, url)?;
+        }
+
+        return Ok(Fetched::Content {
+            bytes,
+            content_type: header(&headers, CONTENT_TYPE),
+            content_encoding: header(&headers, CONTENT_ENCODING),
+            validators: new_validators,
+        });
+    }
+}
+
+/// Make a `HEAD` request for a remote include source and return its validators
+///
+/// Used as a cheaper freshness check than a conditional `GET` for large sources.
+///
+/// Not retried because, on failure, the source is fetched using `GET` anyway.
+pub(crate) async fn head_remote(url: &str, options: &FetchOptions) -> Result<RemoteValidators> {
+    tracing::debug!("Checking freshness of include source {url}");
+
+    throttle(url, &options.rate_limits, options.timeout).await?;
+    let _permit = acquire(url, options).await?;
+    let client = client(options.proxy.as_deref())?;
+    let request = || options.apply(client.head(url));
+    let response = send(request, &options.describe(url), 0).await?;
+
+    Ok(validators_from_headers(response.headers()))
+}
+
+/// Get the value of a header as a string
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+/// Whether the server indicates that it supports range requests
+fn accepts_ranges(headers: &HeaderMap) -> bool {
+    header(headers, ACCEPT_RANGES).is_some_and(|value| value.trim() == "bytes")
+}
+
+/// Get the value to use for the `If-Range` header of a request to resume a download
+///
+/// Weak `ETag`s can not be used for `If-Range` so the `Last-Modified` date is used instead.
+fn if_range(headers: &HeaderMap) -> Option<String> {
+    header(headers, ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(headers, LAST_MODIFIED))
+}
+
+/// Get the start byte, and total length (if known), from the `Content-Range` header
+///
+/// e.g. `bytes 1000-1999/5000` or `bytes 1000-1999/*`
+fn content_range(headers: &HeaderMap) -> Option<(u64, Option<u64>)> {
+    let value = header(headers, CONTENT_RANGE)?;
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, ..) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+/// Get the validators from the headers of a response
+fn validators_from_headers(headers: &HeaderMap) -> RemoteValidators {
+    RemoteValidators {
+        etag: header(headers, ETAG),
+        last_modified: header(headers, LAST_MODIFIED),
+        content_length: header(headers, CONTENT_LENGTH),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn file_urls() {
+        assert_eq!(
+            file_url_path("file:///docs/part.md"),
+            Some(PathBuf::from("/docs/part.md"))
+        );
+        assert_eq!(
+            file_url_path("FILE:///docs/My%20Notes.md"),
+            Some(PathBuf::from("/docs/My Notes.md"))
+        );
+        assert_eq!(
+            file_url_path("file://localhost/docs/part.md"),
+            Some(PathBuf::from("/docs/part.md"))
+        );
+        assert_eq!(file_url_path("file://example.org/docs/part.md"), None);
+        assert_eq!(file_url_path("docs/part.md"), None);
+        assert_eq!(file_url_path("https://example.org/part.md"), None);
+    }
+
+    #[test]
+    fn url_dirs() {
+        let dir = url_dir("https://example.org/docs/part.md?raw=true").expect("is url");
+        assert_eq!(dir, PathBuf::from("https://example.org/docs/"));
+        assert_eq!(url_dir("/home/docs/part.md"), None);
+        assert_eq!(url_dir("data:text/plain,hello"), None);
+
+        assert_eq!(
+            join_url_dir(&dir, "sub/nested.md").as_deref(),
+            Some("https://example.org/docs/sub/nested.md")
+        );
+        assert_eq!(
+            join_url_dir(&dir, "../other.md").as_deref(),
+            Some("https://example.org/other.md")
+        );
+        assert_eq!(join_url_dir(Path::new("/home/docs"), "nested.md"), None);
+    }
+
+    #[test]
+    fn content_ranges() {
+        let range = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_RANGE, HeaderValue::from_str(value).expect("valid"));
+            content_range(&headers)
+        };
+
+        assert_eq!(range("bytes 1000-1999/5000"), Some((1000, Some(5000))));
+        assert_eq!(range("bytes 0-99/*"), Some((0, None)));
+        assert_eq!(range("bytes */5000"), None);
+        assert_eq!(range("items 0-9/10"), None);
+    }
+
+    #[test]
+    fn status_messages() {
+        let url = "https://example.org/a.md";
+
+        assert_eq!(
+            status_message(StatusCode::NOT_FOUND, url, "<html>Not found</html>", ""),
+            "HTTP 404 Not Found fetching https://example.org/a.md"
+        );
+        assert_eq!(
+            status_message(
+                StatusCode::SERVICE_UNAVAILABLE,
+                url,
+                "Down for maintenance\n",
+                " (after 3 attempts)"
+            ),
+            "HTTP 503 Service Unavailable fetching https://example.org/a.md (after 3 attempts): \
+             Down for maintenance"
+        );
+        assert_eq!(
+            status_message(StatusCode::FORBIDDEN, url, &"x".repeat(1000), ""),
+            "HTTP 403 Forbidden fetching https://example.org/a.md"
+        );
+    }
+
+    #[test]
+    fn user_agents() -> Result<()> {
+        let mut options = FetchOptions {
+            proxy: None,
+            user_agent: None,
+            version_header: None,
+            permits: Arc::new(Semaphore::new(1)),
+            rate_limits: RateLimits::default(),
+            retries: 0,
+            timeout: None,
+            sha256: None,
+            accept: None,
+        };
+        let user_agent = |options: &FetchOptions| -> Result<Option<String>> {
+            let request = options
+                .apply(CLIENT.get("https://example.org/a.md"))
+                .build()?;
+            Ok(header(request.headers(), USER_AGENT))
+        };
+
+        // The default user agent is set by the client, rather than on each request
+        assert_eq!(user_agent(&options)?, None);
+
+        options.user_agent = Some("Pipeline/1.0".into());
+        assert_eq!(user_agent(&options)?.as_deref(), Some("Pipeline/1.0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn version_headers() -> Result<()> {
+        let options = FetchOptions {
+            proxy: None,
+            user_agent: None,
+            version_header: Some(("Accept-Version".into(), "2".into())),
+            permits: Arc::new(Semaphore::new(1)),
+            rate_limits: RateLimits::default(),
+            retries: 0,
+            timeout: None,
+            sha256: None,
+            accept: None,
+        };
+
+        let url = "https://example.org/a.md";
+        let request = options.apply(CLIENT.get(url)).build()?;
+        assert_eq!(
+            header(request.headers(), HeaderName::from_static("accept-version")).as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            options.describe(url),
+            "https://example.org/a.md with `Accept-Version: 2`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn accept_headers() -> Result<()> {
+        assert_eq!(
+            accept_header(" application/json ").as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(accept_header("md"), None);
+        assert_eq!(accept_header("text/"), None);
+
+        assert!(is_accepted(
+            "application/json",
+            "Application/JSON; charset=utf-8"
+        ));
+        assert!(!is_accepted("application/json", "text/html"));
+
+        let options = FetchOptions {
+            proxy: None,
+            user_agent: None,
+            version_header: None,
+            permits: Arc::new(Semaphore::new(1)),
+            rate_limits: RateLimits::default(),
+            retries: 0,
+            timeout: None,
+            sha256: None,
+            accept: accept_header("application/json"),
+        };
+        let request = options
+            .apply(CLIENT.get("https://example.org/data"))
+            .build()?;
+        assert_eq!(
+            header(request.headers(), ACCEPT).as_deref(),
+            Some("application/json")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() -> Result<()> {
+        let options = FetchOptions {
+            proxy: None,
+            user_agent: None,
+            version_header: None,
+            permits: Arc::new(Semaphore::new(1)),
+            rate_limits: RateLimits::default(),
+            retries: 0,
+            timeout: Some(Duration::from_millis(10)),
+            sha256: None,
+            accept: None,
+        };
+
+        let permit = acquire("https://example.org/a.md", &options).await?;
+
+        let error = acquire("https://example.org/b.md", &options)
+            .await
+            .expect_err("should time out waiting for a permit");
+        assert!(matches!(
+            error.downcast_ref::<IncludeError>(),
+            Some(IncludeError::Network(..))
+        ));
+
+        drop(permit);
+        acquire("https://example.org/b.md", &options).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Filtering of the content of includes using a predicate
+//!
+//! Allows those embedding the executor to programmatically drop blocks from the content
+//! of all includes (e.g. to remove all code chunks for a "clean" render). Unlike `select`,
+//! which selects from the content of a single include, a filter applies to the content of
+//! every include, at all levels of nesting.
+
+use std::sync::Arc;
+
+use stencila_schema::{
+    Admonition, Block, CallBlock, Claim, Figure, ForBlock, IfBlockClause, IncludeBlock, ListItem,
+    QuoteBlock, Section, StyledBlock, TableCell, VisitorMut, WalkControl, WalkthroughStep,
+};
+
+/// A predicate for the blocks to keep in the content of includes
+///
+/// Blocks for which the predicate returns `false` are removed, along with any content
+/// they contain. See [`crate::Executor::set_include_filter`].
+pub type IncludeFilter = Arc<dyn Fn(&Block) -> bool + Send + Sync>;
+
+/// Remove the blocks that do not pass a filter, including those nested within other blocks
+pub(crate) fn filter_blocks(blocks: &mut Vec<Block>, filter: &IncludeFilter) {
+    let mut filterer = Filterer { filter };
+    filterer.retain(blocks);
+    for block in blocks.iter_mut() {
+        filterer.walk(block);
+    }
+}
+
+/// A visitor that removes the blocks that do not pass a filter from block content
+struct Filterer<'f> {
+    filter: &'f IncludeFilter,
+}
+
+impl Filterer<'_> {
+    /// Retain only those blocks that pass the filter
+    fn retain(&self, blocks: &mut Vec<Block>) {
+        blocks.retain(|block| (self.filter)(block));
+    }
+}
+
+impl VisitorMut for Filterer<'_> {
+    fn visit_block(&mut self, block: &mut Block) -> WalkControl {
+        if let Block::Admonition(Admonition { content, .. })
+        | Block::Claim(Claim { content, .. })
+        | Block::Figure(Figure { content, .. })
+        | Block::ForBlock(ForBlock { content, .. })
+        | Block::QuoteBlock(QuoteBlock { content, .. })
+        | Block::Section(Section { content, .. })
+        | Block::StyledBlock(StyledBlock { content, .. })
+        | Block::IncludeBlock(IncludeBlock {
+            content: Some(content),
+            ..
+        })
+        | Block::CallBlock(CallBlock {
+            content: Some(content),
+            ..
+        }) = block
+        {
+            self.retain(content);
+        }
+
+        WalkControl::Continue
+    }
+
+    fn visit_if_block_clause(&mut self, clause: &mut IfBlockClause) -> WalkControl {
+        self.retain(&mut clause.content);
+        WalkControl::Continue
+    }
+
+    fn visit_list_item(&mut self, list_item: &mut ListItem) -> WalkControl {
+        self.retain(&mut list_item.content);
+        WalkControl::Continue
+    }
+
+    fn visit_table_cell(&mut self, table_cell: &mut TableCell) -> WalkControl {
+        self.retain(&mut table_cell.content);
+        WalkControl::Continue
+    }
+
+    fn visit_walkthrough_step(&mut self, step: &mut WalkthroughStep) -> WalkControl {
+        self.retain(&mut step.content);
+        WalkControl::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::shortcuts::{cc, p, sec, t};
+
+    use super::*;
+
+    #[test]
+    fn filters() {
+        let filter: IncludeFilter = Arc::new(|block| !matches!(block, Block::CodeChunk(..)));
+
+        let mut blocks = vec![
+            p([t("para")]),
+            cc("top", None::<String>),
+            sec([cc("nested", None::<String>), p([t("nested para")])]),
+        ];
+        filter_blocks(&mut blocks, &filter);
+
+        assert_eq!(blocks, vec![p([t("para")]), sec([p([t("nested para")])])]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Flattening of nested includes into the content of their parents
+//!
+//! When exporting a fully resolved document it is often preferable for it to have no
+//! `IncludeBlock`s at all. When the `flatten_includes` compile option is enabled, after
+//! a document has been compiled each include is replaced by an `Excerpt` containing its
+//! content, recursively, so that the content of nested includes is also flattened. The
+//! `source` of each excerpt records the resolved identifier of the include's source so
+//! that the provenance of flattened blocks is not lost.
+//!
+//! Only content that was included during compilation is flattened, so the same cycle and
+//! depth guards apply. Includes without content (e.g. because their `if` argument was
+//! falsy, or their source could not be fetched) are removed. Deduplicated includes (see
+//! [`crate::Executor::set_include_dedup`]) get the content of the include they reference.
+
+use std::{collections::HashMap, path::Path};
+
+use stencila_schema::{
+    Admonition, Article, Block, CallBlock, Claim, Excerpt, Figure, ForBlock, IfBlockClause,
+    IncludeBlock, ListItem, Node, QuoteBlock, Reference, Section, StyledBlock, TableCell,
+    VisitorMut, WalkControl, WalkNode, WalkthroughStep,
+};
+
+use crate::include_deps::include_dependencies;
+
+/// Replace the `IncludeBlock`s within a node with their content, including those nested
+/// within that content
+///
+/// Sources are resolved relative to `home` in the same way as when compiling. Only
+/// includes within the content of an `Article` (or of other blocks) are flattened.
+pub(crate) fn flatten_includes<T: WalkNode>(home: &Path, node: &mut T) {
+    // For fallback chains, use the first alternative (the one that nested
+    // sources are resolved against)
+    let mut identifiers = HashMap::new();
+    for dependency in include_dependencies(home, &*node) {
+        identifiers
+            .entry(dependency.node_id.to_string())
+            .or_insert(dependency.identifier);
+    }
+
+    let mut flattener = Flattener {
+        identifiers,
+        contents: HashMap::new(),
+    };
+    flattener.walk(node);
+}
+
+/// A visitor that replaces `IncludeBlock`s in block content with excerpts
+struct Flattener {
+    /// The resolved identifiers of the sources of includes, keyed by node id
+    identifiers: HashMap<String, String>,
+
+    /// The content of the includes flattened so far, keyed by node id
+    ///
+    /// Used to get the content of deduplicated includes, which always come
+    /// after the include that they reference.
+    contents: HashMap<String, Vec<Block>>,
+}
+
+impl Flattener {
+    /// Flatten the includes in block content
+    fn flatten(&mut self, blocks: &mut Vec<Block>) {
+        if !blocks
+            .iter()
+            .any(|block| matches!(block, Block::IncludeBlock(..)))
+        {
+            return;
+        }
+
+        *blocks = blocks
+            .drain(..)
+            .filter_map(|block| match block {
+                Block::IncludeBlock(include) => self.excerpt(include).map(Block::Excerpt),
+                block => Some(block),
+            })
+            .collect();
+    }
+
+    /// Create an excerpt from an include, or `None` if it has no content
+    fn excerpt(&mut self, include: IncludeBlock) -> Option<Excerpt> {
+        let node_id = include.node_id().to_string();
+
+        let content = match (include.content, include.options.content_from) {
+            (Some(content), ..) => content,
+            (None, Some(content_from)) => self.contents.get(&content_from)?.clone(),
+            (None, None) => return None,
+        };
+        self.contents.insert(node_id.clone(), content.clone());
+
+        let identifier = self.identifiers.remove(&node_id).unwrap_or(include.source);
+        let source = Reference {
+            url: Some(identifier),
+            ..Default::default()
+        };
+
+        Some(Excerpt::new(source, String::new(), String::new(), content))
+    }
+}
+
+impl VisitorMut for Flattener {
+    fn visit_node(&mut self, node: &mut Node) -> WalkControl {
+        if let Node::Article(Article { content, .. }) = node {
+            self.flatten(content);
+        }
+
+        WalkControl::Continue
+    }
+
+    fn visit_block(&mut self, block: &mut Block) -> WalkControl {
+        if let Block::Admonition(Admonition { content, .. })
+        | Block::Claim(Claim { content, .. })
+        | Block::Excerpt(Excerpt { content, .. })
+        | Block::Figure(Figure { content, .. })
+        | Block::ForBlock(ForBlock { content, .. })
+        | Block::QuoteBlock(QuoteBlock { content, .. })
+        | Block::Section(Section { content, .. })
+        | Block::StyledBlock(StyledBlock { content, .. })
+        | Block::CallBlock(CallBlock {
+            content: Some(content),
+            ..
+        }) = block
+        {
+            self.flatten(content);
+        }
+
+        WalkControl::Continue
+    }
+
+    fn visit_if_block_clause(&mut self, clause: &mut IfBlockClause) -> WalkControl {
+        self.flatten(&mut clause.content);
+        WalkControl::Continue
+    }
+
+    fn visit_list_item(&mut self, list_item: &mut ListItem) -> WalkControl {
+        self.flatten(&mut list_item.content);
+        WalkControl::Continue
+    }
+
+    fn visit_table_cell(&mut self, table_cell: &mut TableCell) -> WalkControl {
+        self.flatten(&mut table_cell.content);
+        WalkControl::Continue
+    }
+
+    fn visit_walkthrough_step(&mut self, step: &mut WalkthroughStep) -> WalkControl {
+        self.flatten(&mut step.content);
+        WalkControl::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::{
+        IncludeBlockOptions,
+        shortcuts::{p, sec, t},
+    };
+
+    use super::*;
+
+    #[test]
+    fn flattening() {
+        let nested = IncludeBlock {
+            content: Some(vec![p([t("nested")])]),
+            ..IncludeBlock::new("nested.md".into())
+        };
+        let outer = IncludeBlock {
+            content: Some(vec![p([t("outer")]), sec([Block::IncludeBlock(nested)])]),
+            ..IncludeBlock::new("parts/outer.md".into())
+        };
+        let deduped = IncludeBlock {
+            options: Box::new(IncludeBlockOptions {
+                content_from: Some(outer.node_id().to_string()),
+                ..Default::default()
+            }),
+            ..IncludeBlock::new("parts/outer.md".into())
+        };
+        let excluded = IncludeBlock::new("excluded.md".into());
+
+        let mut article = Node::Article(Article::new(vec![
+            Block::IncludeBlock(outer),
+            Block::IncludeBlock(deduped),
+            Block::IncludeBlock(excluded),
+        ]));
+        flatten_includes(Path::new("/home"), &mut article);
+
+        let excerpt = |url: &str, content| {
+            Block::Excerpt(Excerpt::new(
+                Reference {
+                    url: Some(url.into()),
+                    ..Default::default()
+                },
+                String::new(),
+                String::new(),
+                content,
+            ))
+        };
+        let outer = excerpt(
+            "/home/parts/outer.md",
+            vec![
+                p([t("outer")]),
+                sec([excerpt("/home/parts/nested.md", vec![p([t("nested")])])]),
+            ],
+        );
+        assert_eq!(
+            article,
+            Node::Article(Article::new(vec![outer.clone(), outer]))
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Isolated execution of the content of includes
+//!
+//! By default, the content of an include is executed in the kernels of the including
+//! document, so that variables assigned by included code (and the include's arguments)
+//! are available to the nodes that follow it. When an include has a truthy `isolate`
+//! argument (e.g. `--isolate=true`) its arguments are set, and its content is executed,
+//! within a fork of those kernels instead. Included code can still use the variables of
+//! the including document but any variables that it assigns are discarded once it has
+//! been executed. Only the outputs of the included content are kept in the document.
+//!
+//! Isolation is not free: the kernels are forked each time the include is executed. For
+//! kernels that run in a separate process (e.g. Python, R) this involves forking that
+//! process, which takes longer, and uses more memory, the more state the kernel holds.
+//! Kernels that do not support forking can not be isolated, in which case the content
+//! of the include is not executed and an error message is added to the include.
+
+use std::sync::Arc;
+
+use eyre::Result;
+use tokio::sync::RwLock;
+
+use stencila_kernels::Kernels;
+use stencila_schema::ExecutionBounds;
+
+use crate::Executor;
+
+/// The name of the argument used to execute the content of an include in isolation
+pub(crate) const ISOLATE_ARGUMENT: &str = "isolate";
+
+/// A scope within which an executor uses a fork of its kernels
+pub(crate) struct IsolationScope {
+    /// The kernels of the executor before the scope was entered
+    kernels: Arc<RwLock<Kernels>>,
+}
+
+impl IsolationScope {
+    /// Replace the kernels of the executor with a fork of them
+    ///
+    /// Errors if any of the kernels can not be forked.
+    pub async fn enter(executor: &mut Executor) -> Result<Self> {
+        let fork = executor
+            .replicate_kernels(ExecutionBounds::Fork, None)
+            .await?;
+        let kernels = std::mem::replace(&mut executor.kernels, fork);
+
+        Ok(Self { kernels })
+    }
+
+    /// Restore the kernels of the executor, discarding the fork
+    pub fn exit(self, executor: &mut Executor) {
+        executor.kernels = self.kernels;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use stencila_schema::Node;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn isolation() -> Result<()> {
+        let kernels = Arc::new(RwLock::new(Kernels::new_here(ExecutionBounds::Main)));
+        kernels
+            .write()
+            .await
+            .set("host", &Node::Integer(1), None)
+            .await?;
+        let mut executor = Executor::new(PathBuf::new(), kernels.clone(), None);
+
+        // Variables of the host are available within the scope...
+        let scope = IsolationScope::enter(&mut executor).await?;
+        assert!(!Arc::ptr_eq(&executor.kernels, &kernels));
+        let mut fork = executor.kernels.write().await;
+        assert_eq!(fork.bounds(), ExecutionBounds::Fork);
+        assert_eq!(fork.get("host").await?, Some(Node::Integer(1)));
+        fork.set("leaked", &Node::Integer(2), None).await?;
+        drop(fork);
+        scope.exit(&mut executor);
+
+        // ...but those assigned within it are discarded
+        assert!(Arc::ptr_eq(&executor.kernels, &kernels));
+        assert_eq!(kernels.read().await.get("leaked").await?, None);
+
+        Ok(())
+    }
+}
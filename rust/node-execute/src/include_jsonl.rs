@@ -0,0 +1,225 @@
+//! Decoding of JSON Lines sources into a `Datatable`
+//!
+//! Each line of the source is a JSON object which becomes a row of the datatable.
+//! The columns are the union of the keys of all objects, in the order that they
+//! are first encountered, with missing values being null. Local files are decoded
+//! as they are read, rather than being read into memory first.
+
+use eyre::Result;
+use indexmap::IndexMap;
+use serde_json::Value;
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+};
+
+use stencila_schema::{Datatable, DatatableColumn};
+
+/// The media types of JSON Lines sources
+const JSONL_MEDIA_TYPES: [&str; 3] = [
+    "application/x-ndjson",
+    "application/jsonl",
+    "application/x-jsonlines",
+];
+
+/// The file extensions of JSON Lines sources
+const JSONL_EXTENSIONS: [&str; 2] = [".jsonl", ".ndjson"];
+
+/// Whether a source is JSON Lines, based on its media type or, if none, its path
+pub(crate) fn is_jsonl(media_type: &Option<String>, path: &str) -> bool {
+    match media_type {
+        Some(media_type) => {
+            let media_type = media_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            JSONL_MEDIA_TYPES.contains(&media_type.as_str())
+        }
+        None => {
+            let path = path.to_lowercase();
+            JSONL_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+        }
+    }
+}
+
+/// Decode JSON Lines into a datatable
+///
+/// Blank lines are ignored. Lines that are not valid JSON, or are not a JSON object,
+/// are skipped. If `limit` is specified, then no more than that number of rows are
+/// decoded. Returns the datatable and the number of lines that were skipped.
+pub(crate) fn jsonl_to_datatable(text: &str, limit: Option<usize>) -> (Datatable, usize) {
+    let mut decoder = JsonlDecoder::new(limit);
+    for line in text.lines() {
+        if decoder.is_full() {
+            break;
+        }
+        decoder.decode_line(line);
+    }
+    decoder.finish()
+}
+
+/// Decode a JSON Lines file into a datatable
+///
+/// As for [`jsonl_to_datatable`] but the file is read, and decoded, a line at a time
+/// rather than being read into memory in its entirety. Reading stops once `limit` rows
+/// have been decoded so only the start of large files, when limited, is read.
+pub(crate) async fn read_jsonl(path: &str, limit: Option<usize>) -> Result<(Datatable, usize)> {
+    let mut reader = BufReader::new(File::open(path).await?);
+    let mut decoder = JsonlDecoder::new(limit);
+    let mut line = Vec::new();
+    while !decoder.is_full() {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).await? == 0 {
+            break;
+        }
+        decoder.decode_line(&String::from_utf8_lossy(&line));
+    }
+    Ok(decoder.finish())
+}
+
+/// An incremental decoder of JSON Lines into a datatable
+///
+/// Each line is decoded directly into the columns of the datatable so that
+/// the rows of the source do not all need to be held in memory at once.
+struct JsonlDecoder {
+    /// The values of each column, in the order that they were first encountered
+    columns: IndexMap<String, Vec<Value>>,
+
+    /// The number of rows decoded
+    rows: usize,
+
+    /// The number of lines skipped
+    skipped: usize,
+
+    /// The maximum number of rows to decode
+    limit: Option<usize>,
+}
+
+impl JsonlDecoder {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            columns: IndexMap::new(),
+            rows: 0,
+            skipped: 0,
+            limit,
+        }
+    }
+
+    /// Whether the maximum number of rows have been decoded
+    fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.rows >= limit)
+    }
+
+    /// Decode a line into a row, skipping it if it is not a JSON object
+    fn decode_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+
+        let Ok(Value::Object(row)) = serde_json::from_str(line) else {
+            self.skipped += 1;
+            return;
+        };
+
+        for (key, value) in row {
+            self.columns
+                .entry(key)
+                .or_insert_with(|| vec![Value::Null; self.rows])
+                .push(value);
+        }
+        self.rows += 1;
+
+        // Fill in nulls for the columns that this row did not have a value for
+        for values in self.columns.values_mut() {
+            values.resize(self.rows, Value::Null);
+        }
+    }
+
+    /// Finish decoding, returning the datatable and the number of lines skipped
+    fn finish(self) -> (Datatable, usize) {
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|(name, values)| DatatableColumn::from_json_values(name, values))
+            .collect();
+
+        (Datatable::new(columns), self.skipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use stencila_schema::{Null, Primitive};
+
+    use super::*;
+
+    #[test]
+    fn jsonl() {
+        assert!(is_jsonl(&None, "data/rows.jsonl"));
+        assert!(is_jsonl(&None, "/rows.NDJSON"));
+        assert!(!is_jsonl(&None, "rows.json"));
+        assert!(is_jsonl(
+            &Some("application/x-ndjson; charset=utf-8".into()),
+            "rows"
+        ));
+        assert!(!is_jsonl(&Some("application/json".into()), "rows.jsonl"));
+
+        let text = r#"{"a": 1, "b": "x"}
+
+not json
+{"b": "y", "c": true}
+[1, 2]
+{"a": 3}
+"#;
+
+        let (datatable, skipped) = jsonl_to_datatable(text, None);
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            datatable
+                .columns
+                .iter()
+                .map(|column| column.name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+        assert_eq!(
+            datatable.columns[0].values,
+            [
+                Primitive::Integer(1),
+                Primitive::Null(Null),
+                Primitive::Integer(3)
+            ]
+        );
+        assert_eq!(datatable.columns[2].values.len(), 3);
+
+        let (datatable, skipped) = jsonl_to_datatable(text, Some(2));
+        assert_eq!(skipped, 1);
+        assert_eq!(datatable.columns[1].values.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn streaming() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("rows.jsonl");
+        tokio::fs::write(&path, b"{\"a\": 1}\n{\"a\": \"\xff\"}\n\n{\"b\": 2}").await?;
+        let path = path.to_string_lossy();
+
+        let (datatable, skipped) = read_jsonl(&path, None).await?;
+        assert_eq!(skipped, 0);
+        assert_eq!(datatable.columns.len(), 2);
+        assert_eq!(datatable.columns[0].values.len(), 3);
+
+        let (datatable, ..) = read_jsonl(&path, Some(1)).await?;
+        assert_eq!(datatable.columns.len(), 1);
+        assert_eq!(datatable.columns[0].values, [Primitive::Integer(1)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! Selection of regions delimited by marker lines from the text of a source
+//!
+//! For sources that do not use AsciiDoc style tags (see [`crate::include_tags`]), a
+//! region can be selected using regular expressions for its start and end marker lines
+//! e.g. `markers:BEGIN snippet,END snippet` selects the lines between a line matching
+//! `BEGIN snippet` and the next line matching `END snippet`. As for tagged regions, the
+//! region is selected from the text of the source before it is decoded, and the marker
+//! lines themselves are not part of it.
+//!
+//! The start and end expressions are separated by the first comma that is not escaped
+//! with a backslash, so a literal comma in the start expression can be written as `\,`.
+//! If there is more than one marked region, the first is selected.
+
+use eyre::{Result, bail};
+use regex::Regex;
+
+/// The prefix of a `select` for selecting a region delimited by markers
+pub(crate) const MARKERS_PREFIX: &str = "markers:";
+
+/// The regular expressions for the start and end markers of a region
+#[derive(Debug)]
+pub(crate) struct Markers {
+    start: Regex,
+    end: Regex,
+}
+
+/// The marker that was not matched when selecting a region
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Unmatched {
+    Start,
+    End,
+}
+
+impl Markers {
+    /// Parse markers from the part of a `select` after the [`MARKERS_PREFIX`]
+    pub fn parse(markers: &str) -> Result<Self> {
+        let split = markers
+            .char_indices()
+            .find(|&(index, char)| char == ',' && !markers[..index].ends_with('\\'))
+            .map(|(index, ..)| index);
+        let Some(split) = split else {
+            bail!("Expected start and end markers separated by a comma in `{markers}`")
+        };
+
+        let (start, end) = (markers[..split].trim(), markers[split + 1..].trim());
+        if start.is_empty() || end.is_empty() {
+            bail!("Expected non-empty start and end markers in `{markers}`")
+        }
+
+        let regex = |pattern: &str| match Regex::new(pattern) {
+            Ok(regex) => Ok(regex),
+            Err(error) => bail!("Invalid marker `{pattern}`: {error}"),
+        };
+
+        Ok(Self {
+            start: regex(start)?,
+            end: regex(end)?,
+        })
+    }
+
+    /// Select the lines of the first region of text between the markers
+    pub fn region(&self, text: &str) -> Result<String, Unmatched> {
+        let mut lines = text.lines();
+        if !lines.any(|line| self.start.is_match(line)) {
+            return Err(Unmatched::Start);
+        }
+
+        let mut region = String::new();
+        for line in lines {
+            if self.end.is_match(line) {
+                return Ok(region);
+            }
+            region.push_str(line);
+            region.push('\n');
+        }
+
+        Err(Unmatched::End)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions() -> Result<()> {
+        let text = r#"fn main() {
+    // BEGIN snippet
+    let a = 1;
+    // END snippet
+
+    // BEGIN snippet
+    let b = 2;
+    // END snippet
+}
+"#;
+
+        let markers = Markers::parse("BEGIN snippet, END snippet")?;
+        assert_eq!(markers.region(text), Ok("    let a = 1;\n".to_string()));
+
+        let markers = Markers::parse(r"BEGIN \w+,END \w+")?;
+        assert_eq!(markers.region(text), Ok("    let a = 1;\n".to_string()));
+
+        let markers = Markers::parse("BEGIN other,END other")?;
+        assert_eq!(markers.region(text), Err(Unmatched::Start));
+
+        let markers = Markers::parse("BEGIN snippet,END other")?;
+        assert_eq!(markers.region(text), Err(Unmatched::End));
+
+        let markers = Markers::parse(r"a\,b,c")?;
+        assert_eq!(markers.region("a,b\nx\nc\n"), Ok("x\n".to_string()));
+
+        assert!(Markers::parse("BEGIN snippet").is_err());
+        assert!(Markers::parse("BEGIN snippet,").is_err());
+        assert!(Markers::parse("BEGIN (,END").is_err());
+
+        Ok(())
+    }
+}
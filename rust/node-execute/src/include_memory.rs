@@ -0,0 +1,47 @@
+//! In-memory sources for includes
+//!
+//! Allows tests, and those embedding the executor, to supply the content of include
+//! sources programmatically rather than from files or remote URLs. In-memory sources are
+//! keyed by resolved identifier and are consulted before the file system or network.
+
+use stencila_schema::Block;
+
+/// The content of an in-memory source for includes
+///
+/// See [`crate::Executor::set_include_source`].
+#[derive(Debug, Clone)]
+pub enum IncludeSource {
+    /// The bytes of the source, decoded in the same way as the bytes of a file
+    /// (i.e. using the format of the include's `mediaType` or of the identifier's extension)
+    Bytes(Vec<u8>),
+
+    /// Blocks that are the already decoded content of the source
+    ///
+    /// Arguments of the include that apply to the bytes of a source (e.g. `raw` and
+    /// `encoding`) can not be used with this kind of source.
+    Blocks(Vec<Block>),
+}
+
+impl From<Vec<u8>> for IncludeSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<&str> for IncludeSource {
+    fn from(text: &str) -> Self {
+        Self::Bytes(text.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for IncludeSource {
+    fn from(text: String) -> Self {
+        Self::Bytes(text.into_bytes())
+    }
+}
+
+impl From<Vec<Block>> for IncludeSource {
+    fn from(blocks: Vec<Block>) -> Self {
+        Self::Blocks(blocks)
+    }
+}
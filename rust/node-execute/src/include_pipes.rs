@@ -0,0 +1,383 @@
+//! Filters applied to the values of include argument expressions
+//!
+//! As in template languages such as Jinja, the code of an argument expression may be
+//! followed by a pipeline of filters, separated by `|`, which transform its value
+//! e.g. `--slug={{title | lower | replace(' ', '-')}}`. The expression before the first
+//! filter is evaluated by the kernels, and the filters are then applied in order.
+//!
+//! Filter arguments must be string, number, or boolean literals. Code containing `||`,
+//! or a `|` within quotes or brackets, is not split, so the logical-or operators of
+//! kernel languages can still be used in expressions.
+
+use std::sync::LazyLock;
+
+use eyre::{Result, bail};
+use itertools::Itertools;
+use regex::Regex;
+
+use stencila_schema::{Node, Null};
+
+/// The names of the available filters, listed when a filter is unknown
+const FILTER_NAMES: [&str; 5] = ["lower", "upper", "trim", "replace", "default"];
+
+/// A regex for a filter, with optional arguments, in a pipeline
+static FILTER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z_]\w*)\s*(?:\(([\s\S]*)\))?$").expect("invalid regex"));
+
+/// A filter applied to the value of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Filter {
+    /// Convert a string to lowercase
+    Lower,
+
+    /// Convert a string to uppercase
+    Upper,
+
+    /// Remove leading and trailing whitespace from a string
+    Trim,
+
+    /// Replace all occurrences of a substring of a string
+    Replace(String, String),
+
+    /// Use a default if the value is null or an empty string, or the expression fails
+    Default(Node),
+}
+
+/// An expression followed by a pipeline of filters
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Pipeline {
+    /// The code of the expression to evaluate
+    pub expression: String,
+
+    /// The filters to apply to the value of the expression
+    pub filters: Vec<Filter>,
+}
+
+impl Pipeline {
+    /// Parse the code of an argument expression into a pipeline
+    ///
+    /// Returns `None` if the code has no filters, or if any of the parts after the
+    /// expression do not have the syntax of a filter, in which case the code should
+    /// be evaluated as is. Returns an error if a filter is unknown or has invalid arguments.
+    pub fn parse(code: &str) -> Result<Option<Self>> {
+        let parts = split_top_level(code, '|');
+        let Some((expression, rest)) = parts.split_first() else {
+            return Ok(None);
+        };
+        if rest.is_empty() || expression.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let Some(filters) = rest
+            .iter()
+            .map(|part| FILTER.captures(part.trim()))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(None);
+        };
+
+        let filters = filters
+            .iter()
+            .map(|captures| {
+                let name = &captures[1];
+                let args = match captures.get(2) {
+                    Some(args) => parse_args(name, args.as_str())?,
+                    None => Vec::new(),
+                };
+                Filter::new(name, args)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Self {
+            expression: expression.trim().to_string(),
+            filters,
+        }))
+    }
+
+    /// The value to use if the expression fails to evaluate, if any
+    ///
+    /// Only a `default` filter that immediately follows the expression provides a fallback
+    /// (e.g. for `{{title | default('Untitled')}}` when `title` is not defined).
+    pub fn fallback(&self) -> Option<&Node> {
+        match self.filters.first() {
+            Some(Filter::Default(node)) => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Apply the filters of the pipeline, in order, to a value
+    pub fn apply(&self, value: Node) -> Result<Node> {
+        self.filters
+            .iter()
+            .try_fold(value, |value, filter| filter.apply(value))
+    }
+}
+
+impl Filter {
+    /// Create a filter from its name and arguments
+    fn new(name: &str, args: Vec<Node>) -> Result<Self> {
+        let arity = match name {
+            "lower" | "upper" | "trim" => 0,
+            "replace" => 2,
+            "default" => 1,
+            _ => bail!(
+                "Unknown filter `{name}` (available filters: {})",
+                FILTER_NAMES
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .join(", ")
+            ),
+        };
+        if args.len() != arity {
+            bail!(
+                "Filter `{name}` expects {arity} argument{} but got {}",
+                if arity == 1 { "" } else { "s" },
+                args.len()
+            )
+        }
+
+        let mut args = args.into_iter();
+        if name == "default" {
+            return Ok(Filter::Default(args.next().unwrap_or(Node::Null(Null))));
+        }
+
+        let mut next_string = || match args.next() {
+            Some(Node::String(string)) => Ok(string),
+            Some(node) => bail!(
+                "Filter `{name}` expects string arguments but got a {}",
+                node.node_type()
+            ),
+            None => bail!("Filter `{name}` is missing an argument"),
+        };
+
+        Ok(match name {
+            "lower" => Filter::Lower,
+            "upper" => Filter::Upper,
+            "trim" => Filter::Trim,
+            _ => Filter::Replace(next_string()?, next_string()?),
+        })
+    }
+
+    /// Get the name of the filter
+    fn name(&self) -> &'static str {
+        match self {
+            Filter::Lower => "lower",
+            Filter::Upper => "upper",
+            Filter::Trim => "trim",
+            Filter::Replace(..) => "replace",
+            Filter::Default(..) => "default",
+        }
+    }
+
+    /// Apply the filter to a value
+    fn apply(&self, value: Node) -> Result<Node> {
+        if let Filter::Default(default) = self {
+            return Ok(match value {
+                Node::Null(..) => default.clone(),
+                Node::String(string) if string.is_empty() => default.clone(),
+                _ => value,
+            });
+        }
+
+        let Node::String(string) = value else {
+            bail!(
+                "Filter `{}` can only be applied to a string but the value is a {}",
+                self.name(),
+                value.node_type()
+            )
+        };
+
+        Ok(Node::String(match self {
+            Filter::Lower => string.to_lowercase(),
+            Filter::Upper => string.to_uppercase(),
+            Filter::Trim => string.trim().to_string(),
+            Filter::Replace(from, to) => string.replace(from, to),
+            Filter::Default(..) => string,
+        }))
+    }
+}
+
+/// Split code on a separator that is not within quotes or brackets
+///
+/// A separator that is doubled (e.g. `||`) is not split on.
+fn split_top_level(code: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    let chars: Vec<(usize, char)> = code.char_indices().collect();
+    for (index, &(position, char)) in chars.iter().enumerate() {
+        if let Some(open) = quote {
+            if escaped {
+                escaped = false;
+            } else if char == '\\' {
+                escaped = true;
+            } else if char == open {
+                quote = None;
+            }
+            continue;
+        }
+
+        match char {
+            '\'' | '"' => quote = Some(char),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ if char == separator && depth == 0 => {
+                let is_doubled = |index: Option<usize>| {
+                    index
+                        .and_then(|index| chars.get(index))
+                        .is_some_and(|&(.., char)| char == separator)
+                };
+                if !is_doubled(index.checked_sub(1)) && !is_doubled(Some(index + 1)) {
+                    parts.push(&code[start..position]);
+                    start = position + char.len_utf8();
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.push(&code[start..]);
+
+    parts
+}
+
+/// Parse the arguments of a filter
+fn parse_args(name: &str, args: &str) -> Result<Vec<Node>> {
+    if args.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    split_top_level(args, ',')
+        .into_iter()
+        .map(|arg| {
+            let arg = arg.trim();
+            parse_literal(arg).ok_or_else(|| {
+                eyre::eyre!(
+                    "Invalid argument `{arg}` for filter `{name}`: arguments must be \
+                     string, number, or boolean literals"
+                )
+            })
+        })
+        .collect()
+}
+
+/// Parse a string, number, or boolean literal
+fn parse_literal(literal: &str) -> Option<Node> {
+    let mut chars = literal.chars();
+    if let (Some(first @ ('\'' | '"')), Some(last)) = (chars.next(), chars.next_back())
+        && first == last
+    {
+        let mut string = String::new();
+        let mut escaped = false;
+        for char in chars {
+            if escaped {
+                string.push(match char {
+                    'n' => '\n',
+                    't' => '\t',
+                    _ => char,
+                });
+                escaped = false;
+            } else if char == '\\' {
+                escaped = true;
+            } else {
+                string.push(char);
+            }
+        }
+        return Some(Node::String(string));
+    }
+
+    match literal {
+        "true" | "True" | "TRUE" => Some(Node::Boolean(true)),
+        "false" | "False" | "FALSE" => Some(Node::Boolean(false)),
+        _ => literal
+            .parse::<i64>()
+            .map(Node::Integer)
+            .or_else(|_| literal.parse::<f64>().map(Node::Number))
+            .ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(code: &str) -> Pipeline {
+        Pipeline::parse(code)
+            .expect("valid pipeline")
+            .expect("has filters")
+    }
+
+    fn string(value: &str) -> Node {
+        Node::String(value.into())
+    }
+
+    #[test]
+    fn parsing() -> Result<()> {
+        assert_eq!(
+            pipeline("title | lower | replace(' ', '-')"),
+            Pipeline {
+                expression: "title".into(),
+                filters: vec![Filter::Lower, Filter::Replace(" ".into(), "-".into())]
+            }
+        );
+        assert_eq!(
+            pipeline("f(a, '|') | default(\"a | b\")"),
+            Pipeline {
+                expression: "f(a, '|')".into(),
+                filters: vec![Filter::Default(string("a | b"))]
+            }
+        );
+        assert_eq!(
+            pipeline("count|default(0)").filters,
+            vec![Filter::Default(Node::Integer(0))]
+        );
+
+        // Not pipelines
+        assert_eq!(Pipeline::parse("title")?, None);
+        assert_eq!(Pipeline::parse("a || b")?, None);
+        assert_eq!(Pipeline::parse("a | b + 1")?, None);
+        assert_eq!(Pipeline::parse("[x for x in y if x | 1]")?, None);
+
+        // Errors
+        let error = Pipeline::parse("title | slugify").expect_err("unknown filter");
+        assert!(error.to_string().contains("Unknown filter `slugify`"));
+        assert!(error.to_string().contains("`replace`"));
+
+        let error = Pipeline::parse("title | replace('a')").expect_err("missing argument");
+        assert!(error.to_string().contains("expects 2 arguments but got 1"));
+
+        let error = Pipeline::parse("title | replace(a, 'b')").expect_err("not a literal");
+        assert!(error.to_string().contains("Invalid argument `a`"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn applying() -> Result<()> {
+        assert_eq!(
+            pipeline("x | trim | lower | replace(' ', '-')").apply(string("  Hello World "))?,
+            string("hello-world")
+        );
+        assert_eq!(pipeline("x | upper").apply(string("abc"))?, string("ABC"));
+
+        let default = pipeline("x | default('none') | upper");
+        assert_eq!(default.apply(Node::Null(Null))?, string("NONE"));
+        assert_eq!(default.apply(string(""))?, string("NONE"));
+        assert_eq!(default.apply(string("some"))?, string("SOME"));
+        assert_eq!(default.fallback(), Some(&string("none")));
+        assert_eq!(pipeline("x | lower | default('')").fallback(), None);
+
+        let error = pipeline("x | lower")
+            .apply(Node::Integer(1))
+            .expect_err("not a string");
+        assert!(
+            error
+                .to_string()
+                .contains("Filter `lower` can only be applied to a string")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Recording of the sources that the content of includes came from
+//!
+//! Each block in the content of an include is given an `Importer` author role whose author
+//! is a `Thing` with the resolved identifier of the source (a file path or URL) as its `url`.
+//! Because this is recorded on the blocks themselves, rather than derived from the
+//! `IncludeBlock` that they are within, it is retained when includes are flattened and when
+//! content is serialized. Being authorship, it is not encoded when an include is re-encoded
+//! back to an include form (which only has a `source`), and it can be removed using the
+//! `authors` strip scope. Blocks that have no `authors` property (e.g. `ThematicBreak`)
+//! are not tagged, although any blocks nested within them are.
+
+use stencila_schema::{
+    Author, AuthorRole, AuthorRoleAuthor, AuthorRoleName, Block, PatchNode, Thing, ThingOptions,
+    authorship,
+};
+
+/// Record the source that the content of an include came from on each of its blocks
+///
+/// Any existing authorship of the blocks (e.g. from a source in a Stencila format) is
+/// replaced, as when assigning authorship to other generated content.
+pub(crate) fn record_source(blocks: &mut Vec<Block>, identifier: &str) {
+    let role = AuthorRole {
+        author: AuthorRoleAuthor::Thing(Thing {
+            options: Box::new(ThingOptions {
+                url: Some(identifier.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        role_name: AuthorRoleName::Importer,
+        ..Default::default()
+    };
+
+    if let Err(error) = authorship(blocks, vec![role]) {
+        tracing::error!("While recording source of included content: {error}");
+    }
+}
+
+/// Get the source that a block was included from, if any
+///
+/// Returns the identifier recorded by [`record_source`] when the block was included.
+pub(crate) fn included_from(block: &Block) -> Option<String> {
+    block
+        .authors()?
+        .into_iter()
+        .find_map(|author| match author {
+            Author::AuthorRole(AuthorRole {
+                author: AuthorRoleAuthor::Thing(thing),
+                role_name: AuthorRoleName::Importer,
+                ..
+            }) => thing.options.url,
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::{
+        StripScope, StripTargets,
+        shortcuts::{p, t, tb},
+        strip,
+    };
+
+    use super::*;
+
+    #[test]
+    fn recorded_sources() {
+        let mut blocks = vec![p([t("One")]), tb()];
+        assert_eq!(included_from(&blocks[0]), None);
+
+        record_source(&mut blocks, "/docs/part.md");
+        assert_eq!(included_from(&blocks[0]).as_deref(), Some("/docs/part.md"));
+        assert_eq!(included_from(&blocks[1]), None);
+
+        strip(&mut blocks, StripTargets::scope(StripScope::Authors));
+        assert_eq!(included_from(&blocks[0]), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Limiting of the rate of requests for remote include sources
+//!
+//! Documents that include many fragments from the same host (e.g. an API with a rate
+//! limit) can set a maximum rate of requests per second for that host, or a default for
+//! all hosts (see [`crate::Executor::set_include_rate_limit`]). Requests to a host are
+//! spaced so that they do not exceed its rate, with each request reserving the next
+//! available time slot, so that requests queued at the same time are spread out.
+//!
+//! As for the limit on concurrent fetches, the times of requests to each host are
+//! shared by all executors, so that documents being compiled concurrently do not,
+//! together, exceed the rate for a host.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use eyre::Result;
+use reqwest::Url;
+use tokio::time::{Instant, sleep_until};
+
+use crate::include_error::IncludeError;
+
+/// The maximum rates of requests for remote include sources
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RateLimits {
+    /// The rate, in requests per second, for hosts without their own rate
+    default: Option<f64>,
+
+    /// The rate, in requests per second, for specific hosts
+    hosts: HashMap<String, f64>,
+}
+
+impl RateLimits {
+    /// Set the default rate, or remove it if `None`
+    pub fn set_default(&mut self, rate: Option<f64>) {
+        self.default = rate;
+    }
+
+    /// Set the rate for a host, or remove it if `None`
+    pub fn set_host(&mut self, host: String, rate: Option<f64>) {
+        let host = host.to_lowercase();
+        match rate {
+            Some(rate) => self.hosts.insert(host, rate),
+            None => self.hosts.remove(&host),
+        };
+    }
+
+    /// Get the host of a URL, and the minimum interval between requests to it, if any
+    ///
+    /// Rates that are not positive and finite disable rate limiting.
+    fn interval(&self, url: &str) -> Option<(String, Duration)> {
+        let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+        let rate = self.hosts.get(&host).copied().or(self.default)?;
+        (rate.is_finite() && rate > 0.0).then(|| (host, Duration::from_secs_f64(1.0 / rate)))
+    }
+}
+
+/// The time at which the next request can be made to each host
+static NEXT_REQUESTS: LazyLock<Mutex<HashMap<String, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Wait until a request can be made to the host of a URL
+///
+/// If there is a `timeout` and the wait would be longer than it then errors
+/// immediately, without reserving a time slot.
+pub(crate) async fn throttle(
+    url: &str,
+    limits: &RateLimits,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let Some((host, interval)) = limits.interval(url) else {
+        return Ok(());
+    };
+
+    let now = Instant::now();
+    let at = {
+        let Ok(mut next) = NEXT_REQUESTS.lock() else {
+            return Ok(());
+        };
+
+        let at = next
+            .get(&host)
+            .copied()
+            .filter(|at| *at > now)
+            .unwrap_or(now);
+
+        if let Some(timeout) = timeout
+            && at - now > timeout
+        {
+            return Err(IncludeError::Network(format!(
+                "Timed out after {timeout:?} waiting to fetch {url} because of the rate \
+                 limit for remote includes from `{host}`"
+            ))
+            .into());
+        }
+
+        next.insert(host, at + interval);
+        at
+    };
+
+    if at > now {
+        tracing::debug!(
+            "Waiting {:?} to fetch {url} because of rate limit",
+            at - now
+        );
+        sleep_until(at).await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals() {
+        let mut limits = RateLimits::default();
+        assert_eq!(limits.interval("https://example.org/a.md"), None);
+
+        limits.set_default(Some(2.0));
+        limits.set_host("API.example.org".into(), Some(0.5));
+        assert_eq!(
+            limits.interval("https://example.org/a.md"),
+            Some(("example.org".into(), Duration::from_millis(500)))
+        );
+        assert_eq!(
+            limits.interval("https://api.example.org/a.md"),
+            Some(("api.example.org".into(), Duration::from_secs(2)))
+        );
+
+        limits.set_host("api.example.org".into(), Some(0.0));
+        assert_eq!(limits.interval("https://api.example.org/a.md"), None);
+
+        limits.set_host("api.example.org".into(), None);
+        limits.set_default(None);
+        assert_eq!(limits.interval("https://api.example.org/a.md"), None);
+    }
+
+    #[tokio::test]
+    async fn spaces_requests() -> Result<()> {
+        let mut limits = RateLimits::default();
+        limits.set_host("rate.test".into(), Some(20.0));
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            throttle("https://rate.test/a.md", &limits, None).await?;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // Other hosts are not limited
+        let start = Instant::now();
+        throttle("https://other.test/a.md", &limits, None).await?;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // Waits longer than the timeout error
+        limits.set_host("slow.test".into(), Some(0.1));
+        throttle("https://slow.test/a.md", &limits, None).await?;
+        let timeout = Some(Duration::from_secs(1));
+        assert!(
+            throttle("https://slow.test/a.md", &limits, timeout)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+//! Rebasing of relative paths in the content of includes
+//!
+//! Images and links in an included source are usually relative to the source (e.g.
+//! `![](images/plot.png)` in `parts/intro.md`), but once inlined into the including
+//! document they would be resolved relative to that document instead. When enabled, the
+//! relative paths in included content are rewritten to be relative to the home directory
+//! of the executor (e.g. `parts/images/plot.png`), or absolute if the source is outside of
+//! it. For remote sources, relative paths are rewritten as absolute URLs.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use regex::Regex;
+use reqwest::Url;
+
+use stencila_schema::{
+    AudioObject, Block, ImageObject, Inline, Link, VideoObject, VisitorMut, WalkControl,
+};
+
+use crate::include_fetch::is_remote;
+
+/// A regex for paths that have a URL scheme (e.g. `https:`, `data:`, `mailto:`)
+static SCHEME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").expect("invalid regex"));
+
+/// The base that relative paths in the content of an include are rebased onto
+#[derive(Debug, Clone, PartialEq)]
+enum Base {
+    /// The URL of a remote source
+    Url(Url),
+
+    /// The directory of a local source, relative to home if within it
+    Dir(PathBuf),
+}
+
+/// Rebase the relative paths of images, media, and links in the content of an include
+///
+/// The `identifier` is the resolved identifier of the source of the content and `home`
+/// is the directory that paths in the including document are relative to.
+pub(crate) fn rebase_paths(blocks: &mut [Block], identifier: &str, home: &Path) {
+    let Some(base) = base(identifier, home) else {
+        return;
+    };
+
+    let mut rebaser = Rebaser { base };
+    for block in blocks {
+        rebaser.walk(block);
+    }
+}
+
+/// Get the base for the content of a source
+///
+/// Returns `None` if the paths in the content do not need to be rebased because the
+/// source is in the home directory.
+fn base(identifier: &str, home: &Path) -> Option<Base> {
+    if is_remote(identifier) {
+        return Url::parse(identifier).ok().map(Base::Url);
+    }
+
+    let dir = Path::new(identifier).parent()?;
+    let dir = dir.strip_prefix(home).unwrap_or(dir);
+    (!dir.as_os_str().is_empty()).then(|| Base::Dir(dir.to_path_buf()))
+}
+
+/// Rebase a path onto a base
+///
+/// Returns `None` if the path is not relative (e.g. it is empty, absolute, a URL, or
+/// only a fragment) and so should be left unchanged.
+fn rebase(path: &str, base: &Base) -> Option<String> {
+    if path.is_empty() || path.starts_with(['#', '/', '?']) || SCHEME.is_match(path) {
+        return None;
+    }
+
+    match base {
+        Base::Url(url) => url.join(path).ok().map(|url| url.to_string()),
+        Base::Dir(dir) => Some(
+            normalize(&dir.join(path))
+                .to_string_lossy()
+                .replace('\\', "/"),
+        ),
+    }
+}
+
+/// Lexically normalize a path by removing `.` components and resolving `..` components
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(..))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// A visitor that rebases the relative paths of images, media, and links
+struct Rebaser {
+    base: Base,
+}
+
+impl Rebaser {
+    /// Rebase a path in place if it is relative
+    fn rebase(&self, path: &mut String) {
+        if let Some(rebased) = rebase(path, &self.base) {
+            *path = rebased;
+        }
+    }
+}
+
+impl VisitorMut for Rebaser {
+    fn visit_block(&mut self, block: &mut Block) -> WalkControl {
+        match block {
+            Block::ImageObject(ImageObject { content_url, .. })
+            | Block::AudioObject(AudioObject { content_url, .. })
+            | Block::VideoObject(VideoObject { content_url, .. }) => self.rebase(content_url),
+            _ => {}
+        }
+
+        WalkControl::Continue
+    }
+
+    fn visit_inline(&mut self, inline: &mut Inline) -> WalkControl {
+        match inline {
+            Inline::ImageObject(ImageObject { content_url, .. })
+            | Inline::AudioObject(AudioObject { content_url, .. })
+            | Inline::VideoObject(VideoObject { content_url, .. }) => self.rebase(content_url),
+            Inline::Link(Link { target, .. }) => self.rebase(target),
+            _ => {}
+        }
+
+        WalkControl::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::shortcuts::{img, lnk, p, t};
+
+    use super::*;
+
+    fn paths(blocks: &[Block]) -> Vec<String> {
+        let Some(Block::Paragraph(para)) = blocks.first() else {
+            return Vec::new();
+        };
+        para.content
+            .iter()
+            .filter_map(|inline| match inline {
+                Inline::ImageObject(image) => Some(image.content_url.clone()),
+                Inline::Link(link) => Some(link.target.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn content() -> Vec<Block> {
+        vec![p([
+            img("images/plot.png"),
+            img("../shared/logo.svg"),
+            img("https://example.org/photo.jpg"),
+            img("data:image/png;base64,AAAA"),
+            img("/abs/figure.png"),
+            lnk([t("other")], "other.md#intro"),
+            lnk([t("section")], "#intro"),
+        ])]
+    }
+
+    #[test]
+    fn local_sources() {
+        let mut blocks = content();
+        rebase_paths(&mut blocks, "/home/parts/intro.md", Path::new("/home"));
+        assert_eq!(
+            paths(&blocks),
+            [
+                "parts/images/plot.png",
+                "shared/logo.svg",
+                "https://example.org/photo.jpg",
+                "data:image/png;base64,AAAA",
+                "/abs/figure.png",
+                "parts/other.md#intro",
+                "#intro"
+            ]
+        );
+
+        // Sources outside of home are rebased to absolute paths
+        let mut blocks = content();
+        rebase_paths(&mut blocks, "/elsewhere/intro.md", Path::new("/home"));
+        assert_eq!(paths(&blocks)[0], "/elsewhere/images/plot.png");
+
+        // Sources in home are unchanged
+        let mut blocks = content();
+        rebase_paths(&mut blocks, "/home/intro.md", Path::new("/home"));
+        assert_eq!(paths(&blocks), paths(&content()));
+    }
+
+    #[test]
+    fn remote_sources() {
+        let mut blocks = content();
+        rebase_paths(
+            &mut blocks,
+            "https://example.org/docs/intro.md",
+            Path::new("/home"),
+        );
+        assert_eq!(
+            paths(&blocks)[..2],
+            [
+                "https://example.org/docs/images/plot.png",
+                "https://example.org/shared/logo.svg"
+            ]
+        );
+        assert_eq!(paths(&blocks)[5], "https://example.org/docs/other.md#intro");
+    }
+}
@@ -0,0 +1,111 @@
+//! A summary of the result of compiling an include
+//!
+//! Compiling an `IncludeBlock` communicates its result by mutating the include (e.g. setting its
+//! `content` and `compilationMessages`) and sending patches. Embedders driving compilation
+//! directly (e.g. tools, and tests of include behavior) can use
+//! [`crate::Executor::compile_include`] to get a summary of the result instead.
+
+use serde::Serialize;
+
+use stencila_schema::{CompilationMessage, IncludeBlock};
+
+use crate::{Executable, Executor, Phase, include_block::resolve_source};
+
+/// The result of compiling an `IncludeBlock`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeCompileResult {
+    /// The resolved identifier of the source (a URL or file path)
+    ///
+    /// For a fallback chain of sources, the chain of resolved alternatives.
+    pub resolved_identifier: String,
+
+    /// The number of top-level blocks in the content of the include
+    pub block_count: usize,
+
+    /// Whether the content was from the include cache
+    ///
+    /// Also true if the source was not refetched because none of the inputs of
+    /// the include had changed since it was last compiled.
+    pub from_cache: bool,
+
+    /// The compilation messages of the include
+    pub messages: Vec<CompilationMessage>,
+}
+
+/// Compile an include and summarize the result
+pub(crate) async fn compile_include(
+    include: &mut IncludeBlock,
+    executor: &mut Executor,
+) -> IncludeCompileResult {
+    let (resolved_identifier, ..) =
+        resolve_source(&include.source, &mut executor.fork_for_compile());
+
+    // Ensure that any nested includes are also compiled
+    let phase = std::mem::replace(&mut executor.phase, Phase::Compile);
+    executor.include_from_cache = false;
+    include.compile(executor).await;
+    executor.phase = phase;
+
+    IncludeCompileResult {
+        resolved_identifier,
+        block_count: include.content.as_ref().map_or(0, Vec::len),
+        from_cache: executor.include_from_cache,
+        messages: include
+            .options
+            .compilation_messages
+            .clone()
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use eyre::Result;
+    use tempfile::tempdir;
+    use tokio::sync::RwLock;
+
+    use stencila_kernels::Kernels;
+    use stencila_schema::{ExecutionBounds, MessageLevel};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn results() -> Result<()> {
+        let dir = tempdir()?;
+        tokio::fs::write(dir.path().join("part.md"), "One\n\nTwo\n").await?;
+
+        let kernels = Arc::new(RwLock::new(Kernels::new_here(ExecutionBounds::Main)));
+        let mut executor = Executor::new(dir.path().to_path_buf(), kernels, None);
+
+        let mut include = IncludeBlock::new("part.md".into());
+        let result = executor.compile_include(&mut include).await;
+        assert_eq!(
+            PathBuf::from(&result.resolved_identifier),
+            dir.path().join("part.md")
+        );
+        assert_eq!(result.block_count, 2);
+        assert!(!result.from_cache);
+        assert!(result.messages.is_empty());
+
+        // Unchanged so not refetched
+        let result = executor.compile_include(&mut include).await;
+        assert!(result.from_cache);
+
+        // A different include of the same source uses the cached content
+        let mut include = IncludeBlock::new("part.md".into());
+        let result = executor.compile_include(&mut include).await;
+        assert_eq!(result.block_count, 2);
+        assert!(result.from_cache);
+
+        let mut include = IncludeBlock::new("missing.md".into());
+        let result = executor.compile_include(&mut include).await;
+        assert_eq!(result.block_count, 0);
+        assert!(!result.from_cache);
+        assert_eq!(result.messages[0].level, MessageLevel::Error);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1619 @@
+//! Selection of a subset of the content of an `IncludeBlock`
+//!
+//! The `select` property of an `IncludeBlock` is a CSS-like selector. Currently supported are:
+//!
+//! - type selectors e.g. `Figure`
+//! - id selectors e.g. `#cell-train` (e.g. to select a code cell from a Jupyter Notebook by its id)
+//! - attribute selectors matching the whole (`=`), start (`^=`), end (`$=`), or any part (`*=`)
+//!   of a string-valued property e.g. `[label^=fig-]`, `[language=python]` (`language` being
+//!   shorthand for `programmingLanguage`)
+//! - execution status selectors e.g. `CodeChunk[status=failed]` (`status` being shorthand for
+//!   `executionStatus`) which match statuses ignoring case, with `failed` matching both
+//!   `errors` and `exceptions`, and `ok` matching both `succeeded` and `warnings`. These
+//!   only match in sources that have been executed.
+//!
+//! Type, id, and attribute selectors can be combined into a compound selector
+//! e.g. `Figure[label^=fig-]` or `CodeChunk#cell-train`. A compound selector can have
+//! several attribute selectors, all of which must match the same node,
+//! e.g. `CodeChunk[language=python][status=ok]`.
+//! Attribute values may be optionally quoted with single or double quotes.
+//!
+//! Compound selectors can be joined using combinators:
+//!
+//! - child combinator (`>`) e.g. `Section > CodeChunk` selects code chunks whose
+//!   parent block is a section
+//! - descendant combinator (whitespace) e.g. `Section CodeChunk` selects code chunks
+//!   that are anywhere within a section
+//!
+//! A selector can end with a position to select only the nth matching node, in document
+//! order, e.g. `Table:nth(2)` or the shorthands `Table:2` and (as in XPath) `Table[2]` select
+//! the second table. Positions start at 1 and apply to the selector as a whole
+//! e.g. `Section > Table:2` selects the second of the tables that are children of sections.
+//!
+//! Only blocks and inlines are considered when matching combinators, so for example, in
+//! `List > Paragraph` the intermediate `ListItem` is skipped and paragraphs within the list's
+//! items are selected.
+//!
+//! Selected inlines (e.g. `Paragraph > Link`) are wrapped in a paragraph, with inlines from
+//! the same block wrapped in the same paragraph, so that the selected content is always blocks.
+//!
+//! The content under a block with a particular id (e.g. the fragment of a URL) can
+//! also be selected, with the blocks following a heading being selected along with it.
+//!
+//! A range of blocks can be selected using the ids of the first and last blocks in the
+//! range e.g. `#intro .. #methods`. Both blocks must have the same parent.
+//!
+//! Alternatively, `select` can have the prefix `after:` to select all the top-level blocks
+//! after a heading e.g. `after:## Notes`. The level of the heading is optional e.g. `after:Notes`
+//! selects the blocks after the first heading, of any level, with the text "Notes".
+//!
+//! The sections under one or more headings can be selected using a `;`-separated list of
+//! headings e.g. `## Methods; ## Results`. The section under a heading is the heading and
+//! the top-level blocks following it, up to the next heading of the same or higher level.
+//! Sections are concatenated in the order the headings are listed.
+//!
+//! As in many Markdown tools, the section under a heading can also be selected using
+//! the GitHub-style anchor slug of the heading e.g. `#installation` for `## Installation`.
+//! Slugs are only used if no block has the id. If more than one heading has the slug,
+//! the first is selected.
+//!
+//! For sources without stable ids or headings, `select` can have the prefix `text:` to select
+//! the first top-level block whose text contains a substring, ignoring case,
+//! e.g. `text:installation`.
+
+use std::str::FromStr;
+
+use eyre::{Result, bail};
+use itertools::Itertools;
+
+use stencila_codec_text_trait::to_text;
+use stencila_schema::{
+    Block, Inline, Node, NodeId, NodePath, NodeProperty, NodeSet, NodeType, Paragraph, ProbeNode,
+    Visitor, WalkControl,
+};
+
+/// A selector for a subset of the content of an include
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Selector {
+    /// The compound selectors, from outermost to innermost
+    compounds: Vec<Compound>,
+
+    /// The combinators between each consecutive pair of compound selectors
+    combinators: Vec<Combinator>,
+
+    /// The position (1-based) of the matching node to select, if any
+    position: Option<usize>,
+}
+
+/// A combinator between two compound selectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    /// `>`: the second is a direct child of the first
+    Child,
+
+    /// whitespace: the second is a descendant of the first
+    Descendant,
+}
+
+/// A compound selector which matches a single block or inline
+#[derive(Debug, Clone, PartialEq)]
+struct Compound {
+    /// The type of node to match, if any
+    node_type: Option<NodeType>,
+
+    /// The id of the node to match, if any
+    id: Option<String>,
+
+    /// Attribute matchers that must all match
+    attributes: Vec<AttributeMatcher>,
+}
+
+/// A matcher for the value of a property of a node
+#[derive(Debug, Clone, PartialEq)]
+struct AttributeMatcher {
+    /// The property to match
+    property: NodeProperty,
+
+    /// The operator used to match the value
+    operator: AttributeOperator,
+
+    /// The value to match against
+    value: String,
+}
+
+/// An operator for matching the value of a property
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttributeOperator {
+    /// `=`: the value equals
+    Equals,
+
+    /// `^=`: the value starts with
+    Prefix,
+
+    /// `$=`: the value ends with
+    Suffix,
+
+    /// `*=`: the value contains
+    Contains,
+}
+
+impl FromStr for Selector {
+    type Err = eyre::Report;
+
+    fn from_str(selector: &str) -> Result<Self> {
+        let selector = selector.trim();
+        if selector.is_empty() {
+            bail!("Selector is empty")
+        }
+
+        let (compounds_part, position) = split_position(selector)?;
+
+        let mut compounds = Vec::new();
+        let mut combinators = Vec::new();
+        let mut combinator = None;
+        let mut current = String::new();
+        let mut in_brackets = false;
+        let mut quote = None;
+        for c in compounds_part.chars() {
+            if in_brackets {
+                if let Some(q) = quote {
+                    if c == q {
+                        quote = None;
+                    }
+                } else if c == '"' || c == '\'' {
+                    quote = Some(c);
+                } else if c == ']' {
+                    in_brackets = false;
+                }
+                current.push(c);
+                continue;
+            }
+
+            if c == '>' || c.is_whitespace() {
+                if !current.is_empty() {
+                    compounds.push(Compound::parse(&current, selector)?);
+                    current.clear();
+                }
+                if c == '>' {
+                    if compounds.is_empty() || combinator == Some(Combinator::Child) {
+                        bail!("Unexpected `>` in selector `{selector}`")
+                    }
+                    combinator = Some(Combinator::Child);
+                } else if !compounds.is_empty() && combinator.is_none() {
+                    combinator = Some(Combinator::Descendant);
+                }
+                continue;
+            }
+
+            if current.is_empty() && !compounds.is_empty() {
+                combinators.push(combinator.take().unwrap_or(Combinator::Descendant));
+            }
+            if c == '[' {
+                in_brackets = true;
+            }
+            current.push(c);
+        }
+
+        if in_brackets {
+            bail!("Unclosed `[` in selector `{selector}`")
+        }
+        if current.is_empty() {
+            bail!("Expected selector after `>` in selector `{selector}`")
+        }
+        compounds.push(Compound::parse(&current, selector)?);
+
+        Ok(Self {
+            compounds,
+            combinators,
+            position,
+        })
+    }
+}
+
+/// Split any position from the end of a selector e.g. `Table:2`, `Table:nth(2)` or `Table[2]`
+///
+/// All forms are parsed identically. Returns the rest of the selector and the position.
+fn split_position(selector: &str) -> Result<(&str, Option<usize>)> {
+    let is_digits = |string: &str| !string.is_empty() && string.chars().all(|c| c.is_ascii_digit());
+
+    // A bracketed number is a position, rather than an attribute
+    let bracketed = selector
+        .strip_suffix(']')
+        .and_then(|selector| selector.rsplit_once('['))
+        .filter(|(.., number)| is_digits(number.trim()));
+
+    let (rest, number) = if let Some((rest, number)) = bracketed {
+        (rest, number.trim())
+    } else {
+        let Some((rest, suffix)) = selector.rsplit_once(':') else {
+            return Ok((selector, None));
+        };
+
+        match suffix.strip_prefix("nth(") {
+            Some(inner) => match inner.strip_suffix(')') {
+                Some(number) => (rest, number.trim()),
+                None => bail!("Unclosed `(` in selector `{selector}`"),
+            },
+            None if is_digits(suffix) => (rest, suffix),
+            None => return Ok((selector, None)),
+        }
+    };
+
+    match number.parse::<usize>() {
+        Ok(position) if position > 0 => {
+            let rest = rest.trim_end();
+            if rest.is_empty() {
+                bail!("Expected selector before position in selector `{selector}`")
+            }
+            Ok((rest, Some(position)))
+        }
+        _ => bail!("Position `{number}` in selector `{selector}` is not a positive integer"),
+    }
+}
+
+impl Compound {
+    /// Parse a compound selector e.g. `Figure[label^=fig-]` or `CodeChunk#cell-train`
+    fn parse(compound: &str, selector: &str) -> Result<Self> {
+        // Parse the type, if any, up to the id or first attribute
+        let (type_name, rest) = match compound.find(['#', '[']) {
+            Some(pos) => compound.split_at(pos),
+            None => (compound, ""),
+        };
+        let node_type = match type_name {
+            "" | "*" => None,
+            name => match NodeType::from_str(name) {
+                Ok(node_type) => Some(node_type),
+                Err(..) => bail!("Unknown node type `{name}` in selector `{selector}`"),
+            },
+        };
+
+        // Parse the id, if any, up to the first attribute
+        let (id, mut rest) = match rest.strip_prefix('#') {
+            Some(rest) => {
+                let (id, rest) = match rest.find('[') {
+                    Some(pos) => rest.split_at(pos),
+                    None => (rest, ""),
+                };
+                if id.is_empty() {
+                    bail!("Expected id after `#` in selector `{selector}`")
+                }
+                (Some(id.to_string()), rest)
+            }
+            None => (None, rest),
+        };
+
+        // Parse each of the attributes
+        let mut attributes = Vec::new();
+        while !rest.is_empty() {
+            let Some(inner) = rest.strip_prefix('[') else {
+                bail!("Expected `[` at `{rest}` in selector `{selector}`")
+            };
+            let Some(end) = closing_bracket(inner) else {
+                bail!("Unclosed `[` in selector `{selector}`")
+            };
+            attributes.push(AttributeMatcher::parse(&inner[..end], selector)?);
+            rest = &inner[end + 1..];
+        }
+
+        Ok(Self {
+            node_type,
+            id,
+            attributes,
+        })
+    }
+
+    /// Whether a block or inline matches the compound selector
+    fn matches<T: Selectable>(&self, node: &T) -> bool {
+        if let Some(node_type) = self.node_type
+            && node.node_type() != node_type
+        {
+            return false;
+        }
+
+        if let Some(id) = &self.id
+            && string_property(node, NodeProperty::Id).as_ref() != Some(id)
+        {
+            return false;
+        }
+
+        self.attributes
+            .iter()
+            .all(|attribute| attribute.matches(node))
+    }
+}
+
+/// Find the `]` closing an attribute selector, ignoring any within a quoted value
+fn closing_bracket(inner: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, char) in inner.char_indices() {
+        match (char, quote) {
+            ('"' | '\'', None) => quote = Some(char),
+            (char, Some(open)) if char == open => quote = None,
+            (']', None) => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl AttributeMatcher {
+    /// Parse an attribute matcher from the content between square brackets
+    fn parse(attribute: &str, selector: &str) -> Result<Self> {
+        let Some(equals) = attribute.find('=') else {
+            bail!("Unsupported attribute matcher `[{attribute}]` in selector `{selector}`")
+        };
+        let (pos, operator) = match attribute[..equals].chars().last() {
+            Some('^') => (equals - 1, AttributeOperator::Prefix),
+            Some('$') => (equals - 1, AttributeOperator::Suffix),
+            Some('*') => (equals - 1, AttributeOperator::Contains),
+            _ => (equals, AttributeOperator::Equals),
+        };
+
+        let name = attribute[..pos].trim();
+        let property = match name {
+            "language" => NodeProperty::ProgrammingLanguage,
+            "status" => NodeProperty::ExecutionStatus,
+            _ => match NodeProperty::from_str(name) {
+                Ok(property) => property,
+                Err(..) => bail!("Unknown property `{name}` in selector `{selector}`"),
+            },
+        };
+
+        let value = attribute[equals + 1..].trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| {
+                value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\''))
+            })
+            .unwrap_or(value)
+            .to_string();
+
+        Ok(Self {
+            property,
+            operator,
+            value,
+        })
+    }
+
+    /// Whether a block or inline matches
+    ///
+    /// Nodes that do not have the property, or for which the property
+    /// is not a string, do not match.
+    fn matches<T: Selectable>(&self, node: &T) -> bool {
+        let Some(string) = string_property(node, self.property) else {
+            return false;
+        };
+
+        if self.property == NodeProperty::ExecutionStatus {
+            return self.matches_status(&string.to_lowercase());
+        }
+
+        match self.operator {
+            AttributeOperator::Equals => string == self.value,
+            AttributeOperator::Prefix => string.starts_with(&self.value),
+            AttributeOperator::Suffix => string.ends_with(&self.value),
+            AttributeOperator::Contains => string.contains(&self.value),
+        }
+    }
+
+    /// Whether an execution status (in lowercase) matches, ignoring case
+    ///
+    /// The value `failed` matches the statuses of nodes whose execution failed,
+    /// and `ok` those whose execution did not.
+    fn matches_status(&self, status: &str) -> bool {
+        let value = self.value.to_lowercase();
+        match self.operator {
+            AttributeOperator::Equals if value == "failed" => {
+                matches!(status, "errors" | "exceptions")
+            }
+            AttributeOperator::Equals if value == "ok" => {
+                matches!(status, "succeeded" | "warnings")
+            }
+            AttributeOperator::Equals => status == value,
+            AttributeOperator::Prefix => status.starts_with(&value),
+            AttributeOperator::Suffix => status.ends_with(&value),
+            AttributeOperator::Contains => status.contains(&value),
+        }
+    }
+}
+
+impl Selector {
+    /// Whether the selector includes an id selector
+    pub fn has_id(&self) -> bool {
+        self.compounds.iter().any(|compound| compound.id.is_some())
+    }
+
+    /// Whether the selector includes an execution status selector
+    pub fn has_status(&self) -> bool {
+        self.compounds.iter().any(|compound| {
+            compound
+                .attributes
+                .iter()
+                .any(|attribute| attribute.property == NodeProperty::ExecutionStatus)
+        })
+    }
+
+    /// The position of the matching node to select, if any
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    /// Whether the compound selector at `index`, having matched a block, is
+    /// satisfied by the blocks that are ancestors of that block
+    ///
+    /// Each ancestor is represented by whether it matched each compound selector
+    /// (ordered from outermost to innermost).
+    fn matches_ancestors(&self, index: usize, ancestors: &[&Vec<bool>]) -> bool {
+        if index == 0 {
+            return true;
+        }
+
+        let previous = index - 1;
+        match self.combinators[previous] {
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, ancestors)) => {
+                    parent[previous] && self.matches_ancestors(previous, ancestors)
+                }
+                None => false,
+            },
+            Combinator::Descendant => (0..ancestors.len()).rev().any(|position| {
+                ancestors[position][previous]
+                    && self.matches_ancestors(previous, &ancestors[..position])
+            }),
+        }
+    }
+
+    /// Select the blocks matching the selector
+    ///
+    /// Blocks are selected in document order. Nodes nested within a
+    /// matching node are not separately selected.
+    #[cfg(test)]
+    pub fn select(&self, blocks: &[Block]) -> Vec<Block> {
+        self.select_wrapped(blocks).0
+    }
+
+    /// Select the nodes matching the selector, wrapping any inlines in paragraphs
+    ///
+    /// Returns the selected blocks and the number of inlines that were wrapped.
+    pub fn select_wrapped(&self, blocks: &[Block]) -> (Vec<Block>, usize) {
+        let mut walker = Walker {
+            selector: self,
+            selected: Vec::new(),
+            blocks_visited: 0,
+            pending: None,
+            frames: Vec::new(),
+        };
+        for block in blocks {
+            walker.walk(block);
+        }
+
+        // Only retain the node at the position, if any
+        let nodes = match self.position {
+            Some(position) => walker
+                .selected
+                .into_iter()
+                .skip(position - 1)
+                .take(1)
+                .collect(),
+            None => walker.selected,
+        };
+
+        // Wrap inlines in paragraphs, with inlines from the same block in the same paragraph
+        let mut wrapped = 0;
+        let mut selected = Vec::new();
+        let mut wrapper: Option<(usize, Vec<Inline>)> = None;
+        for node in nodes {
+            match node {
+                Selected::Block(block) => {
+                    if let Some((.., inlines)) = wrapper.take() {
+                        selected.push(Block::Paragraph(Paragraph::new(inlines)));
+                    }
+                    selected.push(block);
+                }
+                Selected::Inline(inline, block) => {
+                    wrapped += 1;
+                    match &mut wrapper {
+                        Some((current, inlines)) if *current == block => inlines.push(inline),
+                        _ => {
+                            if let Some((.., inlines)) = wrapper.replace((block, vec![inline])) {
+                                selected.push(Block::Paragraph(Paragraph::new(inlines)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some((.., inlines)) = wrapper {
+            selected.push(Block::Paragraph(Paragraph::new(inlines)));
+        }
+
+        (selected, wrapped)
+    }
+}
+
+/// A selection of the blocks from one id through to another e.g. `#intro .. #methods`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IdRange {
+    /// The id of the first block in the range
+    start: String,
+
+    /// The id of the last block in the range
+    end: String,
+}
+
+impl IdRange {
+    /// Parse a `select` as a range of ids
+    ///
+    /// Returns `None` if the `select` is not a range, so that it can be parsed as a selector.
+    pub fn parse(select: &str) -> Option<Self> {
+        let (start, end) = select.split_once("..")?;
+
+        let id = |part: &str| {
+            let id = part.trim().strip_prefix('#')?;
+            (!id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':')))
+            .then(|| id.to_string())
+        };
+
+        Some(Self {
+            start: id(start)?,
+            end: id(end)?,
+        })
+    }
+
+    /// Select the blocks from the block with the start id through to the block with the end id
+    ///
+    /// Both blocks must have the same parent (e.g. both be top-level blocks, or both be within
+    /// the same section) and the end block must not precede the start block.
+    pub fn select(&self, blocks: &[Block]) -> Result<Vec<Block>> {
+        let Some((siblings, start)) = find_siblings(blocks, &self.start) else {
+            bail!(
+                "No block with id `{}` at the start of the range",
+                self.start
+            )
+        };
+        let Some((end_siblings, end)) = find_siblings(blocks, &self.end) else {
+            bail!("No block with id `{}` at the end of the range", self.end)
+        };
+
+        if !std::ptr::eq(siblings, end_siblings) {
+            bail!(
+                "Blocks `{}` and `{}` at the ends of the range do not have the same parent",
+                self.start,
+                self.end
+            )
+        }
+
+        if end < start {
+            bail!(
+                "Block `{}` at the end of the range precedes block `{}` at the start (swap them)",
+                self.end,
+                self.start
+            )
+        }
+
+        Ok(siblings[start..=end].to_vec())
+    }
+}
+
+/// The prefix of a `select` for selecting the content after a heading
+pub(crate) const AFTER_PREFIX: &str = "after:";
+
+/// A selection of the blocks after a heading e.g. `## Notes`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AfterHeading {
+    /// The level of the heading, if specified
+    level: Option<i64>,
+
+    /// The text of the heading
+    text: String,
+}
+
+impl FromStr for AfterHeading {
+    type Err = eyre::Report;
+
+    fn from_str(heading: &str) -> Result<Self> {
+        let heading = heading.trim();
+        let text = heading.trim_start_matches('#');
+        let level = heading.len() - text.len();
+
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("Expected heading text in selector `{AFTER_PREFIX}{heading}`")
+        }
+
+        Ok(Self {
+            level: (level > 0).then_some(level as i64),
+            text: text.to_string(),
+        })
+    }
+}
+
+impl AfterHeading {
+    /// Select the top-level blocks after the first matching heading
+    ///
+    /// Returns `None` if there is no matching heading.
+    pub fn select(&self, blocks: &[Block]) -> Option<Vec<Block>> {
+        let position = blocks.iter().position(|block| match block {
+            Block::Heading(heading) => {
+                self.level.is_none_or(|level| heading.level == level)
+                    && to_text(&heading.content).trim() == self.text
+            }
+            _ => false,
+        })?;
+
+        Some(blocks[position + 1..].to_vec())
+    }
+}
+
+/// A selection of the sections under headings e.g. `## Methods; ## Results`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HeadingSections {
+    /// The headings, each with a level and text
+    headings: Vec<(i64, String)>,
+}
+
+impl HeadingSections {
+    /// Parse a `select` as a `;`-separated list of headings
+    ///
+    /// Each heading must have one to six `#`s followed by whitespace (so that id
+    /// selectors e.g. `#methods` are not treated as headings). Returns `None` if
+    /// the `select` is not a list of headings, so that it can be parsed as a selector.
+    pub fn parse(select: &str) -> Option<Self> {
+        let headings = select
+            .split(';')
+            .map(str::trim)
+            .filter(|heading| !heading.is_empty())
+            .map(|heading| {
+                let text = heading.trim_start_matches('#');
+                let level = heading.len() - text.len();
+                let is_heading = (1..=6).contains(&level)
+                    && text.starts_with(char::is_whitespace)
+                    && !text.trim().is_empty();
+                is_heading.then(|| (level as i64, text.trim().to_string()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        (!headings.is_empty()).then_some(Self { headings })
+    }
+
+    /// Select the sections under each of the headings, in the order that they are listed
+    ///
+    /// Returns the selected blocks and the headings that were not found (as written
+    /// in the `select` e.g. `## Results`).
+    pub fn select(&self, blocks: &[Block]) -> (Vec<Block>, Vec<String>) {
+        let mut selected = Vec::new();
+        let mut missing = Vec::new();
+        for (level, text) in &self.headings {
+            let start = blocks.iter().position(|block| match block {
+                Block::Heading(heading) => {
+                    heading.level == *level && to_text(&heading.content).trim() == text
+                }
+                _ => false,
+            });
+
+            let Some(start) = start else {
+                missing.push(format!("{} {text}", "#".repeat(*level as usize)));
+                continue;
+            };
+
+            let end = blocks[start + 1..]
+                .iter()
+                .position(
+                    |block| matches!(block, Block::Heading(heading) if heading.level <= *level),
+                )
+                .map_or(blocks.len(), |index| start + 1 + index);
+
+            selected.extend_from_slice(&blocks[start..end]);
+        }
+
+        (selected, missing)
+    }
+}
+
+/// A selection of the section under the heading with an anchor slug e.g. `#getting-started`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HeadingSlug {
+    /// The slug, without the leading `#`
+    slug: String,
+}
+
+impl HeadingSlug {
+    /// Parse a `select` as a `#` followed by a slug
+    ///
+    /// Returns `None` if the `select` is not a slug (e.g. it is a compound selector).
+    pub fn parse(select: &str) -> Option<Self> {
+        let slug = select.trim().strip_prefix('#')?;
+        (!slug.is_empty()
+            && slug
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_')))
+        .then(|| Self {
+            slug: slug.to_string(),
+        })
+    }
+
+    /// Select the section under the first heading with the slug
+    ///
+    /// Returns the selected blocks and the number of headings with the slug, or `None` if
+    /// no heading has the slug. Recurses into sections and styled blocks.
+    pub fn select(&self, blocks: &[Block]) -> Option<(Vec<Block>, usize)> {
+        let mut found = Vec::new();
+        self.find(blocks, &mut found);
+
+        let (siblings, index) = found.first()?;
+        Some((heading_section(siblings, *index), found.len()))
+    }
+
+    /// Find the headings with the slug, collecting their siblings and their index within them
+    fn find<'b>(&self, blocks: &'b [Block], found: &mut Vec<(&'b [Block], usize)>) {
+        for (index, block) in blocks.iter().enumerate() {
+            match block {
+                Block::Heading(heading)
+                    if heading_slug(&to_text(&heading.content)) == self.slug =>
+                {
+                    found.push((blocks, index))
+                }
+                Block::Section(section) => self.find(&section.content, found),
+                Block::StyledBlock(styled) => self.find(&styled.content, found),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Get the GitHub-style anchor slug for the text of a heading
+///
+/// The text is lowercased, punctuation (other than `-` and `_`) is removed, and
+/// each whitespace character is replaced with a `-` e.g. `Getting Started!` becomes
+/// `getting-started`.
+pub(crate) fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_whitespace() => Some('-'),
+            c if c.is_alphanumeric() || matches!(c, '-' | '_') => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The prefix of a `select` for selecting a block by its text
+pub(crate) const TEXT_PREFIX: &str = "text:";
+
+/// A selection of the first block whose text contains a substring e.g. `installation`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TextMatch {
+    /// The substring, in lowercase
+    text: String,
+}
+
+impl FromStr for TextMatch {
+    type Err = eyre::Report;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("Expected text in selector `{TEXT_PREFIX}`")
+        }
+
+        Ok(Self {
+            text: text.to_lowercase(),
+        })
+    }
+}
+
+impl TextMatch {
+    /// Select the first top-level block whose text contains the substring, ignoring case
+    ///
+    /// Returns `None` if no block contains the substring.
+    pub fn select(&self, blocks: &[Block]) -> Option<Vec<Block>> {
+        blocks
+            .iter()
+            .find(|block| to_text(*block).to_lowercase().contains(&self.text))
+            .map(|block| vec![block.clone()])
+    }
+}
+
+/// A node that can be matched by a selector
+trait Selectable: ProbeNode {
+    /// The type of the node
+    fn node_type(&self) -> NodeType;
+}
+
+impl Selectable for Block {
+    fn node_type(&self) -> NodeType {
+        Block::node_type(self)
+    }
+}
+
+impl Selectable for Inline {
+    fn node_type(&self) -> NodeType {
+        Inline::node_type(self)
+    }
+}
+
+/// Get the value of a string property of a node, if any
+fn string_property<T: ProbeNode>(node: &T, property: NodeProperty) -> Option<String> {
+    match node.duplicate(&mut NodePath::from(property)) {
+        Ok(NodeSet::One(Node::String(value))) => Some(value),
+        _ => None,
+    }
+}
+
+/// Get the `id` of a block, if any
+fn block_id(block: &Block) -> Option<String> {
+    string_property(block, NodeProperty::Id)
+}
+
+/// Get the ids of all blocks, in document order
+///
+/// Used to provide a helpful message listing the available ids
+/// when an id selector does not match.
+pub(crate) fn block_ids(blocks: &[Block]) -> Vec<String> {
+    let mut collector = IdCollector::default();
+    for block in blocks {
+        collector.walk(block);
+    }
+    collector.ids
+}
+
+/// Select the content under the block with an id e.g. from the fragment of a URL
+///
+/// If the block is a heading then it and the blocks following it, up to the next heading
+/// of the same or higher level, are selected (i.e. the content under the heading).
+/// Otherwise, the block is selected. Returns `None` if no block has the id.
+pub(crate) fn select_fragment(blocks: &[Block], id: &str) -> Option<Vec<Block>> {
+    if let Some(selected) = select_fragment_siblings(blocks, id) {
+        return Some(selected);
+    }
+
+    // Fallback to blocks nested within other types of blocks (e.g. within lists)
+    let selector = Selector {
+        compounds: vec![Compound {
+            node_type: None,
+            id: Some(id.to_string()),
+            attributes: Vec::new(),
+        }],
+        combinators: Vec::new(),
+        position: None,
+    };
+    let (selected, ..) = selector.select_wrapped(blocks);
+    selected.into_iter().next().map(|block| vec![block])
+}
+
+/// Select the content under the block with an id within a list of sibling blocks
+///
+/// Recurses into sections and styled blocks (in which headings are commonly nested).
+fn select_fragment_siblings(blocks: &[Block], id: &str) -> Option<Vec<Block>> {
+    let (siblings, index) = find_siblings(blocks, id)?;
+    Some(heading_section(siblings, index))
+}
+
+/// Get the content under the block at an index within a list of sibling blocks
+///
+/// If the block is a heading then it and the blocks following it, up to the next heading
+/// of the same or higher level, are returned. Otherwise, only the block is returned.
+fn heading_section(siblings: &[Block], index: usize) -> Vec<Block> {
+    let Block::Heading(heading) = &siblings[index] else {
+        return vec![siblings[index].clone()];
+    };
+
+    let end = siblings[index + 1..]
+        .iter()
+        .position(|next| matches!(next, Block::Heading(next) if next.level <= heading.level))
+        .map_or(siblings.len(), |position| index + 1 + position);
+    siblings[index..end].to_vec()
+}
+
+/// Find the block with an id, returning its siblings and its index within them
+///
+/// Recurses into sections and styled blocks (in which headings are commonly nested).
+fn find_siblings<'b>(blocks: &'b [Block], id: &str) -> Option<(&'b [Block], usize)> {
+    for (index, block) in blocks.iter().enumerate() {
+        if block_id(block).as_deref() == Some(id) {
+            return Some((blocks, index));
+        }
+
+        let content = match block {
+            Block::Section(section) => &section.content,
+            Block::StyledBlock(styled) => &styled.content,
+            _ => continue,
+        };
+        if let Some(found) = find_siblings(content, id) {
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+/// Whether any of the blocks, or the blocks within them, has an execution status
+///
+/// Used to warn when a selector for execution status is used with a source
+/// that has not been executed.
+pub(crate) fn has_execution_status(blocks: &[Block]) -> bool {
+    let mut finder = StatusFinder { found: false };
+    for block in blocks {
+        finder.walk(block);
+        if finder.found {
+            return true;
+        }
+    }
+    false
+}
+
+/// A visitor that finds blocks with an execution status
+struct StatusFinder {
+    found: bool,
+}
+
+impl Visitor for StatusFinder {
+    fn visit_block(&mut self, block: &Block) -> WalkControl {
+        self.found |= string_property(block, NodeProperty::ExecutionStatus).is_some();
+        match self.found {
+            true => WalkControl::Break,
+            false => WalkControl::Continue,
+        }
+    }
+}
+
+/// A visitor that collects the ids of blocks
+#[derive(Default)]
+struct IdCollector {
+    ids: Vec<String>,
+}
+
+impl Visitor for IdCollector {
+    fn visit_block(&mut self, block: &Block) -> WalkControl {
+        if let Some(id) = block_id(block) {
+            self.ids.push(id);
+        }
+        WalkControl::Continue
+    }
+}
+
+/// A node selected by a selector
+enum Selected {
+    Block(Block),
+
+    /// An inline, and the number of blocks visited before it, used to
+    /// group inlines from the same block into the same paragraph
+    Inline(Inline, usize),
+}
+
+/// A visitor that collects the nodes matching a selector
+struct Walker<'s> {
+    /// The selector being matched
+    selector: &'s Selector,
+
+    /// The nodes selected so far
+    selected: Vec<Selected>,
+
+    /// The number of blocks visited so far
+    blocks_visited: usize,
+
+    /// The id of the node last visited, and whether it matched each
+    /// compound selector, to be pushed onto `frames` when it is entered
+    pending: Option<(NodeId, Vec<bool>)>,
+
+    /// A frame for each struct currently being walked over, with the
+    /// compound selector matches if the struct is a block or inline
+    frames: Vec<Option<Vec<bool>>>,
+}
+
+impl Walker<'_> {
+    /// Match a node against the selector
+    ///
+    /// Returns `true` if the node is selected. Otherwise, records whether the node
+    /// matched each compound selector, for when its children are walked over.
+    fn visit<T: Selectable>(&mut self, node: &T, node_id: Option<NodeId>) -> bool {
+        let matches = self
+            .selector
+            .compounds
+            .iter()
+            .map(|compound| compound.matches(node))
+            .collect_vec();
+
+        let last = matches.len() - 1;
+        if matches[last] {
+            let ancestors = self.frames.iter().flatten().collect_vec();
+            if self.selector.matches_ancestors(last, &ancestors) {
+                return true;
+            }
+        }
+
+        self.pending = node_id.map(|node_id| (node_id, matches));
+
+        false
+    }
+}
+
+impl Visitor for Walker<'_> {
+    fn visit_block(&mut self, block: &Block) -> WalkControl {
+        self.blocks_visited += 1;
+
+        if self.visit(block, block.node_id()) {
+            self.selected.push(Selected::Block(block.clone()));
+            return WalkControl::Break;
+        }
+
+        WalkControl::Continue
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) -> WalkControl {
+        if self.visit(inline, inline.node_id()) {
+            self.selected
+                .push(Selected::Inline(inline.clone(), self.blocks_visited));
+            return WalkControl::Break;
+        }
+
+        WalkControl::Continue
+    }
+
+    fn enter_struct(&mut self, _node_type: NodeType, node_id: NodeId) -> WalkControl {
+        let frame = self
+            .pending
+            .take()
+            .and_then(|(pending_id, matches)| (pending_id == node_id).then_some(matches));
+        self.frames.push(frame);
+
+        WalkControl::Continue
+    }
+
+    fn exit_struct(&mut self) {
+        self.frames.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::{
+        CodeChunk, ExecutionStatus, Figure, Heading,
+        shortcuts::{cc, em, fig, h1, h2, lnk, p, sec, t},
+    };
+
+    use super::*;
+
+    fn figure(label: &str) -> Block {
+        Block::Figure(Figure {
+            label: Some(label.into()),
+            content: vec![p([t(label)])],
+            ..Default::default()
+        })
+    }
+
+    fn labels(blocks: &[Block]) -> Vec<String> {
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Figure(figure) => figure.label.clone(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn attribute_operators() -> Result<()> {
+        let blocks = [
+            figure("fig-one"),
+            p([t("para")]),
+            figure("tab-one"),
+            cc("code", None::<String>),
+            figure("fig-two-wide"),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(labels(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(select("[label^=fig-]")?, ["fig-one", "fig-two-wide"]);
+        assert_eq!(select("Figure[label$='-one']")?, ["fig-one", "tab-one"]);
+        assert_eq!(select("[label*=\"two\"]")?, ["fig-two-wide"]);
+        assert_eq!(select("Figure[label=fig-one]")?, ["fig-one"]);
+        assert_eq!(select("[label = 'tab-one']")?, ["tab-one"]);
+        assert_eq!(select("[label=fig]")?, Vec::<String>::new());
+
+        // Non-string fields do not match
+        assert_eq!(select("[content^=fig]")?, Vec::<String>::new());
+
+        Ok(())
+    }
+
+    fn codes(blocks: &[Block]) -> Vec<String> {
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::CodeChunk(chunk) => Some(chunk.code.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn combinators() -> Result<()> {
+        let blocks = [
+            cc("top", None::<String>),
+            sec([
+                cc("child", None::<String>),
+                fig([cc("in-figure", None::<String>)]),
+                sec([cc("nested-child", None::<String>)]),
+            ]),
+            fig([sec([cc("in-figure-section", None::<String>)])]),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(codes(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(
+            select("Section > CodeChunk")?,
+            ["child", "nested-child", "in-figure-section"]
+        );
+        assert_eq!(
+            select("Section CodeChunk")?,
+            ["child", "in-figure", "nested-child", "in-figure-section"]
+        );
+        assert_eq!(select("Section>Figure>CodeChunk")?, ["in-figure"]);
+        assert_eq!(select("Section Section > CodeChunk")?, ["nested-child"]);
+        assert_eq!(select("Figure Section CodeChunk")?, ["in-figure-section"]);
+        assert_eq!(select("Figure > CodeChunk")?, ["in-figure"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn languages() -> Result<()> {
+        let blocks = [
+            cc("py", Some("python")),
+            cc("r", Some("r")),
+            cc("none", None::<String>),
+            sec([cc("nested-py", Some("python"))]),
+            cc("py3", Some("python3")),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(codes(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(select("[language=python]")?, ["py", "nested-py"]);
+        assert_eq!(select("CodeChunk[programmingLanguage=r]")?, ["r"]);
+        assert_eq!(select("Section > [language=python]")?, ["nested-py"]);
+        assert_eq!(select("[language=javascript]")?, Vec::<String>::new());
+
+        // Fields that nodes do not have do not match
+        assert_eq!(select("[label=py]")?, Vec::<String>::new());
+
+        Ok(())
+    }
+
+    fn chunk(id: &str, code: &str) -> Block {
+        Block::CodeChunk(CodeChunk {
+            id: Some(id.into()),
+            code: code.into(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn ids() -> Result<()> {
+        let blocks = [
+            chunk("cell-load", "load"),
+            sec([chunk("cell-train", "train")]),
+            chunk("cell-plot", "plot"),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(codes(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(select("#cell-train")?, ["train"]);
+        assert_eq!(select("CodeChunk#cell-plot")?, ["plot"]);
+        assert_eq!(select("Section > #cell-train")?, ["train"]);
+        assert_eq!(select("Figure#cell-train")?, Vec::<String>::new());
+        assert_eq!(select("#cell-load[code^=lo]")?, ["load"]);
+        assert_eq!(select("#cell-none")?, Vec::<String>::new());
+
+        assert_eq!(block_ids(&blocks), ["cell-load", "cell-train", "cell-plot"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn statuses() -> Result<()> {
+        let chunk = |code: &str, status: Option<ExecutionStatus>| {
+            let mut chunk = CodeChunk::new(code.into());
+            chunk.options.execution_status = status;
+            Block::CodeChunk(chunk)
+        };
+        let blocks = [
+            chunk("ok", Some(ExecutionStatus::Succeeded)),
+            chunk("error", Some(ExecutionStatus::Errors)),
+            sec([chunk("exception", Some(ExecutionStatus::Exceptions))]),
+            chunk("warning", Some(ExecutionStatus::Warnings)),
+            chunk("unexecuted", None),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(codes(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(select("CodeChunk[status=failed]")?, ["error", "exception"]);
+        assert_eq!(select("[status=Succeeded]")?, ["ok"]);
+        assert_eq!(select("[executionStatus=warnings]")?, ["warning"]);
+        assert_eq!(select("[status$=s]")?, ["error", "exception", "warning"]);
+        assert_eq!(select("[status=ok]")?, ["ok", "warning"]);
+
+        assert!(Selector::from_str("[status=failed]")?.has_status());
+        assert!(!Selector::from_str("[label=failed]")?.has_status());
+
+        assert!(has_execution_status(&blocks));
+        assert!(!has_execution_status(&[chunk("unexecuted", None)]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn multiple_attributes() -> Result<()> {
+        let chunk = |code: &str, language: &str, status: ExecutionStatus| {
+            let mut chunk = CodeChunk::new(code.into());
+            chunk.programming_language = Some(language.into());
+            chunk.options.execution_status = Some(status);
+            Block::CodeChunk(chunk)
+        };
+        let blocks = [
+            chunk("py-ok", "python", ExecutionStatus::Succeeded),
+            chunk("py-error", "python", ExecutionStatus::Errors),
+            chunk("r-ok", "r", ExecutionStatus::Succeeded),
+            chunk("bracketed", "a]b", ExecutionStatus::Succeeded),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(codes(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(select("CodeChunk[language=python][status=ok]")?, ["py-ok"]);
+        assert_eq!(select("[status=failed][language=python]")?, ["py-error"]);
+        assert_eq!(select("[language=r][status=failed]")?, Vec::<String>::new());
+        assert_eq!(select("[language='a]b'][status=ok]")?, ["bracketed"]);
+
+        assert!(Selector::from_str("[language=python][status=ok").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn after_heading() -> Result<()> {
+        let blocks = [
+            h1([t("Title")]),
+            p([t("intro")]),
+            h2([t("Notes")]),
+            p([t("first")]),
+            p([t("second")]),
+        ];
+
+        let after = |heading: &str| -> Result<Option<Vec<Block>>> {
+            Ok(AfterHeading::from_str(heading)?.select(&blocks))
+        };
+
+        assert_eq!(
+            after("## Notes")?,
+            Some(vec![p([t("first")]), p([t("second")])])
+        );
+        assert_eq!(after("Notes")?, after("## Notes")?);
+        assert_eq!(after("# Notes")?, None);
+        assert_eq!(after("Missing")?, None);
+        assert_eq!(after("#Title")?.map(|blocks| blocks.len()), Some(4));
+        assert!(AfterHeading::from_str("##").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn heading_sections() {
+        let blocks = [
+            h1([t("Title")]),
+            h2([t("A")]),
+            p([t("a")]),
+            h2([t("B")]),
+            p([t("b")]),
+            h2([t("C")]),
+            p([t("c")]),
+            h1([t("Appendix")]),
+        ];
+
+        let sections = HeadingSections::parse("## A; ## C").expect("headings");
+        assert_eq!(
+            sections.select(&blocks),
+            (
+                vec![h2([t("A")]), p([t("a")]), h2([t("C")]), p([t("c")])],
+                vec![]
+            )
+        );
+
+        // In the order listed, with missing headings reported
+        let sections = HeadingSections::parse("## C;## Missing; ## B;").expect("headings");
+        assert_eq!(
+            sections.select(&blocks),
+            (
+                vec![h2([t("C")]), p([t("c")]), h2([t("B")]), p([t("b")])],
+                vec!["## Missing".to_string()]
+            )
+        );
+
+        // A section extends to the next heading of the same or higher level
+        let (selected, ..) = HeadingSections::parse("# Title")
+            .expect("heading")
+            .select(&blocks);
+        assert_eq!(selected.len(), 7);
+
+        // Not lists of headings
+        assert_eq!(HeadingSections::parse("#intro"), None);
+        assert_eq!(HeadingSections::parse("## A; Figure"), None);
+        assert_eq!(HeadingSections::parse("####### Deep"), None);
+        assert_eq!(HeadingSections::parse(";"), None);
+    }
+
+    #[test]
+    fn heading_slugs() {
+        assert_eq!(heading_slug("Installation"), "installation");
+        assert_eq!(heading_slug(" Getting Started! "), "getting-started");
+        assert_eq!(heading_slug("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(
+            heading_slug("snake_case & kebab-case"),
+            "snake_case--kebab-case"
+        );
+
+        let blocks = [
+            h1([t("Guide")]),
+            sec([
+                h2([t("Getting Started")]),
+                p([t("first")]),
+                h2([t("Usage")]),
+            ]),
+            h2([t("Getting started")]),
+            p([t("second")]),
+        ];
+
+        let select = |select: &str| HeadingSlug::parse(select).expect("slug").select(&blocks);
+
+        // The first of duplicate slugs is selected, with the number of matches
+        assert_eq!(
+            select("#getting-started"),
+            Some((vec![h2([t("Getting Started")]), p([t("first")])], 2))
+        );
+        assert_eq!(select("#usage"), Some((vec![h2([t("Usage")])], 1)));
+        assert_eq!(select("#guide").map(|(blocks, ..)| blocks.len()), Some(4));
+        assert_eq!(select("#missing"), None);
+
+        // Not slugs
+        assert_eq!(HeadingSlug::parse("#"), None);
+        assert_eq!(HeadingSlug::parse("Heading#intro"), None);
+        assert_eq!(HeadingSlug::parse("#a > #b"), None);
+    }
+
+    #[test]
+    fn text_matches() -> Result<()> {
+        let blocks = [
+            h1([t("Guide")]),
+            p([t("Some intro.")]),
+            p([t("See the "), em([t("Installation")]), t(" notes.")]),
+            p([t("Installation is easy.")]),
+        ];
+
+        let select = |text: &str| -> Result<Option<Vec<Block>>> {
+            Ok(TextMatch::from_str(text)?.select(&blocks))
+        };
+
+        assert_eq!(
+            select("installation")?,
+            Some(vec![p([
+                t("See the "),
+                em([t("Installation")]),
+                t(" notes.")
+            ])])
+        );
+        assert_eq!(
+            select(" IS EASY ")?,
+            Some(vec![p([t("Installation is easy.")])])
+        );
+        assert_eq!(select("missing")?, None);
+        assert!(TextMatch::from_str(" ").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fragments() {
+        let heading = |level, id: &str, text: &str| {
+            Block::Heading(Heading {
+                id: Some(id.into()),
+                ..Heading::new(level, vec![t(text)])
+            })
+        };
+        let para = |id: &str, text: &str| {
+            Block::Paragraph(Paragraph {
+                id: Some(id.into()),
+                ..Paragraph::new(vec![t(text)])
+            })
+        };
+
+        let blocks = [
+            heading(1, "title", "Title"),
+            sec([
+                heading(2, "install", "Install"),
+                p([t("first")]),
+                heading(3, "linux", "Linux"),
+                p([t("second")]),
+                heading(2, "usage", "Usage"),
+                para("last", "third"),
+            ]),
+        ];
+
+        let texts = |id: &str| {
+            select_fragment(&blocks, id).map(|blocks| {
+                blocks
+                    .iter()
+                    .map(|block| to_text(block).trim().to_string())
+                    .collect_vec()
+            })
+        };
+
+        assert_eq!(
+            texts("install"),
+            Some(vec![
+                "Install".to_string(),
+                "first".to_string(),
+                "Linux".to_string(),
+                "second".to_string()
+            ])
+        );
+        assert_eq!(
+            texts("linux"),
+            Some(vec!["Linux".to_string(), "second".to_string()])
+        );
+        assert_eq!(texts("last"), Some(vec!["third".to_string()]));
+        assert_eq!(texts("missing"), None);
+        assert_eq!(texts("title").map(|texts| texts.len()), Some(2));
+    }
+
+    #[test]
+    fn id_ranges() -> Result<()> {
+        let para = |id: &str| {
+            Block::Paragraph(Paragraph {
+                id: Some(id.into()),
+                ..Paragraph::new(vec![t(id)])
+            })
+        };
+
+        let blocks = [
+            para("one"),
+            para("two"),
+            para("three"),
+            sec([para("four"), para("five")]),
+        ];
+
+        let range = |select: &str| -> Result<Vec<String>> {
+            let Some(range) = IdRange::parse(select) else {
+                bail!("Not a range")
+            };
+            Ok(range
+                .select(&blocks)?
+                .iter()
+                .map(|block| to_text(block).trim().to_string())
+                .collect())
+        };
+
+        assert_eq!(range("#one .. #two")?, ["one", "two"]);
+        assert_eq!(range("#two..#two")?, ["two"]);
+        assert_eq!(range("#one .. #three")?.len(), 3);
+        assert_eq!(range("#four .. #five")?, ["four", "five"]);
+
+        assert!(
+            range("#three .. #one")
+                .unwrap_err()
+                .to_string()
+                .contains("precedes")
+        );
+        assert!(
+            range("#one .. #five")
+                .unwrap_err()
+                .to_string()
+                .contains("same parent")
+        );
+        assert!(range("#one .. #six").is_err());
+
+        assert!(IdRange::parse("Figure").is_none());
+        assert!(IdRange::parse("#one .. Figure").is_none());
+        assert!(IdRange::parse("[label*=..]").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn inlines() -> Result<()> {
+        let blocks = [
+            p([
+                t("one "),
+                lnk([t("a")], "https://a.org"),
+                t(" "),
+                lnk([t("b")], "https://b.org"),
+            ]),
+            fig([cc("code", None::<String>)]),
+            p([lnk([t("c")], "https://c.org")]),
+        ];
+
+        let (selected, wrapped) = Selector::from_str("Link")?.select_wrapped(&blocks);
+        assert_eq!(wrapped, 3);
+        assert_eq!(
+            selected,
+            [
+                p([
+                    lnk([t("a")], "https://a.org"),
+                    lnk([t("b")], "https://b.org")
+                ]),
+                p([lnk([t("c")], "https://c.org")])
+            ]
+        );
+
+        let (selected, wrapped) =
+            Selector::from_str("Paragraph > Link[target*=c]")?.select_wrapped(&blocks);
+        assert_eq!(wrapped, 1);
+        assert_eq!(selected, [p([lnk([t("c")], "https://c.org")])]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn positions() -> Result<()> {
+        let blocks = [
+            figure("fig-one"),
+            sec([figure("fig-two"), figure("tab-one")]),
+            figure("fig-three"),
+        ];
+
+        let select = |selector: &str| -> Result<Vec<String>> {
+            Ok(labels(&Selector::from_str(selector)?.select(&blocks)))
+        };
+
+        assert_eq!(select("Figure:2")?, ["fig-two"]);
+        assert_eq!(select("Figure:nth(2)")?, select("Figure:2")?);
+        assert_eq!(select("Figure[label^=fig]:3")?, ["fig-three"]);
+        assert_eq!(select("Figure[2]")?, select("Figure:2")?);
+        assert_eq!(select("Figure[label^=fig][ 3 ]")?, ["fig-three"]);
+        assert_eq!(select("Section > Figure:nth( 2 )")?, ["tab-one"]);
+        assert_eq!(select("Figure:5")?, Vec::<String>::new());
+        assert_eq!(select("Figure:nth(5)")?, Vec::<String>::new());
+
+        assert_eq!(Selector::from_str("Figure:2")?.position(), Some(2));
+        assert_eq!(Selector::from_str("Figure")?.position(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_selectors() {
+        assert!(Selector::from_str("").is_err());
+        assert!(Selector::from_str("Foo").is_err());
+        assert!(Selector::from_str("[label]").is_err());
+        assert!(Selector::from_str("[foo=fig]").is_err());
+        assert!(Selector::from_str("[foo^=fig]").is_err());
+        assert!(Selector::from_str("[label^=fig").is_err());
+        assert!(Selector::from_str("> Figure").is_err());
+        assert!(Selector::from_str("Section >").is_err());
+        assert!(Selector::from_str("Section > > Figure").is_err());
+        assert!(Selector::from_str("CodeChunk#").is_err());
+        assert!(Selector::from_str("Figure:0").is_err());
+        assert!(Selector::from_str("Figure:nth(two)").is_err());
+        assert!(Selector::from_str("Figure:nth(2").is_err());
+        assert!(Selector::from_str(":2").is_err());
+        assert!(Selector::from_str("[2]").is_err());
+        assert!(Selector::from_str("Figure[0]").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Sniffing of the format of include sources from their leading bytes
+//!
+//! Only used as a last resort, when the format of a source can not be
+//! determined from its `mediaType`, its extension, or the `Content-Type`
+//! header of the response it was fetched from.
+
+use stencila_codecs::Format;
+
+/// Whether a format is ambiguous i.e. there is no codec to decode it
+pub(crate) fn is_ambiguous(format: &Format) -> bool {
+    format.is_unknown() || format.is_other()
+}
+
+/// The number of leading bytes examined when sniffing markup formats
+const HEAD_LENGTH: usize = 1024;
+
+/// Sniff the format of a source from its bytes
+///
+/// Distinguishes PDF, Jupyter Notebook, JSON, HTML, JATS, and LaTeX sources.
+/// Returns `None` if the format can not be determined.
+pub(crate) fn sniff_format(bytes: &[u8]) -> Option<Format> {
+    // Ignore any UTF-8 byte order mark and leading whitespace
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let bytes = &bytes[start..];
+
+    if bytes.starts_with(b"%PDF-") {
+        return Some(Format::Pdf);
+    }
+
+    match bytes[0] {
+        b'{' => {
+            // Notebook metadata (including `nbformat`) is usually at the end so check all bytes
+            if contains(bytes, b"\"nbformat\"") && contains(bytes, b"\"cells\"") {
+                Some(Format::Ipynb)
+            } else {
+                Some(Format::Json)
+            }
+        }
+        b'[' => Some(Format::Json),
+        b'<' => {
+            let head = bytes[..bytes.len().min(HEAD_LENGTH)].to_ascii_lowercase();
+            if contains(&head, b"<!doctype html") || contains(&head, b"<html") {
+                Some(Format::Html)
+            } else if contains(&head, b"<article") {
+                Some(Format::Jats)
+            } else if bytes.starts_with(b"<?xml") {
+                None
+            } else {
+                Some(Format::Html)
+            }
+        }
+        b'\\' if bytes.starts_with(b"\\documentclass") || bytes.starts_with(b"\\begin{") => {
+            Some(Format::Latex)
+        }
+        _ => None,
+    }
+}
+
+/// Whether a slice of bytes contains another
+fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+    bytes.windows(needle.len()).any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!(sniff_format(b"  {\"a\": 1}"), Some(Format::Json));
+        assert_eq!(sniff_format(b"[1, 2]"), Some(Format::Json));
+        assert_eq!(
+            sniff_format(b"{\"cells\": [], \"metadata\": {}, \"nbformat\": 4}"),
+            Some(Format::Ipynb)
+        );
+        assert_eq!(
+            sniff_format(b"\xEF\xBB\xBF<!DOCTYPE html><html></html>"),
+            Some(Format::Html)
+        );
+        assert_eq!(sniff_format(b"<p>Para</p>"), Some(Format::Html));
+        assert_eq!(
+            sniff_format(b"<?xml version=\"1.0\"?>\n<article dtd-version=\"1.3\"></article>"),
+            Some(Format::Jats)
+        );
+        assert_eq!(sniff_format(b"<?xml version=\"1.0\"?>\n<svg></svg>"), None);
+        assert_eq!(sniff_format(b"%PDF-1.7"), Some(Format::Pdf));
+        assert_eq!(
+            sniff_format(b"\\documentclass{article}"),
+            Some(Format::Latex)
+        );
+        assert_eq!(sniff_format(b"# Heading"), None);
+        assert_eq!(sniff_format(b"   "), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Selection of tagged regions from the text of a source
+//!
+//! As in AsciiDoc, a region of a source is tagged by lines containing `tag::name[]`
+//! and `end::name[]` markers, usually within comments of the source's format
+//! e.g. `// tag::setup[]` or `<!-- end::setup[] -->`. A `select` of `tag:setup`
+//! selects the lines between these markers, before the source is decoded, so that
+//! regions do not need to align with the structure of the decoded content.
+//!
+//! Lines containing markers (including those for other, nested, tags) are not
+//! part of the selected region. A tag may be used for more than one region, in
+//! which case the regions are concatenated.
+
+use std::sync::LazyLock;
+
+use indexmap::IndexSet;
+use regex::Regex;
+
+/// The prefix of a `select` for selecting a tagged region
+pub(crate) const TAG_PREFIX: &str = "tag:";
+
+/// A regex for the start and end markers of tagged regions
+static MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(tag|end)::([\w.\-]+)\[\]").expect("invalid regex"));
+
+/// Select the lines of the region of text with a tag
+///
+/// Returns the names of the tags in the text, in the order in which they
+/// first occur, if there is no region with the tag. A region that is not
+/// ended extends to the end of the text.
+pub(crate) fn tagged_region(text: &str, tag: &str) -> Result<String, Vec<String>> {
+    let mut tags = IndexSet::new();
+    let mut found = false;
+    let mut within = false;
+    let mut region = String::new();
+    for line in text.lines() {
+        let mut is_marker = false;
+        for captures in MARKER.captures_iter(line) {
+            is_marker = true;
+
+            let name = &captures[2];
+            if name != tag {
+                tags.insert(name.to_string());
+                continue;
+            }
+
+            found = true;
+            within = &captures[1] == "tag";
+        }
+
+        if within && !is_marker {
+            region.push_str(line);
+            region.push('\n');
+        }
+    }
+
+    if found {
+        Ok(region)
+    } else {
+        Err(tags.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions() {
+        let text = r#"= Guide
+
+// tag::intro[]
+Welcome.
+// tag::note[]
+A note.
+// end::note[]
+// end::intro[]
+
+Not tagged.
+
+<!-- tag::intro[] -->
+Welcome again.
+<!-- end::intro[] -->
+// tag::rest[]
+The rest.
+"#;
+
+        assert_eq!(
+            tagged_region(text, "intro"),
+            Ok("Welcome.\nA note.\nWelcome again.\n".to_string())
+        );
+        assert_eq!(tagged_region(text, "note"), Ok("A note.\n".to_string()));
+        assert_eq!(tagged_region(text, "rest"), Ok("The rest.\n".to_string()));
+        assert_eq!(
+            tagged_region(text, "outro"),
+            Err(vec![
+                "intro".to_string(),
+                "note".to_string(),
+                "rest".to_string()
+            ])
+        );
+        assert_eq!(tagged_region("No tags", "intro"), Err(Vec::new()));
+    }
+}
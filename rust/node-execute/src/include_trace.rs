@@ -0,0 +1,59 @@
+//! Tracing of the resolution of includes for debugging
+//!
+//! When [`crate::Executor::set_include_trace`] is enabled, each step in getting the content
+//! of an include (its source, the directory it is resolved against, the resolved identifier
+//! and any redirect, the media type and selector used, and the resulting content) is recorded
+//! and added to the compilation messages of the include as a single info level message. This
+//! makes it easier to see why an include did not get the expected content.
+
+use stencila_schema::{CompilationMessage, MessageLevel};
+
+/// The steps in resolving the content of an include
+pub(crate) struct IncludeTrace {
+    /// The name and detail of each step
+    steps: Vec<(&'static str, String)>,
+}
+
+impl IncludeTrace {
+    /// Start a trace with the source of an include
+    pub fn new(source: &str) -> Self {
+        Self {
+            steps: vec![("source", format!("`{source}`"))],
+        }
+    }
+
+    /// Add a step to the trace
+    pub fn step<S: Into<String>>(&mut self, name: &'static str, detail: S) {
+        self.steps.push((name, detail.into()));
+    }
+
+    /// Create a compilation message for the trace
+    pub fn message(self) -> CompilationMessage {
+        let steps = self
+            .steps
+            .into_iter()
+            .map(|(name, detail)| format!("\n  {name}: {detail}"))
+            .collect::<String>();
+
+        CompilationMessage::new(MessageLevel::Info, format!("Include trace:{steps}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message() {
+        let mut trace = IncludeTrace::new("part.md");
+        trace.step("resolved", "`/docs/part.md`");
+        trace.step("content", "2 blocks");
+
+        let message = trace.message();
+        assert_eq!(message.level, MessageLevel::Info);
+        assert_eq!(
+            message.message,
+            "Include trace:\n  source: `part.md`\n  resolved: `/docs/part.md`\n  content: 2 blocks"
+        );
+    }
+}
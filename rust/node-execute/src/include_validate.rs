@@ -0,0 +1,69 @@
+//! Validation of the content of includes against house rules
+//!
+//! Allows those embedding the executor to enforce content policies (e.g. "no level one
+//! headings in includes" or "every figure needs a caption") at the include boundary, rather
+//! than needing a separate lint pass over the whole document. The validator is run over the
+//! content of each include, at all levels of nesting, and the messages it returns are added
+//! to the `compilationMessages` of the include.
+
+use std::sync::Arc;
+
+use stencila_schema::{Block, CompilationMessage};
+
+/// A validator for the content of includes
+///
+/// Called with the content of each include, after any `select` and filter have been
+/// applied, and returns any messages for the include. See
+/// [`crate::Executor::set_include_validator`].
+pub type IncludeValidator = Arc<dyn Fn(&[Block]) -> Vec<CompilationMessage> + Send + Sync>;
+
+/// Validate the content of an include, adding any messages to `messages`
+pub(crate) fn validate_blocks(
+    blocks: &[Block],
+    validator: &IncludeValidator,
+    messages: &mut Vec<CompilationMessage>,
+) {
+    messages.append(&mut validator(blocks));
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_schema::{
+        Heading, MessageLevel,
+        shortcuts::{h1, h2, p, t},
+    };
+
+    use super::*;
+
+    #[test]
+    fn validates() {
+        let validator: IncludeValidator = Arc::new(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| matches!(block, Block::Heading(Heading { level: 1, .. })))
+                .map(|_| {
+                    CompilationMessage::new(
+                        MessageLevel::Warning,
+                        "Includes should not have level one headings".into(),
+                    )
+                })
+                .collect()
+        });
+
+        let mut messages = Vec::new();
+        validate_blocks(
+            &[h2([t("Methods")]), p([t("para")])],
+            &validator,
+            &mut messages,
+        );
+        assert!(messages.is_empty());
+
+        validate_blocks(
+            &[h1([t("Title")]), p([t("para")])],
+            &validator,
+            &mut messages,
+        );
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].level, MessageLevel::Warning);
+    }
+}
@@ -0,0 +1,115 @@
+//! Requesting specific versions of remote include sources
+//!
+//! Some servers can serve different revisions of a source at the same URL. An include
+//! can be pinned to a particular revision using the `version` argument e.g.
+//! `--version=2`. How the version is requested can be configured for each host (see
+//! [`crate::Executor::set_include_versioning`]) and defaults to adding a `version` query
+//! parameter to the URL (e.g. `https://example.org/part.md?version=2`). Alternatively, a
+//! header can be used (e.g. `Accept-Version: 2`).
+
+use std::collections::HashMap;
+
+use reqwest::Url;
+
+/// The name of the argument used to request a specific version of a remote source
+pub(crate) const VERSION_ARGUMENT: &str = "version";
+
+/// How to request a specific version of a remote include source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeVersioning {
+    /// Add a query parameter, with this name, to the URL e.g. `?version=2`
+    Query(String),
+
+    /// Add a header, with this name, to the request e.g. `Accept-Version: 2`
+    Header(String),
+}
+
+impl Default for IncludeVersioning {
+    fn default() -> Self {
+        Self::Query("version".into())
+    }
+}
+
+impl IncludeVersioning {
+    /// Get the versioning to use for a URL from those configured for hosts
+    pub(crate) fn for_url(url: &str, hosts: &HashMap<String, IncludeVersioning>) -> Self {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                url.host_str()
+                    .and_then(|host| hosts.get(&host.to_lowercase()))
+                    .cloned()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Apply the versioning to a URL
+    ///
+    /// Returns the URL to fetch and the header, if any, to add to the request.
+    pub(crate) fn apply(&self, url: &str, version: &str) -> (String, Option<(String, String)>) {
+        match self {
+            Self::Query(name) => {
+                let Ok(mut parsed) = Url::parse(url) else {
+                    return (url.to_string(), None);
+                };
+
+                // Replace any existing parameter with the same name
+                let pairs: Vec<(String, String)> = parsed
+                    .query_pairs()
+                    .filter(|(key, ..)| key != name)
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect();
+                parsed
+                    .query_pairs_mut()
+                    .clear()
+                    .extend_pairs(pairs)
+                    .append_pair(name, version);
+
+                (parsed.to_string(), None)
+            }
+            Self::Header(name) => (url.to_string(), Some((name.clone(), version.to_string()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versioning() {
+        let url = "https://example.org/part.md?lang=en&version=1";
+
+        assert_eq!(
+            IncludeVersioning::default().apply(url, "2"),
+            (
+                "https://example.org/part.md?lang=en&version=2".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            IncludeVersioning::Query("rev".into()).apply("https://example.org/part.md", "a b"),
+            ("https://example.org/part.md?rev=a+b".to_string(), None)
+        );
+        assert_eq!(
+            IncludeVersioning::Header("Accept-Version".into()).apply(url, "2"),
+            (
+                url.to_string(),
+                Some(("Accept-Version".to_string(), "2".to_string()))
+            )
+        );
+
+        let hosts = HashMap::from([(
+            "api.example.org".to_string(),
+            IncludeVersioning::Header("Accept-Version".into()),
+        )]);
+        assert_eq!(
+            IncludeVersioning::for_url("https://API.example.org/part.md", &hosts),
+            IncludeVersioning::Header("Accept-Version".into())
+        );
+        assert_eq!(
+            IncludeVersioning::for_url("https://example.org/part.md", &hosts),
+            IncludeVersioning::default()
+        );
+    }
+}
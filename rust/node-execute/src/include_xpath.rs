@@ -0,0 +1,351 @@
+//! Selection of elements from XML sources using XPath expressions
+//!
+//! As with XInclude's `xpointer`, a `select` of an include of an XML-family source
+//! (e.g. JATS) may be an XPath expression e.g. `//fig[@id='f1']`. The elements matching
+//! the expression are extracted from the XML of the source before it is decoded, so that
+//! only those elements are converted to blocks.
+//!
+//! A subset of XPath is supported: absolute location paths made of child (`/`) and
+//! descendant (`//`) steps, with name tests (including `*` and prefixed names, which are
+//! matched on their local name) and predicates for the existence (`[@id]`) or value
+//! (`[@id='f1']`) of attributes, and for position (`[2]`).
+
+use std::{str::FromStr, sync::LazyLock};
+
+use eyre::Result;
+use itertools::Itertools;
+use regex::Regex;
+use roxmltree::{Document, Node, ParsingOptions};
+
+/// A regex for a predicate of a step
+static PREDICATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(?:@([\w.\-:]+)(?:\s*=\s*(?:'([^']*)'|"([^"]*)"))?|(\d+))$"#)
+        .expect("invalid regex")
+});
+
+/// A regex for the name test of a step
+static NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\*|[A-Za-z_][\w.\-]*(?::[A-Za-z_][\w.\-]*)?)$").expect("invalid regex")
+});
+
+/// Whether a `select` is an XPath expression
+pub(crate) fn is_xpath(select: &str) -> bool {
+    select.trim().starts_with('/')
+}
+
+/// An XPath expression
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct XPath {
+    steps: Vec<Step>,
+}
+
+/// A step of the location path of an XPath expression
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    /// Whether the step selects descendants (`//`), rather than children (`/`)
+    descendants: bool,
+
+    /// The local name of the elements selected, or `None` for any element (`*`)
+    name: Option<String>,
+
+    /// The predicates that selected elements must satisfy
+    predicates: Vec<Predicate>,
+}
+
+/// A predicate of a step
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    /// The element has an attribute, optionally with a value
+    Attribute(String, Option<String>),
+
+    /// The element is at a (one-based) position amongst those selected from its parent
+    Position(usize),
+}
+
+impl FromStr for XPath {
+    type Err = String;
+
+    fn from_str(xpath: &str) -> Result<Self, Self::Err> {
+        let mut rest = xpath.trim();
+        if !rest.starts_with('/') {
+            return Err("expression should start with `/` or `//`".into());
+        }
+
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            let descendants = rest.starts_with("//");
+            rest = rest
+                .strip_prefix(if descendants { "//" } else { "/" })
+                .ok_or("expected `/` or `//` between steps")?;
+
+            let (step, remaining) = split_step(rest)?;
+            let mut step: Step = step.parse()?;
+            step.descendants = descendants;
+            steps.push(step);
+            rest = remaining;
+        }
+
+        Ok(Self { steps })
+    }
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(step: &str) -> Result<Self, Self::Err> {
+        let (name, predicates) = match step.find('[') {
+            Some(index) => step.split_at(index),
+            None => (step, ""),
+        };
+
+        let name = name.trim();
+        if !NAME.is_match(name) {
+            return Err(if name.is_empty() {
+                "expected an element name".to_string()
+            } else {
+                format!("invalid element name `{name}`")
+            });
+        }
+        let name = (name != "*").then(|| {
+            name.rsplit_once(':')
+                .map_or(name, |(.., local)| local)
+                .to_string()
+        });
+
+        let predicates = predicates
+            .split_terminator(']')
+            .map(|predicate| {
+                let predicate = predicate
+                    .trim()
+                    .strip_prefix('[')
+                    .ok_or("expected `[` at start of predicate")?
+                    .trim();
+                let captures = PREDICATE
+                    .captures(predicate)
+                    .ok_or_else(|| format!("unsupported predicate `[{predicate}]`"))?;
+                if let Some(position) = captures.get(4) {
+                    let position = position.as_str().parse().unwrap_or_default();
+                    if position == 0 {
+                        return Err("positions start at 1".to_string());
+                    }
+                    return Ok(Predicate::Position(position));
+                }
+                let value = captures.get(2).or(captures.get(3));
+                Ok(Predicate::Attribute(
+                    captures[1].to_string(),
+                    value.map(|value| value.as_str().to_string()),
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            descendants: false,
+            name,
+            predicates,
+        })
+    }
+}
+
+/// Split the next step from the rest of a location path
+///
+/// Steps are separated by `/` but not within the brackets or quotes of predicates.
+fn split_step(path: &str) -> Result<(&str, &str), String> {
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    for (index, char) in path.char_indices() {
+        match (quote, char) {
+            (Some(open), _) if char == open => quote = None,
+            (Some(..), _) => {}
+            (None, '\'' | '"') => quote = Some(char),
+            (None, '[') => depth += 1,
+            (None, ']') => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or("unexpected `]` without matching `[`")?
+            }
+            (None, '/') if depth == 0 => return Ok(path.split_at(index)),
+            _ => {}
+        }
+    }
+
+    if quote.is_some() {
+        Err("unterminated string in predicate".into())
+    } else if depth > 0 {
+        Err("unterminated predicate, expected `]`".into())
+    } else {
+        Ok((path, ""))
+    }
+}
+
+impl XPath {
+    /// Select the elements of a document that match the expression, in document order
+    fn select<'a, 'input>(&self, doc: &'a Document<'input>) -> Vec<Node<'a, 'input>> {
+        let mut nodes = vec![doc.root()];
+        for step in &self.steps {
+            nodes = nodes
+                .iter()
+                .flat_map(|node| step.select(node))
+                .unique_by(|node| node.id())
+                .sorted_by_key(|node| node.id())
+                .collect();
+        }
+        nodes
+    }
+}
+
+impl Step {
+    /// Select the elements that match the step from a context node
+    fn select<'a, 'input>(&self, node: &Node<'a, 'input>) -> Vec<Node<'a, 'input>> {
+        let candidates: Vec<Node> = if self.descendants {
+            node.descendants().skip(1).collect()
+        } else {
+            node.children().collect()
+        };
+
+        let mut selected = candidates
+            .into_iter()
+            .filter(|node| {
+                node.is_element()
+                    && self
+                        .name
+                        .as_ref()
+                        .is_none_or(|name| node.tag_name().name() == name)
+            })
+            .collect_vec();
+
+        for predicate in &self.predicates {
+            selected = match predicate {
+                Predicate::Attribute(name, value) => selected
+                    .into_iter()
+                    .filter(|node| {
+                        let local = name
+                            .rsplit_once(':')
+                            .map_or(name.as_str(), |(.., local)| local);
+                        node.attributes()
+                            .find(|attr| attr.name() == local)
+                            .is_some_and(|attr| {
+                                value.as_ref().is_none_or(|value| attr.value() == value)
+                            })
+                    })
+                    .collect(),
+                Predicate::Position(position) => {
+                    selected.into_iter().skip(position - 1).take(1).collect()
+                }
+            }
+        }
+
+        selected
+    }
+}
+
+/// Extract the elements of a XML document that match an XPath expression
+///
+/// Returns `None` if no elements match. Otherwise, returns the XML of the matching
+/// elements wrapped in the `<article>` and `<body>` elements of a JATS document (unless the
+/// only match is the `<article>` itself), with the namespace declarations of the
+/// document's root element so that prefixed names (e.g. `xlink:href`) remain valid.
+pub(crate) fn xpath_fragment(xml: &str, xpath: &XPath) -> Result<Option<String>> {
+    let doc = Document::parse_with_options(
+        xml,
+        ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )?;
+
+    let nodes = xpath.select(&doc);
+    if nodes.is_empty() {
+        return Ok(None);
+    }
+
+    if let [node] = nodes.as_slice()
+        && node.tag_name().name() == "article"
+    {
+        return Ok(Some(xml[node.range()].to_string()));
+    }
+
+    let namespaces = doc
+        .root_element()
+        .namespaces()
+        .filter(|namespace| namespace.name() != Some("xml"))
+        .map(|namespace| {
+            let uri = namespace.uri().replace('&', "&amp;").replace('"', "&quot;");
+            match namespace.name() {
+                Some(name) => format!(" xmlns:{name}=\"{uri}\""),
+                None => format!(" xmlns=\"{uri}\""),
+            }
+        })
+        .join("");
+
+    let elements = nodes.iter().map(|node| &xml[node.range()]).join("\n");
+
+    Ok(Some(format!(
+        "<article{namespaces}><body>\n{elements}\n</body></article>"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JATS: &str = r#"<?xml version="1.0"?>
+<article xmlns:xlink="http://www.w3.org/1999/xlink">
+  <body>
+    <sec id="s1">
+      <p>Intro</p>
+      <fig id="f1"><graphic xlink:href="f1.png"/></fig>
+    </sec>
+    <sec id="s2">
+      <fig id="f2"><graphic xlink:href="f2.png"/></fig>
+      <p>Outro</p>
+    </sec>
+  </body>
+</article>"#;
+
+    fn select(xpath: &str) -> Option<String> {
+        let xpath = xpath.parse::<XPath>().expect("valid xpath");
+        xpath_fragment(JATS, &xpath).expect("valid xml")
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            "//fig[@id='f1']".parse::<XPath>(),
+            Ok(XPath {
+                steps: vec![Step {
+                    descendants: true,
+                    name: Some("fig".into()),
+                    predicates: vec![Predicate::Attribute("id".into(), Some("f1".into()))]
+                }]
+            })
+        );
+        assert!("/article/body/sec[2]/*".parse::<XPath>().is_ok());
+        assert!("//sec[@id=\"a/b\"]//p".parse::<XPath>().is_ok());
+
+        assert!("fig".parse::<XPath>().is_err());
+        assert!("//".parse::<XPath>().is_err());
+        assert!("//fig[@id='f1'".parse::<XPath>().is_err());
+        assert!("//fig[contains(., 'x')]".parse::<XPath>().is_err());
+        assert!("//fig[0]".parse::<XPath>().is_err());
+    }
+
+    #[test]
+    fn selecting() {
+        let fragment = select("//fig[@id='f1']").expect("match");
+        assert!(fragment.starts_with(r#"<article xmlns:xlink="http://www.w3.org/1999/xlink">"#));
+        assert!(fragment.contains(r#"<fig id="f1">"#));
+        assert!(!fragment.contains("f2"));
+        Document::parse(&fragment).expect("well formed");
+
+        let fragment = select("/article/body/sec[2]/*[1]").expect("match");
+        assert!(fragment.contains(r#"<fig id="f2">"#));
+        assert!(!fragment.contains("Outro"));
+
+        let fragment = select("//sec//p").expect("match");
+        assert!(fragment.contains("Intro") && fragment.contains("Outro"));
+
+        assert!(select("/article").expect("match").starts_with("<article"));
+        assert_eq!(select("//fig[@id='f3']"), None);
+        assert_eq!(select("//table"), None);
+    }
+}
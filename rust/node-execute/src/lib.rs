@@ -1,6 +1,15 @@
 #![recursion_limit = "256"]
 
-use std::{collections::HashMap, fmt::Debug, path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 use clap::Args;
 use eyre::{Result, bail, eyre};
@@ -8,17 +17,29 @@ use futures::future::join_all;
 use indexmap::IndexMap;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{RwLock, RwLockWriteGuard, mpsc, oneshot};
+use tokio::sync::{RwLock, RwLockWriteGuard, Semaphore, mpsc, oneshot};
 
 use stencila_codecs::{DecodeOptions, Format};
 use stencila_kernels::Kernels;
 use stencila_linters::LintingOptions;
 use stencila_schema::{
     AuthorRole, AuthorRoleName, Block, CitationGroup, CompilationMessage, Config, ExecutionBounds,
-    ExecutionMode, ExecutionRequired, ExecutionStatus, IfBlockClause, Inline, LabelType, Link,
-    List, ListItem, ListOrder, Node, NodeId, NodePath, NodeProperty, NodeType, Paragraph, Patch,
-    PatchNode, PatchOp, PatchValue, Reference, SuggestionBlock, Timestamp, VisitorAsync,
-    WalkControl, WalkNode,
+    ExecutionMode, ExecutionRequired, ExecutionStatus, IfBlockClause, IncludeBlock, Inline,
+    LabelType, Link, List, ListItem, ListOrder, Node, NodeId, NodePath, NodeProperty, NodeType,
+    Paragraph, Patch, PatchNode, PatchOp, PatchValue, Reference, SuggestionBlock, Timestamp,
+    VisitorAsync, WalkControl, WalkNode, diff,
+};
+
+use crate::{
+    include_arguments::content_arguments,
+    include_block::{
+        IncludeMessages, prefetch_source, report_repeated_include_messages, resolve_content,
+    },
+    include_cache::{INCLUDE_CACHE, IncludeCache},
+    include_deps::include_dependencies,
+    include_fetch::{DEFAULT_RETRIES, FETCH_PERMITS},
+    include_flatten::flatten_includes,
+    include_rate::RateLimits,
 };
 
 type NodeIds = Vec<NodeId>;
@@ -40,11 +61,44 @@ mod figure;
 mod for_block;
 mod heading;
 mod if_block;
+mod include_arguments;
+mod include_biblio;
 mod include_block;
+mod include_broken;
+mod include_cache;
+mod include_checksum;
+mod include_cycle;
+mod include_decode;
+mod include_deps;
+mod include_diagram;
+mod include_dir;
+mod include_encoding;
+mod include_error;
+mod include_export;
+mod include_fetch;
+mod include_filter;
+mod include_flatten;
+mod include_isolate;
+mod include_jsonl;
+mod include_markers;
+mod include_memory;
+mod include_pipes;
+mod include_provenance;
+mod include_rate;
+mod include_rebase;
+mod include_result;
+mod include_select;
+mod include_sniff;
+mod include_tags;
+mod include_trace;
+mod include_validate;
+mod include_version;
+mod include_xpath;
 mod instruction_block;
 mod instruction_inline;
 mod island;
 mod link;
+mod list;
 mod math_block;
 mod math_inline;
 mod model_utils;
@@ -59,6 +113,16 @@ mod supplement;
 mod table;
 mod text;
 
+pub use include_block::EmptyIncludePolicy;
+pub use include_broken::BrokenInclude;
+pub use include_cache::IncludeCachePolicy;
+pub use include_error::IncludeError;
+pub use include_filter::IncludeFilter;
+pub use include_memory::IncludeSource;
+pub use include_result::IncludeCompileResult;
+pub use include_validate::IncludeValidator;
+pub use include_version::IncludeVersioning;
+
 type PatchSender = mpsc::UnboundedSender<(Patch, Option<oneshot::Sender<()>>)>;
 
 /// Walk over a root node and compile it and child nodes
@@ -81,6 +145,134 @@ pub async fn compile(
     executor.finalize().await
 }
 
+/// Get the local file paths that the `IncludeBlock`s within a root node depend upon
+///
+/// Paths are resolved in the same way as when compiling (i.e. relative to `home` and
+/// to the directories of any parent includes) and canonicalized where possible. Intended
+/// to be used by file watchers to know which files to monitor, calling [`compile_includes_of`]
+/// when one of them changes.
+pub async fn include_paths(home: &Path, root: Arc<RwLock<Node>>) -> Vec<PathBuf> {
+    let root = root.read().await;
+    include_dependencies(home, &*root)
+        .iter()
+        .filter_map(|dependency| dependency.path())
+        .unique()
+        .collect()
+}
+
+/// Get the source that a block was included from
+///
+/// Returns the resolved identifier (a file path or URL) of the source that the block was
+/// included from, as recorded on the block when the include was compiled, or `None` if the
+/// block was not included. Allows tools to determine which file a block originated from
+/// (e.g. for attribution, or to open it for editing), including after includes are flattened.
+pub fn included_from(block: &Block) -> Option<String> {
+    include_provenance::included_from(block)
+}
+
+/// Get the `IncludeBlock`s within a compiled root node that have errors
+///
+/// Returns each include that has an error compilation message, along with its resolved source
+/// and messages, in document order. Sources are resolved in the same way as for [`include_paths`].
+/// Intended for build reports e.g. to fail a CI job, listing the broken includes, after compiling.
+pub async fn broken_includes(home: &Path, root: Arc<RwLock<Node>>) -> Vec<BrokenInclude> {
+    let root = root.read().await;
+    include_broken::broken_includes(home, &*root)
+}
+
+/// Recompile the `IncludeBlock`s within a root node whose source is a changed file
+///
+/// The cached content for the file is invalidated and the root node is compiled,
+/// but only those includes whose resolved source is `path` are refetched. Returns the
+/// ids of those includes (if empty, no compilation is done).
+#[allow(clippy::too_many_arguments)]
+pub async fn compile_includes_of(
+    home: PathBuf,
+    root: Arc<RwLock<Node>>,
+    kernels: Arc<RwLock<Kernels>>,
+    config: Config,
+    patch_sender: Option<PatchSender>,
+    decode_options: Option<DecodeOptions>,
+    compile_options: Option<CompileOptions>,
+    path: &Path,
+) -> Result<Vec<NodeId>> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    let mut root = root.read().await.clone();
+    let dependencies = include_dependencies(&home, &root)
+        .into_iter()
+        .filter(|dependency| dependency.path().as_ref() == Some(&path))
+        .collect_vec();
+    if dependencies.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut executor = Executor::new(home, kernels, patch_sender);
+    for dependency in &dependencies {
+        executor.invalidate_include(&dependency.identifier);
+    }
+
+    let node_ids = dependencies
+        .into_iter()
+        .map(|dependency| dependency.node_id)
+        .collect_vec();
+
+    executor.config = Some(config);
+    executor.decode_options = decode_options;
+    executor.compile_options = compile_options;
+    executor.node_ids = Some(node_ids.clone());
+    executor.compile(&mut root).await?;
+    executor.link(&mut root).await?;
+    executor.finalize().await?;
+
+    Ok(node_ids)
+}
+
+/// Get the content of an `IncludeBlock` from its source without compiling a document
+///
+/// The source is resolved relative to `home`. Unlike compiling, the content is not
+/// compiled and the include's `if` argument is not evaluated. On failure, returns an
+/// [`IncludeError`] indicating the kind of error so that callers can react to it.
+pub async fn resolve_include_content(
+    home: PathBuf,
+    include: &IncludeBlock,
+    kernels: Arc<RwLock<Kernels>>,
+    decode_options: Option<DecodeOptions>,
+) -> Result<Vec<Block>, IncludeError> {
+    let mut executor = Executor::new(home, kernels, None);
+    executor.decode_options = decode_options;
+    materialize_include(include, &executor).await
+}
+
+/// Fetch and decode the sources of all `IncludeBlock`s within a root node into the include cache
+///
+/// Sources are resolved relative to `home`. See [`Executor::prefetch_includes`].
+pub async fn prefetch_includes(
+    home: PathBuf,
+    root: Arc<RwLock<Node>>,
+    kernels: Arc<RwLock<Kernels>>,
+    decode_options: Option<DecodeOptions>,
+) -> Vec<(String, IncludeError)> {
+    let root = root.read().await;
+    let mut executor = Executor::new(home, kernels, None);
+    executor.decode_options = decode_options;
+    executor.prefetch_includes(&*root).await
+}
+
+/// Get the content of an `IncludeBlock` from its source using an existing executor
+///
+/// The source is resolved and decoded (applying the include's `mediaType` and `select`)
+/// using a fork of the executor, so neither the include nor the executor are mutated
+/// (although the executor's include cache is shared). As for [`resolve_include_content`],
+/// the content is not compiled. Intended for previews, linting, and tests.
+pub async fn materialize_include(
+    include: &IncludeBlock,
+    executor: &Executor,
+) -> Result<Vec<Block>, IncludeError> {
+    let mut executor = executor.fork_for_compile();
+    resolve_content(include, &mut executor).await
+}
+
 /// Walk over a root node and execute it and child nodes
 pub async fn execute(
     home: PathBuf,
@@ -165,6 +357,161 @@ pub struct Executor {
     /// The decoding options used when compiling `IncludeBlock`s
     decode_options: Option<DecodeOptions>,
 
+    /// The cache of content decoded from the sources of `IncludeBlock`s
+    ///
+    /// Defaults to the process-wide cache so that content is reused across compiles.
+    include_cache: Arc<Mutex<IncludeCache>>,
+
+    /// How the include cache is used when getting the content of includes
+    ///
+    /// See [`Executor::set_include_cache_policy`].
+    include_cache_policy: IncludeCachePolicy,
+
+    /// Whether to check the freshness of cached remote includes using a `HEAD` request
+    ///
+    /// See [`Executor::set_include_head_check`].
+    include_head_check: bool,
+
+    /// The proxy to use when fetching remote includes
+    ///
+    /// See [`Executor::set_include_proxy`].
+    include_proxy: Option<String>,
+
+    /// The `User-Agent` to use when fetching remote includes
+    ///
+    /// See [`Executor::set_include_user_agent`].
+    include_user_agent: Option<String>,
+
+    /// How to request specific versions of remote includes, for each host
+    ///
+    /// See [`Executor::set_include_versioning`].
+    include_versioning: HashMap<String, IncludeVersioning>,
+
+    /// The number of times to retry fetching a remote include after a transient failure
+    ///
+    /// See [`Executor::set_include_retries`].
+    include_retries: u32,
+
+    /// The permits limiting the number of remote includes fetched concurrently
+    ///
+    /// Defaults to the process-wide permits. See [`Executor::set_include_concurrency`].
+    include_fetch_permits: Arc<Semaphore>,
+
+    /// The maximum rates of requests for remote includes, by host
+    ///
+    /// See [`Executor::set_include_rate_limit`] and [`Executor::set_include_host_rate_limit`].
+    include_rate_limits: RateLimits,
+
+    /// What to do when an include has no source, or its source has no content
+    ///
+    /// See [`Executor::set_include_empty_policy`].
+    include_empty_policy: EmptyIncludePolicy,
+
+    /// The media types of the sources that may be included, if restricted
+    ///
+    /// See [`Executor::set_include_allowed_media_types`].
+    include_allowed_media_types: Option<Vec<String>>,
+
+    /// The media type to decode includes as when their format can not otherwise be determined
+    ///
+    /// See [`Executor::set_include_default_media_type`].
+    include_default_media_type: Option<String>,
+
+    /// A predicate for the blocks to keep in the content of includes
+    ///
+    /// See [`Executor::set_include_filter`].
+    include_filter: Option<IncludeFilter>,
+
+    /// A validator for the content of includes
+    ///
+    /// See [`Executor::set_include_validator`].
+    include_validator: Option<IncludeValidator>,
+
+    /// Whether to restore the variables set for the arguments of includes
+    ///
+    /// See [`Executor::set_include_restore_arguments`].
+    include_restore_arguments: bool,
+
+    /// Whether to deduplicate the content of includes
+    ///
+    /// See [`Executor::set_include_dedup`].
+    include_dedup: bool,
+
+    /// The ids of the first includes with each digest of content and arguments
+    ///
+    /// Used to deduplicate identical content when `include_dedup` is enabled. Cleared at
+    /// the start of each compile so that includes are not deduplicated against includes
+    /// from earlier compiles.
+    include_contents: HashMap<u64, NodeId>,
+
+    /// In-memory sources for includes, keyed by resolved identifier
+    ///
+    /// See [`Executor::set_include_source`].
+    include_sources: Arc<HashMap<String, IncludeSource>>,
+
+    /// Redirects from the resolved identifiers of include sources to others
+    ///
+    /// See [`Executor::set_include_redirect`].
+    include_redirects: HashMap<String, String>,
+
+    /// Whether to rebase relative paths in the content of includes
+    ///
+    /// See [`Executor::set_include_rebase_paths`].
+    include_rebase_paths: bool,
+
+    /// The maximum number of blocks in the content of a single include
+    ///
+    /// See [`Executor::set_include_max_blocks`].
+    include_max_blocks: Option<usize>,
+
+    /// The maximum number of blocks in the content of all includes in a compile
+    ///
+    /// See [`Executor::set_include_max_total_blocks`].
+    include_max_total_blocks: Option<usize>,
+
+    /// The maximum depth that includes can be nested to
+    ///
+    /// See [`Executor::set_include_max_depth`].
+    include_max_depth: Option<usize>,
+
+    /// The number of blocks in the content of includes so far in the current compile
+    ///
+    /// Shared with forks of the executor so that includes compiled within
+    /// them count towards the total.
+    include_total_blocks: Arc<AtomicUsize>,
+
+    /// Whether an error getting the content of an include should fail the walk
+    ///
+    /// See [`Executor::set_strict_includes`].
+    strict_includes: bool,
+
+    /// The first error getting the content of an include when `strict_includes` is enabled
+    ///
+    /// Reset at the start of each walk so that an error does not fail later walks.
+    include_failure: Option<IncludeError>,
+
+    /// Compilation messages from the sources of `IncludeBlock`s, keyed by resolved identifier
+    ///
+    /// Used to deduplicate identical messages when the same source is included
+    /// in multiple places within a single compile.
+    include_messages: HashMap<String, IncludeMessages>,
+
+    /// The number of times that content has been used from the include cache
+    ///
+    /// Compared before and after getting the content of an include to determine
+    /// whether it was from the cache.
+    include_cache_hits: usize,
+
+    /// Whether the content of the last `IncludeBlock` compiled was from the include cache
+    ///
+    /// See [`Executor::compile_include`].
+    include_from_cache: bool,
+
+    /// Whether to trace the resolution of includes
+    ///
+    /// See [`Executor::set_include_trace`].
+    include_trace: bool,
+
     /// The options used when compiling nodes
     compile_options: Option<CompileOptions>,
 
@@ -219,6 +566,24 @@ pub struct Executor {
     /// The count of `Supplement` nodes
     supplement_count: u32,
 
+    /// The number of the last item of the last top-level ordered `List`
+    list_number: Option<i64>,
+
+    /// Whether the next top-level ordered `List` should continue numbering from `list_number`
+    ///
+    /// Set by `IncludeBlock`s with a truthy `continue-lists` argument before walking their content.
+    list_continuation: bool,
+
+    /// The number of `IncludeBlock`s whose content is currently being walked
+    ///
+    /// Used to record the include nesting depth on the compilation messages of includes.
+    include_depth: u32,
+
+    /// The resolved identifiers of the `IncludeBlock`s whose content is currently being walked
+    ///
+    /// Used to detect includes whose source would include itself.
+    include_ancestors: Vec<String>,
+
     /// Labels that may be the target of internal `Link`s
     labels: HashMap<String, (LabelType, String)>,
 
@@ -346,6 +711,35 @@ pub struct CompileOptions {
 
     /// If should lint, should also fix warnings and errors where possible
     pub should_fix: bool,
+
+    /// Resolve, but do not fetch, the sources of `IncludeBlock`s
+    ///
+    /// Useful for previewing what a document would include, and for checking
+    /// the resolution of source paths, without the cost of fetching and decoding
+    /// each source. Each include gets an informational compilation message
+    /// with the resolved identifier and its existing `content` is left unchanged.
+    pub dry_run: bool,
+
+    /// Exclude the content of `IncludeBlock`s whose `if` condition fails to evaluate
+    ///
+    /// By default, if the `if` argument of an include can not be evaluated (e.g. because
+    /// it refers to a variable that has not yet been assigned) then the source is
+    /// included (i.e. it fails open). Use this option to exclude it instead.
+    pub exclude_on_condition_error: bool,
+
+    /// Exclude the content of remote HTML `IncludeBlock`s whose fragment does not match an id
+    ///
+    /// By default, if the source of an include is a remote HTML page with a fragment
+    /// (e.g. `https://example.org/docs.html#install`) and no element in the page has
+    /// that id, then the whole page is included. Use this option to include nothing instead.
+    pub exclude_on_missing_fragment: bool,
+
+    /// Replace `IncludeBlock`s with their content after compiling
+    ///
+    /// Useful for exporting a fully resolved document. Each include, including those
+    /// nested within the content of others, is replaced by an `Excerpt` containing its
+    /// content and with the resolved identifier of its source as the excerpt's `source`.
+    pub flatten_includes: bool,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Args)]
@@ -434,6 +828,36 @@ impl Executor {
         Self {
             directory_stack: vec![home],
             decode_options: None,
+            include_cache: INCLUDE_CACHE.clone(),
+            include_cache_policy: IncludeCachePolicy::Default,
+            include_head_check: false,
+            include_proxy: None,
+            include_user_agent: None,
+            include_versioning: HashMap::new(),
+            include_retries: DEFAULT_RETRIES,
+            include_fetch_permits: FETCH_PERMITS.clone(),
+            include_rate_limits: RateLimits::default(),
+            include_empty_policy: EmptyIncludePolicy::default(),
+            include_allowed_media_types: None,
+            include_default_media_type: None,
+            include_filter: None,
+            include_validator: None,
+            include_restore_arguments: false,
+            include_dedup: false,
+            include_contents: HashMap::new(),
+            include_sources: Arc::new(HashMap::new()),
+            include_redirects: HashMap::new(),
+            include_rebase_paths: false,
+            include_max_blocks: None,
+            include_max_total_blocks: None,
+            include_max_depth: None,
+            include_total_blocks: Arc::new(AtomicUsize::new(0)),
+            strict_includes: false,
+            include_failure: None,
+            include_messages: HashMap::new(),
+            include_cache_hits: 0,
+            include_from_cache: false,
+            include_trace: false,
             compile_options: None,
             kernels,
             patch_sender,
@@ -448,6 +872,10 @@ impl Executor {
             figure_count: 0,
             equation_count: 0,
             supplement_count: 0,
+            list_number: None,
+            list_continuation: false,
+            include_depth: 0,
+            include_ancestors: Vec::new(),
             labels: Default::default(),
             bibliography: Default::default(),
             citations: Default::default(),
@@ -474,6 +902,8 @@ impl Executor {
             figure_count: 0,
             equation_count: 0,
             supplement_count: 0,
+            list_number: None,
+            list_continuation: false,
             labels: Default::default(),
             bibliography: Default::default(),
             citations: Default::default(),
@@ -547,18 +977,39 @@ impl Executor {
     }
 
     /// Run [`Phase::Compile`]
-    async fn compile<N: WalkNode + PatchNode + Debug>(&mut self, root: &mut N) -> Result<()> {
+    async fn compile<N: WalkNode + PatchNode + Clone + Debug>(
+        &mut self,
+        root: &mut N,
+    ) -> Result<()> {
         self.phase = Phase::Compile;
         self.appendix_count = None;
         self.table_count = 0;
         self.figure_count = 0;
         self.equation_count = 0;
         self.supplement_count = 0;
+        self.list_number = None;
+        self.list_continuation = false;
+        self.include_depth = 0;
+        self.include_ancestors.clear();
+        self.include_contents.clear();
+        self.include_total_blocks.store(0, Ordering::Relaxed);
+        self.include_failure = None;
         self.linting_context.clear();
         self.walk_position = 0;
         self.walk_ancestors.clear();
         root.walk_async(self).await?;
 
+        report_repeated_include_messages(self);
+
+        if self
+            .compile_options
+            .as_ref()
+            .map(|opts| opts.flatten_includes)
+            .unwrap_or_default()
+        {
+            self.flatten_includes(root);
+        }
+
         if self
             .compile_options
             .as_ref()
@@ -576,19 +1027,46 @@ impl Executor {
         self.phase = Phase::Link;
         self.walk_position = 0;
         self.walk_ancestors.clear();
+        self.include_failure = None;
         root.walk_async(self).await?;
 
         Ok(())
     }
 
     /// Run [`Phase::Compile`] and [`Phase::Link`] on a node
-    async fn compile_link<N: WalkNode + PatchNode + Debug>(&mut self, node: &mut N) -> Result<()> {
+    async fn compile_link<N: WalkNode + PatchNode + Clone + Debug>(
+        &mut self,
+        node: &mut N,
+    ) -> Result<()> {
         self.compile(node).await?;
         self.link(node).await?;
 
         Ok(())
     }
 
+    /// Replace the `IncludeBlock`s within a compiled root node with their content
+    ///
+    /// Sends a patch with the difference between the root and the flattened root.
+    fn flatten_includes<N: WalkNode + PatchNode + Clone + Debug>(&self, root: &mut N) {
+        let home = self.directory_stack.first().cloned().unwrap_or_default();
+
+        let mut flattened = root.clone();
+        flatten_includes(&home, &mut flattened);
+
+        match diff(root, &flattened, None, None) {
+            Ok(patch) => {
+                if !patch.ops.is_empty() {
+                    self.send_patch(patch);
+                }
+            }
+            Err(error) => {
+                tracing::error!("While diffing flattened includes: {error}")
+            }
+        }
+
+        *root = flattened;
+    }
+
     /// Add a variable declaration to the linting context
     ///
     /// This is used when it is necessary to declare some variable as being part of
@@ -950,6 +1428,7 @@ impl Executor {
         self.phase = Phase::Prepare;
         self.walk_position = 0;
         self.walk_ancestors.clear();
+        self.include_failure = None;
         root.walk_async(self).await
     }
 
@@ -958,6 +1437,7 @@ impl Executor {
         self.phase = Phase::Execute;
         self.walk_position = 0;
         self.walk_ancestors.clear();
+        self.include_failure = None;
         root.walk_async(self).await?;
 
         Ok(())
@@ -968,6 +1448,7 @@ impl Executor {
         self.phase = Phase::Interrupt;
         self.walk_position = 0;
         self.walk_ancestors.clear();
+        self.include_failure = None;
         root.walk_async(self).await
     }
 
@@ -996,6 +1477,340 @@ impl Executor {
         Ok(())
     }
 
+    /// Clear all cached `IncludeBlock` content
+    ///
+    /// Removes both decoded content and the validators used to make conditional
+    /// requests for remote sources so that the next compile refetches all includes.
+    pub fn clear_include_cache(&mut self) {
+        if let Ok(mut cache) = self.include_cache.lock() {
+            cache.clear();
+        }
+    }
+
+    /// Set how the include cache is used when getting the content of includes
+    ///
+    /// By default, cached content is reused (for local files, if they have not been modified
+    /// and, for remote sources, if a conditional request indicates they are unchanged). Use
+    /// [`IncludeCachePolicy::ForceFresh`] to fetch and decode all sources afresh (e.g. for
+    /// deterministic renders in CI), repopulating the cache. Use [`IncludeCachePolicy::Offline`]
+    /// to never make network requests, in which case includes of remote sources that are not
+    /// cached (or registered using [`Executor::set_include_source`]) fail with an error.
+    pub fn set_include_cache_policy(&mut self, policy: IncludeCachePolicy) {
+        self.include_cache_policy = policy;
+    }
+
+    /// Fetch and decode the sources of all `IncludeBlock`s within a node into the include cache
+    ///
+    /// Intended for warming the cache (e.g. when a server starts) so that the first compile
+    /// of a document does not wait for its sources to be fetched. Sources are fetched
+    /// concurrently, with remote sources subject to the include concurrency limit (see
+    /// [`Executor::set_include_concurrency`]). As for [`include_paths`], nested includes are
+    /// only prefetched if the includes they are within already have content.
+    ///
+    /// A failure to fetch or decode a source does not stop the others from being fetched.
+    /// Failures are returned with the resolved identifier of the source.
+    pub async fn prefetch_includes<T: WalkNode>(&self, node: &T) -> Vec<(String, IncludeError)> {
+        let home = self.directory_stack.first().cloned().unwrap_or_default();
+        let dependencies = include_dependencies(&home, node)
+            .into_iter()
+            .unique_by(|dependency| {
+                (
+                    dependency.identifier.clone(),
+                    dependency.media_type.clone(),
+                    dependency.select.clone(),
+                    content_arguments(&dependency.arguments),
+                )
+            })
+            .collect_vec();
+
+        let results = join_all(
+            dependencies
+                .iter()
+                .map(|dependency| prefetch_source(dependency, self)),
+        )
+        .await;
+
+        dependencies
+            .into_iter()
+            .zip(results)
+            .filter_map(|(dependency, result)| {
+                result.err().map(|error| (dependency.identifier, error))
+            })
+            .collect()
+    }
+
+    /// Compile an `IncludeBlock` and get a summary of the result
+    ///
+    /// For embedders driving compilation directly, and for tools and tests that need to
+    /// check the behavior of an include without inspecting patches or logs. The include is
+    /// compiled using this executor (so its settings and include cache are used) in the same
+    /// way as it would be within a document, including any nested includes in its content.
+    pub async fn compile_include(&mut self, include: &mut IncludeBlock) -> IncludeCompileResult {
+        include_result::compile_include(include, self).await
+    }
+
+    /// Enable or disable tracing of the resolution of includes
+    ///
+    /// When enabled, each include that is compiled has an info level compilation message
+    /// listing the steps in getting its content: its source, the directory it was resolved
+    /// against, the resolved file path or URL (and any redirect), the media type and selector
+    /// used, and the number of blocks of content (and whether it was from the cache). Intended
+    /// for debugging includes that do not get the expected content. Disabled by default.
+    pub fn set_include_trace(&mut self, trace: bool) {
+        self.include_trace = trace;
+    }
+
+    /// Invalidate the cached content for an `IncludeBlock` source
+    ///
+    /// The `identifier` is the resolved source i.e. an absolute file path or URL.
+    /// Use this when it is known that a source has changed (e.g. a file watcher
+    /// has detected a change) so that it is refetched on the next compile.
+    pub fn invalidate_include(&mut self, identifier: &str) {
+        if let Ok(mut cache) = self.include_cache.lock() {
+            cache.invalidate(identifier);
+        }
+    }
+
+    /// Enable or disable `HEAD` based freshness checks for remote includes
+    ///
+    /// When enabled, and there is cached content for a remote source, a `HEAD` request
+    /// is made and its `ETag`, `Content-Length`, and `Last-Modified` headers are compared
+    /// to those cached. The `GET` request is skipped if they are unchanged. This can be
+    /// cheaper than a conditional `GET` for very large sources but is disabled by default
+    /// because some servers do not handle `HEAD` requests well.
+    pub fn set_include_head_check(&mut self, enabled: bool) {
+        self.include_head_check = enabled;
+    }
+
+    /// Set the proxy to use when fetching remote includes
+    ///
+    /// By default, the proxy specified in the `HTTPS_PROXY` (or `HTTP_PROXY` for
+    /// `http://` URLs, or `ALL_PROXY` for either) environment variable is used. A proxy
+    /// set here (e.g. `http://proxy.example.org:8080`) takes precedence over those variables
+    /// and is used for all remote includes. In both cases, hosts listed in the `NO_PROXY`
+    /// environment variable are fetched directly. Set to `None` to revert to the default.
+    pub fn set_include_proxy(&mut self, proxy: Option<String>) {
+        self.include_proxy = proxy;
+    }
+
+    /// Set the `User-Agent` header of requests for remote includes
+    ///
+    /// By default, the user agent identifies the version of Stencila making the request
+    /// (e.g. `Stencila/2.0.0 (mailto:user-agent@stencila.io)`). Some servers block or throttle
+    /// requests from unknown user agents, and operators may want to identify traffic from a
+    /// particular pipeline, so a different user agent can be set here. Set to `None` to revert
+    /// to the default.
+    pub fn set_include_user_agent(&mut self, user_agent: Option<String>) {
+        self.include_user_agent = user_agent;
+    }
+
+    /// Set how to request specific versions of remote includes from a host
+    ///
+    /// Includes with a `version` argument (e.g. `--version=2`) request that version of their
+    /// source. By default, the version is requested using a `version` query parameter
+    /// (e.g. `?version=2`). Use this to set a different query parameter, or a header
+    /// (e.g. [`IncludeVersioning::Header`] with `Accept-Version`), for sources on a host
+    /// (e.g. `api.example.org`).
+    pub fn set_include_versioning(
+        &mut self,
+        host: impl Into<String>,
+        versioning: IncludeVersioning,
+    ) {
+        self.include_versioning
+            .insert(host.into().to_lowercase(), versioning);
+    }
+
+    /// Set the number of times to retry fetching a remote include after a transient failure
+    ///
+    /// Timeouts, connection errors, and server errors are retried with exponential
+    /// backoff. Client errors (e.g. not found) are not. Defaults to 2 retries.
+    pub fn set_include_retries(&mut self, retries: u32) {
+        self.include_retries = retries;
+    }
+
+    /// Set the maximum number of remote includes to fetch concurrently
+    ///
+    /// By default, no more than 8 remote sources are fetched at once across all documents
+    /// being compiled, so that servers are not overwhelmed by documents with many remote
+    /// includes. Setting a limit here gives this executor (and those forked from it) its
+    /// own limit. Any `timeout` for an include includes the time waiting to be fetched.
+    pub fn set_include_concurrency(&mut self, limit: usize) {
+        self.include_fetch_permits = Arc::new(Semaphore::new(limit.max(1)));
+    }
+
+    /// Set the default maximum rate of requests, per second, for remote includes from each host
+    ///
+    /// By default, there is no limit on the rate of requests. When set, requests to each host
+    /// are spaced so that they do not exceed the rate (e.g. `Some(2.0)` for no more than two
+    /// requests per second), across all executors. Hosts with their own rate (see
+    /// [`Executor::set_include_host_rate_limit`]) use that instead. Use `None` to remove the
+    /// default. As for concurrency, any `timeout` for an include includes the time waiting.
+    pub fn set_include_rate_limit(&mut self, requests_per_second: Option<f64>) {
+        self.include_rate_limits.set_default(requests_per_second);
+    }
+
+    /// Set the maximum rate of requests, per second, for remote includes from a host
+    ///
+    /// Overrides the default rate (see [`Executor::set_include_rate_limit`]) for the host
+    /// (e.g. `api.example.org`), for example for APIs with a known rate limit. Use `None`
+    /// to remove the rate for the host, so that the default is used.
+    pub fn set_include_host_rate_limit(
+        &mut self,
+        host: impl Into<String>,
+        requests_per_second: Option<f64>,
+    ) {
+        self.include_rate_limits
+            .set_host(host.into(), requests_per_second);
+    }
+
+    /// Set what to do when an include has no source, or its source has no content
+    ///
+    /// By default, empty includes are silently ignored. Use [`EmptyIncludePolicy::Warn`]
+    /// or [`EmptyIncludePolicy::Error`] to add a compilation message to such includes,
+    /// for example to catch mistyped sources or sources that have been emptied.
+    pub fn set_include_empty_policy(&mut self, policy: EmptyIncludePolicy) {
+        self.include_empty_policy = policy;
+    }
+
+    /// Set the media types of the sources that may be included
+    ///
+    /// By default, sources of any media type may be included. When set, an include whose
+    /// explicit media type, or the media type inferred from the path of its source, is not one
+    /// of these (e.g. `["text/markdown"]`) is not fetched and has an error compilation message.
+    /// Format names (e.g. `md`) may be used instead of media types. Set to `None` to allow all.
+    pub fn set_include_allowed_media_types(&mut self, media_types: Option<Vec<String>>) {
+        self.include_allowed_media_types = media_types;
+    }
+
+    /// Set the media type to decode includes as when their format can not otherwise be determined
+    ///
+    /// By default, an include whose format can not be determined from its explicit media type,
+    /// the extension of its source, the `Content-Type` of the response, or by sniffing its
+    /// leading bytes, fails to decode. When set, such includes are decoded using this media
+    /// type instead e.g. `text/markdown` to treat unknown sources as Markdown. Format names
+    /// (e.g. `md`) may be used instead of media types. Set to `None` to remove the default.
+    pub fn set_include_default_media_type(&mut self, media_type: Option<String>) {
+        self.include_default_media_type = media_type;
+    }
+
+    /// Set a predicate for the blocks to keep in the content of includes
+    ///
+    /// After the content of an include has been decoded (and any `select` applied), blocks
+    /// for which the predicate returns `false` are removed, including those nested within
+    /// other blocks. For example, `Arc::new(|block| !matches!(block, Block::CodeChunk(..)))`
+    /// removes all code chunks from includes. Set to `None` to keep all blocks.
+    pub fn set_include_filter(&mut self, filter: Option<IncludeFilter>) {
+        self.include_filter = filter;
+    }
+
+    /// Set a validator for the content of includes
+    ///
+    /// After the content of an include has been decoded (and any `select` and filter applied),
+    /// the validator is called with it and the messages it returns (e.g. an error for a figure
+    /// without a caption) are added to the compilation messages of the include. The validator
+    /// is called for each include, including those nested within the content of other includes.
+    /// Set to `None` to not validate the content of includes.
+    pub fn set_include_validator(&mut self, validator: Option<IncludeValidator>) {
+        self.include_validator = validator;
+    }
+
+    /// Enable or disable restoring the variables set for the arguments of includes
+    ///
+    /// By default, the arguments of an include remain as variables in the kernels after its
+    /// content has been executed. When enabled, once the content of an include has been
+    /// executed, each variable set for one of its arguments is restored to the value it had
+    /// before the include was executed, or removed if it did not exist, so that arguments
+    /// do not leak into the including document, or accumulate across repeated includes.
+    pub fn set_include_restore_arguments(&mut self, enabled: bool) {
+        self.include_restore_arguments = enabled;
+    }
+
+    /// Enable or disable deduplication of the content of includes
+    ///
+    /// When enabled, an include whose content (and arguments) are identical to those of an
+    /// earlier include has its `content` left empty and its `contentFrom` set to the id of
+    /// that earlier include. This reduces the size of documents that include the same large
+    /// content many times but should only be used when encoding to formats that can reuse
+    /// that content (e.g. HTML). Deduplicated content is not itself compiled or executed.
+    pub fn set_include_dedup(&mut self, enabled: bool) {
+        self.include_dedup = enabled;
+    }
+
+    /// Register an in-memory source for includes
+    ///
+    /// Intended for tests, and for embedders that hold the content of sources in memory.
+    /// The `identifier` is the resolved source i.e. the path of the source relative to
+    /// home (e.g. `/home/parts/intro.md` for a source of `parts/intro.md` when home is
+    /// `/home`), or a URL. In-memory sources are used instead of any file or remote
+    /// resource with the same identifier. Forks of the executor share registered sources.
+    pub fn set_include_source(&mut self, identifier: impl Into<String>, source: IncludeSource) {
+        Arc::make_mut(&mut self.include_sources).insert(identifier.into(), source);
+    }
+
+    /// Redirect includes of one source to another
+    ///
+    /// Allows the same document to include different content in different environments
+    /// without editing it, for example, redirecting `/docs/config.md` to `/docs/config.prod.md`
+    /// in production builds. As for [`Executor::set_include_source`], `from` and `to` are
+    /// resolved identifiers (file paths or URLs). Redirects are applied to each alternative of
+    /// a fallback chain and are not chained. Sources nested within the content of the include
+    /// are still resolved relative to the directory of the original source.
+    pub fn set_include_redirect(&mut self, from: impl Into<String>, to: impl Into<String>) {
+        self.include_redirects.insert(from.into(), to.into());
+    }
+
+    /// Enable or disable rebasing of relative paths in the content of includes
+    ///
+    /// When enabled, the relative paths of images, audio, video, and links in the content of
+    /// an include are rewritten so that they resolve correctly after the content is inlined:
+    /// relative to the home directory for local sources within it (e.g. `images/plot.png` in
+    /// `parts/intro.md` becomes `parts/images/plot.png`), absolute for other local sources,
+    /// and absolute URLs for remote sources. Disabled by default.
+    pub fn set_include_rebase_paths(&mut self, enabled: bool) {
+        self.include_rebase_paths = enabled;
+    }
+
+    /// Set the maximum number of blocks in the content of a single include
+    ///
+    /// Content with more top-level blocks than this (e.g. from a large source, or a glob
+    /// matching many sources) is truncated and a warning is added to the include. Unlike
+    /// limits on the size of sources, this protects against sources that expand into
+    /// very many blocks when decoded. By default there is no limit.
+    pub fn set_include_max_blocks(&mut self, max: Option<usize>) {
+        self.include_max_blocks = max;
+    }
+
+    /// Set the maximum number of blocks in the content of all includes in a compile
+    ///
+    /// Once the top-level blocks of the content of includes (including nested includes)
+    /// reach this number, the content of any further includes is truncated, and a warning
+    /// added to each, so that documents that expand explosively do not freeze those viewing
+    /// or editing them. By default there is no limit.
+    pub fn set_include_max_total_blocks(&mut self, max: Option<usize>) {
+        self.include_max_total_blocks = max;
+    }
+
+    /// Set the maximum depth that includes can be nested to
+    ///
+    /// An include that would be nested deeper than this (e.g. the third level of includes
+    /// when the maximum is two) is not fetched and instead gets an error. Cycles of includes
+    /// are always prevented, so this protects against chains of distinct sources that are
+    /// very deeply nested, such as generated or remote sources. By default there is no limit.
+    pub fn set_include_max_depth(&mut self, max: Option<usize>) {
+        self.include_max_depth = max;
+    }
+
+    /// Enable or disable strict includes
+    ///
+    /// By default, an error getting the content of an include (e.g. a missing file or a
+    /// failed request) is recorded as a compilation message on the include and the walk
+    /// continues. When enabled, the first such error fails the walk and is returned as an
+    /// [`IncludeError`], for example so that publishing pipelines can treat broken includes
+    /// as build failures.
+    pub fn set_strict_includes(&mut self, enabled: bool) {
+        self.strict_includes = enabled;
+    }
+
     /// Obtain a write lock to the kernels
     ///
     /// Used by [`Executable`] nodes to execute and evaluate code and manage variables.
@@ -1254,7 +2069,7 @@ impl VisitorAsync for Executor {
         self.walk_position += 1;
 
         use Block::*;
-        Ok(match block {
+        let control = match block {
             AppendixBreak(node) => self.visit_executable(node).await,
             CallBlock(node) => self.visit_executable(node).await,
             Chat(node) => self.visit_executable(node).await,
@@ -1266,6 +2081,7 @@ impl VisitorAsync for Executor {
             IncludeBlock(node) => self.visit_executable(node).await,
             InstructionBlock(node) => self.visit_executable(node).await,
             Island(node) => self.visit_executable(node).await,
+            List(node) => self.visit_executable(node).await,
             MathBlock(node) => self.visit_executable(node).await,
             PromptBlock(node) => self.visit_executable(node).await,
             RawBlock(node) => self.visit_executable(node).await,
@@ -1274,7 +2090,15 @@ impl VisitorAsync for Executor {
             Supplement(node) => self.visit_executable(node).await,
             Table(node) => self.visit_executable(node).await,
             _ => WalkControl::Continue,
-        })
+        };
+
+        // Fail the walk if there was an error getting the content of an include (only
+        // recorded if includes are strict, and reset at the start of each walk)
+        if let Some(error) = &self.include_failure {
+            return Err(error.clone().into());
+        }
+
+        Ok(control)
     }
 
     async fn visit_suggestion_block(&mut self, block: &mut SuggestionBlock) -> Result<WalkControl> {
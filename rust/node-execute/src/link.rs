@@ -11,6 +11,7 @@ impl Executable for Link {
             should_lint,
             should_format,
             should_fix,
+            ..
         } = executor.compile_options.clone().unwrap_or_default();
 
         if !should_lint {
@@ -0,0 +1,51 @@
+use stencila_schema::{List, ListOrder, NodeProperty, NodeType};
+
+use crate::prelude::*;
+
+impl Executable for List {
+    #[tracing::instrument(skip_all)]
+    async fn compile(&mut self, executor: &mut Executor) -> WalkControl {
+        // Only top-level ordered lists (i.e. not those nested within list items) are numbered
+        if !matches!(self.order, ListOrder::Ascending)
+            || executor.walk_ancestors.contains(&NodeType::ListItem)
+        {
+            return WalkControl::Continue;
+        }
+
+        let node_id = self.node_id();
+        tracing::trace!("Compiling List {node_id}");
+
+        // If this is the leading list of an include that continues the numbering of lists,
+        // then number its items from the last number of the preceding list
+        if executor.list_continuation {
+            executor.list_continuation = false;
+
+            let start = executor.list_number.unwrap_or_default() + 1;
+            for (index, item) in self.items.iter_mut().enumerate() {
+                let position = start + index as i64;
+                if item.position != Some(position) {
+                    item.position = Some(position);
+                    executor.patch(
+                        &item.node_id(),
+                        [set(NodeProperty::Position, Some(position))],
+                    );
+                }
+            }
+        }
+
+        // Record the number of the last item so that lists in later includes can continue from it
+        let first = self
+            .items
+            .first()
+            .and_then(|item| item.position)
+            .unwrap_or(1);
+        let last = self
+            .items
+            .last()
+            .and_then(|item| item.position)
+            .unwrap_or(first + self.items.len() as i64 - 1);
+        executor.list_number = Some(last);
+
+        WalkControl::Continue
+    }
+}
@@ -23,6 +23,24 @@ pub fn is_valid_variable_name(name: &str) -> bool {
     VARIABLE_REGEX.is_match(name)
 }
 
+/// Is a value truthy?
+///
+/// Used to determine whether the condition of an `IfBlockClause`, or
+/// the `if` argument of an `IncludeBlock`, is satisfied.
+pub fn is_truthy(value: &Node) -> bool {
+    match value {
+        Node::Null(..) => false,
+        Node::Boolean(bool) => *bool,
+        Node::Integer(int) => *int > 0,
+        Node::UnsignedInteger(uint) => *uint > 0,
+        Node::Number(number) => *number > 0.,
+        Node::String(string) => !string.is_empty(),
+        Node::Array(array) => !array.is_empty(),
+        Node::Object(object) => !object.is_empty(),
+        _ => true,
+    }
+}
+
 /// Add to an existing digest
 pub fn add_to_digest(digest: &mut u64, bytes: &[u8]) {
     let mut hash = SeaHasher::new();
@@ -55,6 +55,7 @@ pub enum NodeProperty {
     ContactPoints,
     Contains,
     Content,
+    ContentFrom,
     ContentSize,
     ContentUrl,
     Contributors,
@@ -79,6 +80,7 @@ pub enum NodeProperty {
     DependencyId,
     DependencyRelation,
     DependencyType,
+    Depth,
     DeriveAction,
     DeriveFrom,
     DeriveItem,
@@ -142,10 +144,12 @@ pub enum NodeProperty {
     IsDisabled,
     IsEchoed,
     IsEmbedded,
+    IsExpression,
     IsFolded,
     IsGlobal,
     IsHidden,
     IsPartOf,
+    IsRemote,
     IsSelected,
     Issns,
     IssueNumber,
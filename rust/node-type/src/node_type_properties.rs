@@ -16,8 +16,8 @@ pub(crate) fn node_type_properties(node_type: &NodeType) -> Vec<NodeProperty> {
         NodeType::BooleanValidator => vec![NodeProperty::Id],
         NodeType::Brand => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::Logo, NodeProperty::Reviews],
         NodeType::Button => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Code, NodeProperty::ProgrammingLanguage, NodeProperty::ExecutionBounds, NodeProperty::ExecutionBounded, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::Name, NodeProperty::Label, NodeProperty::IsDisabled],
-        NodeType::CallArgument => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Name, NodeProperty::Label, NodeProperty::Value, NodeProperty::Default, NodeProperty::Validator, NodeProperty::DerivedFrom, NodeProperty::Code, NodeProperty::ProgrammingLanguage],
-        NodeType::CallBlock => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Source, NodeProperty::MediaType, NodeProperty::Select, NodeProperty::Content, NodeProperty::Arguments],
+        NodeType::CallArgument => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Name, NodeProperty::Label, NodeProperty::Value, NodeProperty::Default, NodeProperty::Validator, NodeProperty::DerivedFrom, NodeProperty::Code, NodeProperty::ProgrammingLanguage, NodeProperty::IsExpression],
+        NodeType::CallBlock => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Source, NodeProperty::MediaType, NodeProperty::Select, NodeProperty::Content, NodeProperty::Arguments, NodeProperty::IsRemote, NodeProperty::ContentFrom],
         NodeType::Chat => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::WorkType, NodeProperty::Doi, NodeProperty::About, NodeProperty::Abstract, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::Contributors, NodeProperty::Editors, NodeProperty::Maintainers, NodeProperty::Comments, NodeProperty::DateCreated, NodeProperty::DateReceived, NodeProperty::DateAccepted, NodeProperty::DateModified, NodeProperty::DatePublished, NodeProperty::Funders, NodeProperty::FundedBy, NodeProperty::Genre, NodeProperty::Keywords, NodeProperty::IsPartOf, NodeProperty::Licenses, NodeProperty::Parts, NodeProperty::Publisher, NodeProperty::References, NodeProperty::Text, NodeProperty::Title, NodeProperty::Repository, NodeProperty::Path, NodeProperty::Commit, NodeProperty::Version, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::IsEmbedded, NodeProperty::Prompt, NodeProperty::ModelParameters, NodeProperty::TargetNodes, NodeProperty::Content],
         NodeType::ChatMessage => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Author, NodeProperty::Role, NodeProperty::Content, NodeProperty::Files, NodeProperty::IsSelected],
         NodeType::ChatMessageGroup => vec![NodeProperty::Id, NodeProperty::Messages],
@@ -32,7 +32,7 @@ pub(crate) fn node_type_properties(node_type: &NodeType) -> Vec<NodeProperty> {
         NodeType::Collection => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::WorkType, NodeProperty::Doi, NodeProperty::About, NodeProperty::Abstract, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::Contributors, NodeProperty::Editors, NodeProperty::Maintainers, NodeProperty::Comments, NodeProperty::DateCreated, NodeProperty::DateReceived, NodeProperty::DateAccepted, NodeProperty::DateModified, NodeProperty::DatePublished, NodeProperty::Funders, NodeProperty::FundedBy, NodeProperty::Genre, NodeProperty::Keywords, NodeProperty::IsPartOf, NodeProperty::Licenses, NodeProperty::Parts, NodeProperty::Publisher, NodeProperty::References, NodeProperty::Text, NodeProperty::Title, NodeProperty::Repository, NodeProperty::Path, NodeProperty::Commit, NodeProperty::Version],
         NodeType::Comment => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::WorkType, NodeProperty::Doi, NodeProperty::About, NodeProperty::Abstract, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::Contributors, NodeProperty::Editors, NodeProperty::Maintainers, NodeProperty::Comments, NodeProperty::DateCreated, NodeProperty::DateReceived, NodeProperty::DateAccepted, NodeProperty::DateModified, NodeProperty::DatePublished, NodeProperty::Funders, NodeProperty::FundedBy, NodeProperty::Genre, NodeProperty::Keywords, NodeProperty::IsPartOf, NodeProperty::Licenses, NodeProperty::Parts, NodeProperty::Publisher, NodeProperty::References, NodeProperty::Text, NodeProperty::Title, NodeProperty::Repository, NodeProperty::Path, NodeProperty::Commit, NodeProperty::Version, NodeProperty::Content, NodeProperty::ParentItem, NodeProperty::CommentAspect],
         NodeType::CompilationDigest => vec![NodeProperty::Id, NodeProperty::StateDigest, NodeProperty::SemanticDigest, NodeProperty::DependenciesDigest, NodeProperty::DependenciesStale, NodeProperty::DependenciesFailed],
-        NodeType::CompilationMessage => vec![NodeProperty::Id, NodeProperty::Level, NodeProperty::Message, NodeProperty::ErrorType, NodeProperty::CodeLocation],
+        NodeType::CompilationMessage => vec![NodeProperty::Id, NodeProperty::Level, NodeProperty::Message, NodeProperty::ErrorType, NodeProperty::CodeLocation, NodeProperty::Depth],
         NodeType::ConstantValidator => vec![NodeProperty::Id, NodeProperty::Value],
         NodeType::ContactPoint => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::Emails, NodeProperty::TelephoneNumbers, NodeProperty::AvailableLanguages],
         NodeType::CreativeWork => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::WorkType, NodeProperty::Doi, NodeProperty::About, NodeProperty::Abstract, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::Contributors, NodeProperty::Editors, NodeProperty::Maintainers, NodeProperty::Comments, NodeProperty::DateCreated, NodeProperty::DateReceived, NodeProperty::DateAccepted, NodeProperty::DateModified, NodeProperty::DatePublished, NodeProperty::Funders, NodeProperty::FundedBy, NodeProperty::Genre, NodeProperty::Keywords, NodeProperty::IsPartOf, NodeProperty::Licenses, NodeProperty::Parts, NodeProperty::Publisher, NodeProperty::References, NodeProperty::Text, NodeProperty::Title, NodeProperty::Repository, NodeProperty::Path, NodeProperty::Commit, NodeProperty::Version],
@@ -66,7 +66,7 @@ pub(crate) fn node_type_properties(node_type: &NodeType) -> Vec<NodeProperty> {
         NodeType::IfBlock => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Clauses],
         NodeType::IfBlockClause => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Code, NodeProperty::ProgrammingLanguage, NodeProperty::ExecutionBounds, NodeProperty::ExecutionBounded, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::IsActive, NodeProperty::Content],
         NodeType::ImageObject => vec![NodeProperty::Id, NodeProperty::AlternateNames, NodeProperty::Description, NodeProperty::Identifiers, NodeProperty::Images, NodeProperty::Name, NodeProperty::Url, NodeProperty::WorkType, NodeProperty::Doi, NodeProperty::About, NodeProperty::Abstract, NodeProperty::Authors, NodeProperty::Provenance, NodeProperty::Contributors, NodeProperty::Editors, NodeProperty::Maintainers, NodeProperty::Comments, NodeProperty::DateCreated, NodeProperty::DateReceived, NodeProperty::DateAccepted, NodeProperty::DateModified, NodeProperty::DatePublished, NodeProperty::Funders, NodeProperty::FundedBy, NodeProperty::Genre, NodeProperty::Keywords, NodeProperty::IsPartOf, NodeProperty::Licenses, NodeProperty::Parts, NodeProperty::Publisher, NodeProperty::References, NodeProperty::Text, NodeProperty::Title, NodeProperty::Repository, NodeProperty::Path, NodeProperty::Commit, NodeProperty::Version, NodeProperty::Bitrate, NodeProperty::ContentSize, NodeProperty::ContentUrl, NodeProperty::EmbedUrl, NodeProperty::MediaType, NodeProperty::Caption, NodeProperty::Thumbnail],
-        NodeType::IncludeBlock => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Source, NodeProperty::MediaType, NodeProperty::Select, NodeProperty::Content],
+        NodeType::IncludeBlock => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::Source, NodeProperty::MediaType, NodeProperty::Select, NodeProperty::Content, NodeProperty::Arguments, NodeProperty::IsRemote, NodeProperty::ContentFrom],
         NodeType::InlinesBlock => vec![NodeProperty::Id, NodeProperty::Content],
         NodeType::InstructionBlock => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::InstructionType, NodeProperty::Prompt, NodeProperty::Message, NodeProperty::ModelParameters, NodeProperty::ActiveSuggestion, NodeProperty::Content, NodeProperty::Suggestions],
         NodeType::InstructionInline => vec![NodeProperty::Id, NodeProperty::ExecutionMode, NodeProperty::CompilationDigest, NodeProperty::CompilationMessages, NodeProperty::ExecutionDigest, NodeProperty::ExecutionDependencies, NodeProperty::ExecutionDependants, NodeProperty::ExecutionTags, NodeProperty::ExecutionCount, NodeProperty::ExecutionRequired, NodeProperty::ExecutionStatus, NodeProperty::ExecutionInstance, NodeProperty::ExecutionEnded, NodeProperty::ExecutionDuration, NodeProperty::ExecutionMessages, NodeProperty::InstructionType, NodeProperty::Prompt, NodeProperty::Message, NodeProperty::ModelParameters, NodeProperty::ActiveSuggestion, NodeProperty::Content, NodeProperty::Suggestions],
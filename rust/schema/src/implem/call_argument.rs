@@ -3,6 +3,32 @@ use stencila_codec_json5_trait::Json5Codec;
 
 use crate::{CallArgument, prelude::*};
 
+impl CallArgument {
+    /// Get a description of any unbalanced `{{ }}` braces in the argument
+    ///
+    /// Detects the common typo of a missing pair of opening or closing braces around an
+    /// expression (e.g. `{{site` or `site}}`) in the code of an expression argument, which
+    /// would otherwise be code referring to a variable that does not exist. Literal values
+    /// are never checked because they may contain braces (e.g. `--a="{{site"`). Returns `None`
+    /// if the braces are balanced (or there are none).
+    pub fn unbalanced_braces(&self) -> Option<&'static str> {
+        if self.is_expression != Some(true) {
+            return None;
+        }
+
+        let text = self.code.trim();
+        let opens = text.starts_with("{{");
+        let closes = text.ends_with("}}");
+        if opens && !closes {
+            Some("missing closing `}}`")
+        } else if closes && !text.contains('{') {
+            Some("missing opening `{{`")
+        } else {
+            None
+        }
+    }
+}
+
 impl MarkdownCodec for CallArgument {
     fn to_markdown(&self, context: &mut MarkdownEncodeContext) {
         context
@@ -12,20 +38,24 @@ impl MarkdownCodec for CallArgument {
             .push_prop_str(NodeProperty::Name, &self.name)
             .push_str("=");
 
-        if self.code.is_empty() && self.value.is_some() {
-            let json5 = self
-                .value
-                .as_ref()
-                .expect("should be some")
-                .to_json5()
-                .unwrap_or_default();
-            context.push_prop_str(NodeProperty::Value, &json5);
-        } else {
-            context
-                .push_str("`")
-                .push_prop_fn(NodeProperty::Code, |context| self.code.to_markdown(context))
-                .push_str("`");
-        };
+        // Arguments without the `is_expression` flag (e.g. from documents created before it
+        // was added) are expressions if they have code, or have no value to encode
+        let is_expression = self
+            .is_expression
+            .unwrap_or(!self.code.is_empty() || self.value.is_none());
+
+        match &self.value {
+            Some(value) if !is_expression => {
+                let json5 = value.to_json5().unwrap_or_default();
+                context.push_prop_str(NodeProperty::Value, &json5);
+            }
+            _ => {
+                context
+                    .push_str("`")
+                    .push_prop_fn(NodeProperty::Code, |context| self.code.to_markdown(context))
+                    .push_str("`");
+            }
+        }
 
         context.exit_node();
     }
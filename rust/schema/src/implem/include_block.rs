@@ -1,43 +1,221 @@
 use stencila_codec_info::{lost_exec_options, lost_options};
+use stencila_codec_json5_trait::Json5Codec;
+use stencila_codec_latex_trait::escape_latex_literal;
 use stencila_node_url::NodePosition;
 
-use crate::{IncludeBlock, prelude::*};
+use crate::{
+    Block, CallArgument, Cord, Heading, IncludeBlock, MessageLevel, Null, Section, prelude::*,
+};
+
+impl IncludeBlock {
+    /// Whether the include failed to resolve its source
+    ///
+    /// An include without content (or a reference to the content of an earlier include)
+    /// has failed if it has an error or exception compilation message. Includes without
+    /// content for other reasons (e.g. not yet compiled, or excluded by an `if` argument)
+    /// have not failed.
+    fn is_failed(&self) -> bool {
+        if self.content.is_some() || self.options.content_from.is_some() {
+            return false;
+        }
+
+        self.options
+            .compilation_messages
+            .iter()
+            .flatten()
+            .any(|message| matches!(message.level, MessageLevel::Error | MessageLevel::Exception))
+    }
+
+    /// Add an argument with a literal value
+    ///
+    /// For example, `IncludeBlock::new(source).with_argument("level", Node::Integer(2))`.
+    pub fn with_argument<S, V>(mut self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Node>,
+    {
+        self.arguments.get_or_insert_default().push(CallArgument {
+            name: name.into(),
+            value: Some(Box::new(value.into())),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Add an argument with code to be evaluated when the include is executed
+    ///
+    /// For example, `IncludeBlock::new(source).with_code_argument("if", "draft")`.
+    pub fn with_code_argument<S, C>(mut self, name: S, code: C) -> Self
+    where
+        S: Into<String>,
+        C: Into<Cord>,
+    {
+        self.arguments.get_or_insert_default().push(CallArgument {
+            name: name.into(),
+            code: code.into(),
+            is_expression: Some(true),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Add several arguments
+    pub fn with_arguments<I>(mut self, arguments: I) -> Self
+    where
+        I: IntoIterator<Item = CallArgument>,
+    {
+        self.arguments.get_or_insert_default().extend(arguments);
+        self
+    }
+}
+
+/// The LaTeX float environments that the content of an include can be rendered within
+const LATEX_FLOATS: [&str; 2] = ["figure", "table"];
+
+impl IncludeBlock {
+    /// The name of the argument used to conditionally include a source
+    ///
+    /// This, and the other argument names below, are reserved (i.e. not set as variables
+    /// for the content of the include) when executing the include.
+    pub const IF_ARGUMENT: &str = "if";
+
+    /// The name of the argument used to render the content of an include within a LaTeX float
+    pub const LATEX_FLOAT_ARGUMENT: &str = "latex-float";
+
+    /// The name of the argument used for the caption of the LaTeX float of an include
+    pub const LATEX_CAPTION_ARGUMENT: &str = "latex-caption";
+
+    /// The name of the argument used to render the sections of an include as MyST tabs
+    pub const MYST_TABS_ARGUMENT: &str = "myst-tabs";
+
+    /// The name of the argument used to override whether the content of an include is rendered
+    pub const RENDER_ARGUMENT: &str = "render";
+
+    /// Whether an argument has a literal `true` value
+    fn is_true_argument(&self, name: &str) -> bool {
+        self.boolean_argument(name) == Some(true)
+    }
+
+    /// Get the value of an argument with a literal boolean value
+    fn boolean_argument(&self, name: &str) -> Option<bool> {
+        self.arguments
+            .iter()
+            .flatten()
+            .find(|arg| arg.name == name)
+            .and_then(|arg| match arg.value.as_deref() {
+                Some(Node::Boolean(value)) => Some(*value),
+                _ => None,
+            })
+    }
+
+    /// Whether the include should be rendered (i.e. replaced by its content)
+    ///
+    /// A literal boolean `render` argument (e.g. `--render=false`) overrides the
+    /// `render` option of the encoding context for this include, allowing some
+    /// includes to be kept as directives while others are inlined in the same pass.
+    fn is_rendered(&self, render: bool) -> bool {
+        self.boolean_argument(Self::RENDER_ARGUMENT)
+            .unwrap_or(render)
+    }
+
+    /// Get the value of an argument with a literal string value
+    fn string_argument(&self, name: &str) -> Option<&str> {
+        self.arguments
+            .iter()
+            .flatten()
+            .find(|arg| arg.name == name)
+            .and_then(|arg| match arg.value.as_deref() {
+                Some(Node::String(value)) => Some(value.as_str()),
+                _ => None,
+            })
+    }
+}
 
 impl LatexCodec for IncludeBlock {
     fn to_latex(&self, context: &mut LatexEncodeContext) {
         context
             .enter_node(self.node_type(), self.node_id())
-            .merge_losses(lost_options!(self, id, media_type, select, execution_mode))
+            .merge_losses(lost_options!(
+                self,
+                id,
+                media_type,
+                select,
+                arguments,
+                execution_mode
+            ))
             .merge_losses(lost_exec_options!(self));
 
-        if context.render {
+        let render = self.is_rendered(context.render);
+        if render {
+            // The source is escaped, rather than placed in a `\verb`, because `\verb`
+            // can not be used within the argument of `\href`, and sources may contain
+            // characters (e.g. `#`, `%`, `|`) that would break the link or the `\verb`
+            let source = escape_latex_literal(&self.source);
+
             if context.reproducible {
                 context
                     .str("\n\n\\centerline{")
                     .link_with(
                         Some(NodePosition::Begin),
-                        &format!(r"\verb|[Begin {}]|", self.source),
+                        &format!(r"\texttt{{[Begin {source}]}}"),
                     )
                     .str("}\n\n");
             }
 
+            // Render the content within a float if the `latex-float` argument is
+            // a float environment e.g. `--latex-float=figure`
+            let float = self
+                .string_argument(Self::LATEX_FLOAT_ARGUMENT)
+                .and_then(|float| LATEX_FLOATS.into_iter().find(|name| *name == float));
+
+            if let Some(float) = float {
+                context.ensure_blankline().environ_begin(float).newline();
+            }
+
+            // Encode the content consistently with the include itself, restoring
+            // the context's `render` option afterwards if it was overridden
+            let global = std::mem::replace(&mut context.render, render);
             context.property_fn(NodeProperty::Content, |context| {
                 self.content.to_latex(context)
             });
+            context.render = global;
+
+            if let Some(float) = float {
+                context.ensure_blankline();
+                if let Some(caption) = self.string_argument(Self::LATEX_CAPTION_ARGUMENT) {
+                    context
+                        .command_begin("caption")
+                        .escaped_str(caption)
+                        .command_end()
+                        .newline();
+                }
+                let label = match &self.id {
+                    Some(id) => id.clone(),
+                    None => self.node_id().to_string(),
+                };
+                context
+                    .command_begin("label")
+                    .str(&label)
+                    .command_end()
+                    .newline()
+                    .environ_end(float)
+                    .newline()
+                    .newline();
+            }
 
             if context.reproducible {
                 context
                     .str("\n\n\\centerline{")
                     .link_with(
                         Some(NodePosition::End),
-                        &format!(r"\verb|[End {}]|", self.source),
+                        &format!(r"\texttt{{[End {source}]}}"),
                     )
                     .str("}\n\n");
             }
         } else {
             context
                 .str("\\input{")
-                .property_str(NodeProperty::Source, self.source.trim_end_matches(".tex"))
+                .property_str(NodeProperty::Source, strip_tex_extension(&self.source))
                 .char('}')
                 .newline()
                 .newline();
@@ -47,6 +225,26 @@ impl LatexCodec for IncludeBlock {
     }
 }
 
+/// Strip a single trailing `.tex` extension (case-insensitively) from a source
+///
+/// LaTeX's `\input` adds the extension itself. Sources without the extension, or
+/// whose file name is only the extension (e.g. `.tex`), are returned unchanged.
+fn strip_tex_extension(source: &str) -> &str {
+    let Some(split) = source.len().checked_sub(4) else {
+        return source;
+    };
+    if !source.is_char_boundary(split) {
+        return source;
+    }
+
+    let (stem, extension) = source.split_at(split);
+    if extension.eq_ignore_ascii_case(".tex") && !stem.is_empty() && !stem.ends_with('/') {
+        stem
+    } else {
+        source
+    }
+}
+
 impl MarkdownCodec for IncludeBlock {
     fn to_markdown(&self, context: &mut MarkdownEncodeContext) {
         context
@@ -54,12 +252,34 @@ impl MarkdownCodec for IncludeBlock {
             .merge_losses(lost_options!(self, id))
             .merge_losses(lost_exec_options!(self));
 
-        if matches!(context.format, Format::Llmd) || context.render {
+        // Render the sections of the content as tabs if the `myst-tabs` argument
+        // is true e.g. for alternative examples in different languages
+        if matches!(context.format, Format::Myst)
+            && self.is_true_argument(Self::MYST_TABS_ARGUMENT)
+            && let Some(content) = &self.content
+        {
             context
+                .merge_losses(flattened_losses(self))
+                .push_prop_fn(NodeProperty::Content, |context| {
+                    tabs_to_markdown(content, context)
+                })
+                .exit_node();
+
+            return;
+        }
+
+        let render = self.is_rendered(context.render);
+        if matches!(context.format, Format::Llmd) || render {
+            // Encode the content consistently with the include itself, restoring
+            // the context's `render` option afterwards if it was overridden
+            let global = std::mem::replace(&mut context.render, render);
+            context
+                .merge_losses(flattened_losses(self))
                 .push_prop_fn(NodeProperty::Content, |context| {
                     self.content.to_markdown(context)
                 })
                 .exit_node();
+            context.render = global;
 
             return;
         }
@@ -95,6 +315,12 @@ impl MarkdownCodec for IncludeBlock {
                         if let Some(select) = self.select.as_ref() {
                             context.myst_directive_option(NodeProperty::Select, None, select);
                         }
+
+                        for arg in self.arguments.iter().flatten() {
+                            context.push_str(":");
+                            arg_to_markdown(arg, ": ", context);
+                            context.newline();
+                        }
                     },
                     |_| {},
                 )
@@ -107,6 +333,11 @@ impl MarkdownCodec for IncludeBlock {
                 .push_str(" include ")
                 .push_prop_str(NodeProperty::Source, &self.source);
 
+            for arg in self.arguments.iter().flatten() {
+                context.push_str(" --");
+                arg_to_markdown(arg, "=", context);
+            }
+
             if self.execution_mode.is_some() || self.media_type.is_some() || self.select.is_some() {
                 context.push_str(" {");
 
@@ -140,14 +371,365 @@ impl MarkdownCodec for IncludeBlock {
             context.newline().exit_node().newline();
         } else {
             // For Markdown, QMD etc, which do not support include blocks, only encode content (if any)
+            context.merge_losses(flattened_losses(self));
             if let Some(content) = &self.content
                 && !content.is_empty()
             {
                 context.push_prop_fn(NodeProperty::Content, |context| {
                     content.to_markdown(context)
                 });
+            } else if context.include_placeholders && self.is_failed() {
+                // If enabled, make failed includes visible rather than silently omitting them
+                context
+                    .push_str("> ⚠ include failed: ")
+                    .push_prop_str(NodeProperty::Source, &self.source)
+                    .newline()
+                    .newline();
             }
             context.exit_node();
         }
     }
 }
+
+/// Encode the content of an [`IncludeBlock`] as a MyST tab set
+///
+/// Each top-level section of the content becomes a tab titled by the section's heading.
+/// Any blocks before the first section are encoded before the tab set. If the content
+/// has no headings then it is encoded as is.
+fn tabs_to_markdown(content: &[Block], context: &mut MarkdownEncodeContext) {
+    let (preamble, tabs) = split_tabs(content);
+    for block in preamble {
+        block.to_markdown(context);
+    }
+    if tabs.is_empty() {
+        return;
+    }
+
+    context
+        .myst_directive(
+            ':',
+            "tab-set",
+            |_| {},
+            |_| {},
+            |context| {
+                for (heading, blocks) in &tabs {
+                    context.myst_directive(
+                        ':',
+                        "tab-item",
+                        |context| {
+                            context.push_str(" ");
+                            heading.content.to_markdown(context);
+                        },
+                        |_| {},
+                        |context| {
+                            for block in blocks.iter() {
+                                block.to_markdown(context);
+                            }
+                        },
+                    );
+                }
+            },
+        )
+        .newline();
+}
+
+/// Split the content of an [`IncludeBlock`] into the blocks before any tabs, and the tabs
+///
+/// If, after any leading blocks, the content is all `Section`s starting with a heading,
+/// then each section is a tab. Otherwise, each of the top-level headings with the
+/// highest level, and the blocks up until the next one, is a tab.
+fn split_tabs(content: &[Block]) -> (&[Block], Vec<(&Heading, &[Block])>) {
+    fn section_tab(block: &Block) -> Option<(&Heading, &[Block])> {
+        match block {
+            Block::Section(Section { content, .. }) => match content.split_first() {
+                Some((Block::Heading(heading), rest)) => Some((heading, rest)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    if let Some(first) = content
+        .iter()
+        .position(|block| section_tab(block).is_some())
+        && let Some(tabs) = content[first..].iter().map(section_tab).collect()
+    {
+        return (&content[..first], tabs);
+    }
+
+    let Some(level) = content
+        .iter()
+        .filter_map(|block| match block {
+            Block::Heading(heading) => Some(heading.level),
+            _ => None,
+        })
+        .min()
+    else {
+        return (content, Vec::new());
+    };
+
+    let starts = content
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| match block {
+            Block::Heading(heading) if heading.level == level => Some((index, heading)),
+            _ => None,
+        })
+        .collect_vec();
+
+    let tabs = starts
+        .iter()
+        .enumerate()
+        .map(|(nth, (index, heading))| {
+            let end = starts
+                .get(nth + 1)
+                .map_or(content.len(), |(next, ..)| *next);
+            (*heading, &content[index + 1..end])
+        })
+        .collect();
+
+    (&content[..starts[0].0], tabs)
+}
+
+/// The losses when an [`IncludeBlock`] is flattened to its content
+///
+/// Records that the include itself was lost (so that conversion reports show that
+/// it was flattened for the target format) as well as any of its properties that
+/// would otherwise have been encoded.
+fn flattened_losses(include: &IncludeBlock) -> Losses {
+    let mut losses = Losses::one("IncludeBlock");
+    losses.add_prop(include, "source");
+    losses.merge(lost_options!(
+        include,
+        execution_mode,
+        media_type,
+        select,
+        arguments
+    ));
+    losses
+}
+
+impl TextCodec for IncludeBlock {
+    fn to_text(&self) -> String {
+        // As for Markdown formats that do not support include blocks, only
+        // encode the content (if any) with no markup for the include itself
+        self.content.to_text()
+    }
+}
+
+/// Encode an argument of an [`IncludeBlock`] to SMD or MyST
+///
+/// Differs from the encoding of arguments for `CallBlock`s in that expressions
+/// are wrapped in double braces e.g. `--if={{draft}}`. Literal values are always
+/// encoded as JSON5 so that strings containing braces are not treated as expressions.
+/// A warning is logged for expressions with unbalanced braces (e.g. `{{site`) since
+/// these are most likely a typo.
+fn arg_to_markdown(arg: &CallArgument, separator: &str, context: &mut MarkdownEncodeContext) {
+    if let Some(problem) = arg.unbalanced_braces() {
+        context.add_message(
+            arg.node_type(),
+            arg.node_id(),
+            MessageLevel::Warning,
+            format!("Argument `{}` has unbalanced braces ({problem})", arg.name),
+        );
+    }
+
+    context
+        .enter_node(arg.node_type(), arg.node_id())
+        .merge_losses(lost_options!(arg, id, programming_language))
+        .merge_losses(lost_exec_options!(arg))
+        .push_prop_str(NodeProperty::Name, &arg.name)
+        .push_str(separator);
+
+    if arg.is_expression.unwrap_or_default() {
+        context
+            .push_str("{{")
+            .push_prop_fn(NodeProperty::Code, |context| arg.code.to_markdown(context))
+            .push_str("}}");
+    } else {
+        let json5 = match &arg.value {
+            Some(value) => value.to_json5(),
+            None => Node::Null(Null).to_json5(),
+        };
+        context.push_prop_str(NodeProperty::Value, &json5.unwrap_or_default());
+    }
+
+    context.exit_node();
+}
+
+#[cfg(test)]
+mod tests {
+    use stencila_codec_latex_trait::to_latex;
+
+    use crate::{Block, CompilationMessage, Inline, Paragraph, Text};
+
+    use super::*;
+
+    #[test]
+    fn markdown_placeholders() {
+        let encode = |include: &IncludeBlock, include_placeholders: bool| {
+            let mut context = MarkdownEncodeContext::new(Some(Format::Markdown), None);
+            context.include_placeholders = include_placeholders;
+            include.to_markdown(&mut context);
+            context.content
+        };
+
+        let mut failed = IncludeBlock::new("missing.md".into());
+        failed.options.compilation_messages = Some(vec![CompilationMessage::new(
+            MessageLevel::Error,
+            "Unable to read `missing.md`".into(),
+        )]);
+        assert_eq!(encode(&failed, false), "");
+        assert_eq!(encode(&failed, true), "> ⚠ include failed: missing.md\n\n");
+
+        // Includes without content but without an error (e.g. not yet compiled,
+        // or excluded by their condition) are not failures
+        let uncompiled = IncludeBlock::new("part.md".into());
+        assert_eq!(encode(&uncompiled, true), "");
+
+        let excluded = IncludeBlock::new("draft.md".into()).with_code_argument("if", "draft");
+        assert_eq!(encode(&excluded, true), "");
+
+        let included = IncludeBlock {
+            content: Some(vec![Block::Paragraph(Paragraph::new(vec![Inline::Text(
+                Text::from("Part"),
+            )]))]),
+            ..IncludeBlock::new("part.md".into())
+        };
+        assert_eq!(encode(&included, true), "Part\n\n");
+    }
+
+    #[test]
+    fn myst_tabs() {
+        let para =
+            |text: &str| Block::Paragraph(Paragraph::new(vec![Inline::Text(Text::from(text))]));
+        let heading =
+            |text: &str| Block::Heading(Heading::new(2, vec![Inline::Text(Text::from(text))]));
+        let encode = |include: &IncludeBlock| {
+            let mut context = MarkdownEncodeContext::new(Some(Format::Myst), None);
+            include.to_markdown(&mut context);
+            context.content
+        };
+
+        let include = IncludeBlock {
+            content: Some(vec![
+                para("Examples"),
+                heading("Python"),
+                para("py"),
+                heading("R"),
+                para("r"),
+            ]),
+            ..IncludeBlock::new("examples.md".into())
+        };
+        assert!(encode(&include).starts_with("```{include} examples.md"));
+
+        let tabbed = include
+            .clone()
+            .with_argument("myst-tabs", Node::Boolean(true));
+        let markdown = encode(&tabbed);
+        assert!(markdown.starts_with("Examples\n\n:::{tab-set}"));
+        assert!(markdown.contains("{tab-item} Python\n"));
+        assert!(markdown.contains("{tab-item} R\n"));
+        assert!(!markdown.contains("include"));
+
+        // Sections are also split into tabs
+        let sectioned = IncludeBlock {
+            content: Some(vec![
+                Block::Section(Section::new(vec![heading("Python"), para("py")])),
+                Block::Section(Section::new(vec![heading("R"), para("r")])),
+            ]),
+            ..tabbed
+        };
+        assert_eq!(encode(&sectioned).matches("{tab-item}").count(), 2);
+    }
+
+    #[test]
+    fn render_override() {
+        let encode = |include: &IncludeBlock, render: bool| {
+            let mut context = MarkdownEncodeContext::new(Some(Format::Smd), Some(render));
+            include.to_markdown(&mut context);
+            context.content
+        };
+
+        let include = IncludeBlock {
+            content: Some(vec![Block::Paragraph(Paragraph::new(vec![Inline::Text(
+                Text::from("Part"),
+            )]))]),
+            ..IncludeBlock::new("part.md".into())
+        };
+        assert!(encode(&include, false).starts_with("::: include part.md"));
+        assert_eq!(encode(&include, true), "Part\n\n");
+
+        let inlined = include.clone().with_argument("render", Node::Boolean(true));
+        assert_eq!(encode(&inlined, false), "Part\n\n");
+
+        let kept = include.with_argument("render", Node::Boolean(false));
+        assert!(encode(&kept, true).starts_with("::: include part.md"));
+
+        let (latex, ..) = to_latex(&kept, Format::Latex, false, true, false, false);
+        assert_eq!(latex.trim(), r"\input{part.md}");
+    }
+
+    #[test]
+    fn latex_reproducible_markers() {
+        let include = IncludeBlock::new("my dir/part #1 (50%|final).tex".into());
+        let (latex, ..) = to_latex(&include, Format::Latex, false, true, false, true);
+
+        let source = r"my dir/part \#1 (50\%\textbar{}final).tex";
+        assert!(latex.contains(&format!(r"\texttt{{[Begin {source}]}}}}")));
+        assert!(latex.contains(&format!(r"\texttt{{[End {source}]}}}}")));
+        assert!(!latex.contains(r"\verb"));
+    }
+
+    #[test]
+    fn latex_floats() {
+        let include = IncludeBlock {
+            id: Some("inc-results".into()),
+            content: Some(vec![Block::Paragraph(Paragraph::new(vec![Inline::Text(
+                Text::from("Results"),
+            )]))]),
+            ..IncludeBlock::new("results.md".into())
+        };
+
+        let (latex, ..) = to_latex(&include, Format::Latex, false, true, false, false);
+        assert!(!latex.contains(r"\begin{figure}"));
+
+        let include = include
+            .with_argument("latex-float", Node::String("figure".into()))
+            .with_argument("latex-caption", Node::String("Results & discussion".into()));
+        let (latex, ..) = to_latex(&include, Format::Latex, false, true, false, false);
+        assert!(latex.contains(r"\begin{figure}"));
+        assert!(latex.contains(r"\caption{Results \& discussion}"));
+        assert!(latex.contains(r"\label{inc-results}"));
+        assert!(latex.trim_end().ends_with(r"\end{figure}"));
+
+        // Unknown floats are ignored
+        let include = IncludeBlock::new("results.md".into())
+            .with_argument("latex-float", Node::String("box".into()));
+        let (latex, ..) = to_latex(&include, Format::Latex, false, true, false, false);
+        assert!(!latex.contains(r"\begin"));
+    }
+
+    #[test]
+    fn latex_input_extension() {
+        let input = |source: &str| {
+            let (latex, ..) = to_latex(
+                &IncludeBlock::new(source.into()),
+                Format::Latex,
+                false,
+                false,
+                false,
+                false,
+            );
+            latex.trim().to_string()
+        };
+
+        assert_eq!(input("figure.tex"), r"\input{figure}");
+        assert_eq!(input("notes.TEX"), r"\input{notes}");
+        assert_eq!(input("twice.tex.tex"), r"\input{twice.tex}");
+        assert_eq!(input("my.texture"), r"\input{my.texture}");
+        assert_eq!(input(".tex"), r"\input{.tex}");
+        assert_eq!(input("parts/.tex"), r"\input{parts/.tex}");
+        assert_eq!(input("données"), r"\input{données}");
+    }
+}
@@ -4,6 +4,16 @@ use stencila_codec_info::lost_options;
 use crate::{List, ListOrder, prelude::*};
 
 impl List {
+    /// The number of the first item of the list
+    ///
+    /// The `position` of the first item, if any, otherwise 1.
+    fn start(&self) -> i64 {
+        self.items
+            .first()
+            .and_then(|item| item.position)
+            .unwrap_or(1)
+    }
+
     pub fn to_html_special(&self, context: &mut HtmlEncodeContext) -> String {
         let tag = match &self.order {
             ListOrder::Ascending => "ol",
@@ -51,6 +61,14 @@ impl LatexCodec for List {
 
         if !unordered {
             context.str(r"\def\labelenumi{\arabic{enumi}.}").newline();
+
+            // Continue numbering from the position of the first item, if any
+            let start = self.start();
+            if start != 1 {
+                context
+                    .str(&format!(r"\setcounter{{enumi}}{{{}}}", start - 1))
+                    .newline();
+            }
         }
 
         let tight = self.items.iter().all(|item| item.content.len() == 1);
@@ -79,6 +97,7 @@ impl MarkdownCodec for List {
             .merge_losses(lost_options!(self, id, authors, provenance));
 
         let ordered = matches!(self.order, ListOrder::Ascending);
+        let start = self.start();
 
         let tight = self.items.iter().all(|item| item.content.len() == 1);
 
@@ -89,7 +108,9 @@ impl MarkdownCodec for List {
             }
 
             if ordered {
-                context.push_str(&(index + 1).to_string()).push_str(". ")
+                context
+                    .push_str(&(start + index as i64).to_string())
+                    .push_str(". ")
             } else {
                 context.push_str("- ")
             };
@@ -2,6 +2,7 @@
 
 use crate::prelude::*;
 
+use super::boolean::Boolean;
 use super::compilation_digest::CompilationDigest;
 use super::compilation_message::CompilationMessage;
 use super::cord::Cord;
@@ -76,6 +77,13 @@ pub struct CallArgument {
     #[cfg_attr(feature = "proptest", proptest(value = "None"))]
     pub programming_language: Option<String>,
 
+    /// Whether the argument is an expression to be evaluated.
+    #[serde(alias = "is-expression", alias = "is_expression")]
+    #[strip(code)]
+    #[patch(format = "md", format = "smd", format = "myst", format = "ipynb", format = "qmd")]
+    #[cfg_attr(feature = "proptest", proptest(value = "None"))]
+    pub is_expression: Option<Boolean>,
+
     /// Non-core optional fields
     #[serde(flatten)]
     #[html(flatten)]
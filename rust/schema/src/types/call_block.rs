@@ -3,6 +3,7 @@
 use crate::prelude::*;
 
 use super::block::Block;
+use super::boolean::Boolean;
 use super::call_argument::CallArgument;
 use super::compilation_digest::CompilationDigest;
 use super::compilation_message::CompilationMessage;
@@ -197,6 +198,18 @@ pub struct CallBlockOptions {
     #[cfg_attr(feature = "proptest", proptest(value = "None"))]
     #[dom(elem = "span")]
     pub execution_messages: Option<Vec<ExecutionMessage>>,
+
+    /// Whether the source was resolved to a remote URL.
+    #[serde(alias = "is-remote", alias = "is_remote")]
+    #[strip(compilation)]
+    #[cfg_attr(feature = "proptest", proptest(value = "None"))]
+    pub is_remote: Option<Boolean>,
+
+    /// The id of an earlier include with identical content.
+    #[serde(alias = "content-from", alias = "content_from")]
+    #[strip(output)]
+    #[cfg_attr(feature = "proptest", proptest(value = "None"))]
+    pub content_from: Option<String>,
 }
 
 impl CallBlock {
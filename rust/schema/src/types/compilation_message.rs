@@ -3,6 +3,7 @@
 use crate::prelude::*;
 
 use super::code_location::CodeLocation;
+use super::integer::Integer;
 use super::message_level::MessageLevel;
 use super::string::String;
 
@@ -38,6 +39,9 @@ pub struct CompilationMessage {
     #[dom(with = "CodeLocation::to_dom_attr")]
     pub code_location: Option<CodeLocation>,
 
+    /// The include nesting depth at which the message was generated.
+    pub depth: Option<Integer>,
+
     /// A unique identifier for a node within a document
     #[serde(skip)]
     pub uid: NodeUid
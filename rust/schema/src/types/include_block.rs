@@ -3,6 +3,8 @@
 use crate::prelude::*;
 
 use super::block::Block;
+use super::boolean::Boolean;
+use super::call_argument::CallArgument;
 use super::compilation_digest::CompilationDigest;
 use super::compilation_message::CompilationMessage;
 use super::duration::Duration;
@@ -20,7 +22,7 @@ use super::timestamp::Timestamp;
 /// Include block content from an external source (e.g. file, URL).
 #[skip_serializing_none]
 #[serde_as]
-#[derive(Debug, SmartDefault, Clone, PartialEq, Serialize, Deserialize, ProbeNode, StripNode, WalkNode, WriteNode, ReadNode, PatchNode, DomCodec, HtmlCodec, JatsCodec, TextCodec)]
+#[derive(Debug, SmartDefault, Clone, PartialEq, Serialize, Deserialize, ProbeNode, StripNode, WalkNode, WriteNode, ReadNode, PatchNode, DomCodec, HtmlCodec, JatsCodec)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "proptest", derive(Arbitrary))]
 #[derive(derive_more::Display)]
@@ -75,6 +77,15 @@ pub struct IncludeBlock {
     #[dom(elem = "div")]
     pub content: Option<Vec<Block>>,
 
+    /// Arguments to use when including the source.
+    #[serde(alias = "argument")]
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    #[strip(code)]
+    #[patch(format = "md", format = "smd", format = "myst", format = "ipynb", format = "qmd")]
+    #[cfg_attr(feature = "proptest", proptest(value = "None"))]
+    #[dom(elem = "div")]
+    pub arguments: Option<Vec<CallArgument>>,
+
     /// Non-core optional fields
     #[serde(flatten)]
     #[html(flatten)]
@@ -184,6 +195,18 @@ pub struct IncludeBlockOptions {
     #[cfg_attr(feature = "proptest", proptest(value = "None"))]
     #[dom(elem = "span")]
     pub execution_messages: Option<Vec<ExecutionMessage>>,
+
+    /// Whether the source was resolved to a remote URL.
+    #[serde(alias = "is-remote", alias = "is_remote")]
+    #[strip(compilation)]
+    #[cfg_attr(feature = "proptest", proptest(value = "None"))]
+    pub is_remote: Option<Boolean>,
+
+    /// The id of an earlier include with identical content.
+    #[serde(alias = "content-from", alias = "content_from")]
+    #[strip(output)]
+    #[cfg_attr(feature = "proptest", proptest(value = "None"))]
+    pub content_from: Option<String>,
 }
 
 impl IncludeBlock {
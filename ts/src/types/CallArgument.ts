@@ -26,6 +26,11 @@ export class CallArgument extends Parameter {
    */
   programmingLanguage?: string;
 
+  /**
+   * Whether the argument is an expression to be evaluated.
+   */
+  isExpression?: boolean;
+
   constructor(name: string, code: Cord, options?: Partial<CallArgument>) {
     super(name);
     this.type = "CallArgument";
@@ -13,7 +13,7 @@ export class CallBlock extends IncludeBlock {
   /**
    * The value of the source document's parameters to call it with
    */
-  arguments: CallArgument[];
+  declare arguments: CallArgument[];
 
   constructor(source: string, args: CallArgument[], options?: Partial<CallBlock>) {
     super(source);
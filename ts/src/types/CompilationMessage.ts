@@ -2,6 +2,7 @@
 
 import { CodeLocation } from "./CodeLocation.js";
 import { Entity } from "./Entity.js";
+import { Integer } from "./Integer.js";
 import { MessageLevel } from "./MessageLevel.js";
 
 /**
@@ -31,6 +32,11 @@ export class CompilationMessage extends Entity {
    */
   codeLocation?: CodeLocation;
 
+  /**
+   * The include nesting depth at which the message was generated.
+   */
+  depth?: Integer;
+
   constructor(level: MessageLevel, message: string, options?: Partial<CompilationMessage>) {
     super();
     this.type = "CompilationMessage";
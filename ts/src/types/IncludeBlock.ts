@@ -1,6 +1,7 @@
 // Generated file; do not edit. See https://github.com/stencila/stencila/tree/main/rust/schema-gen
 
 import { Block } from "./Block.js";
+import { CallArgument } from "./CallArgument.js";
 import { Executable } from "./Executable.js";
 
 /**
@@ -30,6 +31,21 @@ export class IncludeBlock extends Executable {
    */
   content?: Block[];
 
+  /**
+   * Arguments to use when including the source.
+   */
+  arguments?: CallArgument[];
+
+  /**
+   * Whether the source was resolved to a remote URL.
+   */
+  isRemote?: boolean;
+
+  /**
+   * The id of an earlier include with identical content.
+   */
+  contentFrom?: string;
+
   constructor(source: string, options?: Partial<IncludeBlock>) {
     super();
     this.type = "IncludeBlock";
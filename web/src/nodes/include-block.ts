@@ -28,6 +28,17 @@ export class IncludeBlock extends Executable {
   @property()
   source: string
 
+  /**
+   * The id of an earlier include with identical content
+   *
+   * When includes are deduplicated, the content of this include is not
+   * repeated in the HTML and is instead cloned from that include.
+   *
+   * @see this.cloneContent()
+   */
+  @property({ attribute: 'content-from' })
+  contentFrom?: string
+
   /**
    * Whether the block has any content
    *
@@ -104,6 +115,38 @@ export class IncludeBlock extends Executable {
     })
   }
 
+  override connectedCallback(): void {
+    super.connectedCallback()
+
+    if (this.contentFrom) {
+      this.cloneContent()
+    }
+  }
+
+  /**
+   * Clone the content of the include with the id `contentFrom` into this include
+   *
+   * Ids are removed from the cloned elements so that patches to the
+   * original include's content are not applied to the clone.
+   */
+  private cloneContent() {
+    if (this.querySelector(':scope > [slot="content"]')) {
+      return
+    }
+
+    const content = document
+      .getElementById(this.contentFrom)
+      ?.querySelector(':scope > [slot="content"]')
+    if (!content) {
+      return
+    }
+
+    const clone = content.cloneNode(true) as HTMLElement
+    clone.removeAttribute('id')
+    clone.querySelectorAll('[id]').forEach((elem) => elem.removeAttribute('id'))
+    this.appendChild(clone)
+  }
+
   override render() {
     if (this.isWithin('StyledBlock') || this.isWithinUserChatMessage()) {
       return this.renderContent()